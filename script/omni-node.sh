@@ -1,2 +1,3 @@
 # ./nodes/polkadot-omni-node --chain ./chain-specs/polkadot-asset-hub.json -- --chain ./chain-specs/westend.json
-./binaries/polkadot-omni-node --chain ./chain-specs/chain_spec.json --dev
\ No newline at end of file
+# The binary, chain spec and node flags come from the dot.toml of the project, see `dot config show`
+exec "${OMNI_NODE:-./binaries/polkadot-omni-node}" --chain "${CHAIN_SPEC:-./chain-specs/chain_spec.json}" "$@"
@@ -0,0 +1,225 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::install;
+use crate::output;
+
+/// Handlebars template rendered by frame-omni-bencher into `weights.rs`.
+const WEIGHT_TEMPLATE: &str = include_str!("../script/frame-weight-template.hbs");
+
+/// Base weight of one extrinsic as found in a `weights.rs` file.
+#[derive(Debug, PartialEq)]
+pub struct ExtrinsicWeight {
+    pub name: String,
+    pub ref_time: u64,
+    pub proof_size: u64,
+    pub reads: u64,
+    pub writes: u64,
+}
+
+pub struct BenchOptions<'a> {
+    pub runtime: &'a Path,
+    pub pallet: Option<&'a str>,
+    pub extrinsic: Option<&'a str>,
+    pub steps: &'a str,
+    pub repeat: &'a str,
+    /// Project searched for the pallet crates.
+    pub project: &'a Path,
+    /// Explicit weights file, only valid for a single pallet.
+    pub output: Option<&'a Path>,
+}
+
+/// Benchmarks one or all pallets of a runtime, writes their `weights.rs` into the pallet crates
+/// of the project and compares the new weights with the previous ones.
+pub fn run(options: &BenchOptions) -> Result<(), Box<dyn Error>> {
+    // The weights file is rendered from the benchmarked extrinsics only, so it would drop all the others.
+    if options.extrinsic.is_some() && options.output.is_none() {
+        return Err("--extrinsic would overwrite the weights.rs of the pallet with a single extrinsic, pass --weights <FILE> to write them elsewhere".into());
+    }
+    if !install::binary_path(install::OMNI_BENCHER).exists() {
+        install::install_omni_bencher()?;
+    }
+    if !options.runtime.exists() {
+        return Err(format!("Runtime not found: {}", options.runtime.display()).into());
+    }
+
+    let pallets = match options.pallet {
+        Some(pallet) => vec![pallet.to_string()],
+        None => list_pallets(options.runtime)?,
+    };
+    if options.output.is_some() && pallets.len() != 1 {
+        return Err("--weights can only be used when benchmarking a single pallet".into());
+    }
+
+    let template = env::temp_dir().join("dot-frame-weight-template.hbs");
+    fs::write(&template, WEIGHT_TEMPLATE)?;
+
+    let mut failed = Vec::new();
+    for pallet in &pallets {
+        let output = match options.output {
+            Some(output) => output.to_path_buf(),
+            None => match find_pallet_crate(options.project, pallet) {
+                Some(crate_dir) => crate_dir.join("src").join("weights.rs"),
+                None => {
+                    output::progress(format_args!("Skipping {}: no pallet crate for it in {}", pallet, options.project.display()));
+                    continue;
+                }
+            },
+        };
+
+        let previous = fs::read_to_string(&output).map(|source| parse_weights(&source)).unwrap_or_default();
+        output::progress(format_args!("\nBenchmarking {} -> {}", pallet, output.display()));
+        let status = Command::new(install::binary_path(install::OMNI_BENCHER))
+            .args(["v1", "benchmark", "pallet", "--runtime"])
+            .arg(options.runtime)
+            .args(["--pallet", pallet, "--extrinsic", options.extrinsic.unwrap_or("*")])
+            .args(["--steps", options.steps, "--repeat", options.repeat])
+            .arg("--template")
+            .arg(&template)
+            .arg("--output")
+            .arg(&output)
+            .status()
+            .map_err(|e| format!("Failed to run frame-omni-bencher: {}", e))?;
+        if !status.success() {
+            eprintln!("Benchmarks of {} failed", pallet);
+            failed.push(pallet.clone());
+            continue;
+        }
+
+        let current = parse_weights(&fs::read_to_string(&output)?);
+        print_comparison(pallet, &previous, &current);
+    }
+
+    if !failed.is_empty() {
+        return Err(format!("Benchmarks failed for: {}", failed.join(", ")).into());
+    }
+    Ok(())
+}
+
+/// Pallets with benchmarks in the runtime, from `frame-omni-bencher ... --list`.
+fn list_pallets(runtime: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let output = Command::new(install::binary_path(install::OMNI_BENCHER))
+        .args(["v1", "benchmark", "pallet", "--runtime"])
+        .arg(runtime)
+        .arg("--list")
+        .output()
+        .map_err(|e| format!("Failed to run frame-omni-bencher: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Failed to list benchmarks: {}", String::from_utf8_lossy(&output.stderr)).into());
+    }
+
+    let mut pallets: Vec<String> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines().skip(1) {
+        let pallet = line.split(',').next().unwrap_or_default().trim().to_string();
+        if !pallet.is_empty() && !pallets.contains(&pallet) {
+            pallets.push(pallet);
+        }
+    }
+    Ok(pallets)
+}
+
+/// Finds the crate of a pallet such as `pallet_template` (crate `pallet-template`) inside the project.
+pub fn find_pallet_crate(project: &Path, pallet: &str) -> Option<PathBuf> {
+    let package_line = format!("name = \"{}\"", pallet.replace('_', "-"));
+    let entries = fs::read_dir(project).ok()?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        if name == "target" || name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if let Some(found) = find_pallet_crate(&path, pallet) {
+                return Some(found);
+            }
+        } else if name == "Cargo.toml" {
+            let manifest = fs::read_to_string(&path).unwrap_or_default();
+            if manifest.lines().any(|line| line.trim() == package_line) {
+                return Some(project.to_path_buf());
+            }
+        }
+    }
+    None
+}
+
+/// Extracts the base weights of the `SubstrateWeight<T>` implementation of a weights file.
+pub fn parse_weights(source: &str) -> Vec<ExtrinsicWeight> {
+    let start = source.find("for SubstrateWeight<T>").unwrap_or(0);
+    let end = source.find("impl WeightInfo for ()").filter(|end| *end > start).unwrap_or(source.len());
+
+    let mut weights: Vec<ExtrinsicWeight> = Vec::new();
+    for line in source[start..end].lines().map(str::trim) {
+        if let Some(signature) = line.strip_prefix("fn ") {
+            let name = signature.split('(').next().unwrap_or_default().trim();
+            weights.push(ExtrinsicWeight { name: name.to_string(), ref_time: 0, proof_size: 0, reads: 0, writes: 0 });
+            continue;
+        }
+        let weight = match weights.last_mut() {
+            Some(weight) => weight,
+            None => continue,
+        };
+        if let Some(parts) = line.strip_prefix("Weight::from_parts(") {
+            let mut numbers = parts.trim_end_matches(')').split(',').map(parse_number);
+            weight.ref_time = numbers.next().unwrap_or_default();
+            weight.proof_size = numbers.next().unwrap_or_default();
+        } else if let Some(reads) = base_db_access(line, "reads") {
+            weight.reads = reads;
+        } else if let Some(writes) = base_db_access(line, "writes") {
+            weight.writes = writes;
+        }
+    }
+    weights
+}
+
+/// Parses `.saturating_add(T::DbWeight::get().reads(2_u64))`, ignoring per-component accesses.
+fn base_db_access(line: &str, kind: &str) -> Option<u64> {
+    let start = line.find(&format!(".{}(", kind))? + kind.len() + 2;
+    let argument = &line[start..];
+    if argument.starts_with('(') {
+        return None;
+    }
+    Some(parse_number(argument.split(')').next()?))
+}
+
+fn parse_number(value: &str) -> u64 {
+    value.trim().trim_end_matches("_u64").replace('_', "").parse().unwrap_or_default()
+}
+
+fn print_comparison(pallet: &str, previous: &[ExtrinsicWeight], current: &[ExtrinsicWeight]) {
+    println!("\nWeights of {}:", pallet);
+    println!("  {:<32} {:<36} {:<24} READS/WRITES", "EXTRINSIC", "REF_TIME (ps)", "PROOF_SIZE (bytes)");
+    for weight in current {
+        match previous.iter().find(|old| old.name == weight.name) {
+            Some(old) => println!(
+                "  {:<32} {:<36} {:<24} {}/{} -> {}/{}",
+                weight.name,
+                format_change(old.ref_time, weight.ref_time),
+                format_change(old.proof_size, weight.proof_size),
+                old.reads,
+                old.writes,
+                weight.reads,
+                weight.writes
+            ),
+            None => println!(
+                "  {:<32} {:<36} {:<24} {}/{} (new)",
+                weight.name, weight.ref_time, weight.proof_size, weight.reads, weight.writes
+            ),
+        }
+    }
+    for old in previous.iter().filter(|old| !current.iter().any(|weight| weight.name == old.name)) {
+        println!("  {:<32} (removed)", old.name);
+    }
+}
+
+fn format_change(old: u64, new: u64) -> String {
+    if old == new {
+        return new.to_string();
+    }
+    if old == 0 {
+        return format!("{} -> {}", old, new);
+    }
+    let change = (new as f64 - old as f64) / old as f64 * 100.0;
+    format!("{} -> {} ({:+.1}%)", old, new, change)
+}
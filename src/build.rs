@@ -0,0 +1,347 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use serde_json::{json, Value};
+use crate::config;
+use crate::hashing;
+use crate::output;
+use crate::serve;
+
+/// Directory of `paths.cache` holding the build history of each project, one JSON object per line.
+/// Kept out of the project so it never ends up in its git repository.
+const HISTORY_DIR: &str = "build-history";
+
+const SLOWEST_CRATES: usize = 5;
+
+/// A build taking this much longer than the previous one with the same profile is reported as a regression.
+const REGRESSION_THRESHOLD: f64 = 1.2;
+
+/// Tracks the crates compiled so far from cargo's `--message-format json` output.
+pub struct BuildProgress {
+    compiled: HashSet<String>,
+    total: usize,
+}
+
+impl BuildProgress {
+    pub fn new(total: usize) -> Self {
+        BuildProgress { compiled: HashSet::new(), total }
+    }
+
+    pub fn compiled(&self) -> usize {
+        self.compiled.len()
+    }
+
+    /// Handles a cargo message and returns a rendered warning or error to print, if any.
+    pub fn update(&mut self, message: &Value) -> Option<String> {
+        match message["reason"].as_str() {
+            Some("compiler-artifact") => {
+                if let Some(package) = message["package_id"].as_str() {
+                    self.compiled.insert(package.to_string());
+                }
+                None
+            }
+            Some("compiler-message") => match message["message"]["level"].as_str() {
+                Some("error") | Some("warning") => message["message"]["rendered"].as_str().map(String::from),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn status_line(&self, current: &str) -> String {
+        if self.total == 0 {
+            return format!("[{}] {}", self.compiled(), current);
+        }
+        let done = self.compiled().min(self.total);
+        let filled = done * 30 / self.total;
+        format!("[{}{}] {}/{} {}", "=".repeat(filled), " ".repeat(30 - filled), done, self.total, current)
+    }
+}
+
+/// Builds the project at `project` with a cargo profile and optional features, showing the
+/// progress, then reports the slowest crates and the runtime size and records the build time.
+pub fn build(project: &Path, profile: &str, features: Option<&str>) -> Result<(), Box<dyn Error>> {
+    output::progress(format_args!(
+        "Building {} with the {} profile{}",
+        project.display(),
+        profile,
+        features.map(|f| format!(" and features {}", f)).unwrap_or_default()
+    ));
+
+    let started = Instant::now();
+    let mut progress = BuildProgress::new(dependency_count(project).unwrap_or(0));
+    let mut command = Command::new("cargo");
+    command
+        .args(["build", "--profile", profile, "--message-format", "json-diagnostic-rendered-ansi", "--timings"])
+        .current_dir(project)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(features) = features {
+        command.args(["--features", features]);
+    }
+    output::command(&command);
+    let mut child = command.spawn().map_err(|e| format!("Failed to run cargo: {}", e))?;
+
+    // cargo's own status lines are only shown when the build fails
+    let stderr = child.stderr.take().unwrap();
+    let stderr = thread::spawn(move || BufReader::new(stderr).lines().map_while(Result::ok).collect::<Vec<String>>());
+
+    for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+        let message: Value = match serde_json::from_str(&line?) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        if let Some(diagnostic) = progress.update(&message) {
+            println!("\r\x1b[K{}", diagnostic);
+        }
+        if let Some(name) = message["target"]["name"].as_str() {
+            output::progress_line(progress.status_line(name))?;
+        }
+    }
+    let success = child.wait()?.success();
+    let seconds = started.elapsed().as_secs_f64();
+    output::progress("\r\x1b[K");
+
+    if !success {
+        for line in stderr.join().unwrap_or_default().iter().filter(|line| !line.trim_start().starts_with("Compiling")) {
+            eprintln!("{}", line);
+        }
+        record_build(project, json!({ "profile": profile, "features": features, "seconds": seconds, "success": false }))?;
+        return Err(format!("Build failed after {}", format_duration(seconds)).into());
+    }
+
+    let metadata = cargo_metadata(project)?;
+    let target_dir = PathBuf::from(metadata["target_directory"].as_str().unwrap_or("target"));
+    println!("✓ Built {} crates in {}", progress.compiled(), format_duration(seconds));
+    if let Ok(binary) = node_binary(&metadata, profile) {
+        println!("  Node:    {}", binary.display());
+    }
+    let wasm = serve::newest_wasm(&target_dir.join(profile_dir(profile)).join("wbuild"));
+    let wasm_size = wasm.as_ref().and_then(|wasm| fs::metadata(wasm).ok()).map(|meta| meta.len());
+    if let (Some(wasm), Some(size)) = (&wasm, wasm_size) {
+        println!("  Runtime: {} ({} KiB)", wasm.display(), size / 1024);
+    }
+
+    let timings = fs::read_to_string(target_dir.join("cargo-timings").join("cargo-timing.html")).unwrap_or_default();
+    let units = parse_unit_durations(&timings);
+    if !units.is_empty() {
+        println!("\nSlowest crates:");
+        for (unit, duration) in units.iter().take(SLOWEST_CRATES) {
+            println!("  {:>8}  {}", format_duration(*duration), unit);
+        }
+    }
+
+    let previous = read_history(project)
+        .into_iter()
+        .rev()
+        .find(|record| record["success"] == true && record["profile"] == profile && record["features"] == json!(features));
+    // Builds of a few seconds are only noise
+    if let Some(previous_seconds) = previous.and_then(|record| record["seconds"].as_f64()).filter(|previous| *previous >= 10.0) {
+        let change = format!("{} than the previous {} build ({})", if seconds > previous_seconds { "slower" } else { "faster" }, profile, format_duration(previous_seconds));
+        if seconds > previous_seconds * REGRESSION_THRESHOLD {
+            println!("\n⚠ {:.0}% {}", (seconds / previous_seconds - 1.0) * 100.0, change);
+        } else {
+            println!("\n{:.0}% {}", ((seconds / previous_seconds - 1.0) * 100.0).abs(), change);
+        }
+    }
+
+    record_build(
+        project,
+        json!({ "profile": profile, "features": features, "seconds": seconds, "success": true, "crates": progress.compiled(), "wasm_size": wasm_size }),
+    )
+}
+
+/// Number of packages built for the host in the dependency graph of the project, used as the progress total.
+fn dependency_count(project: &Path) -> Option<usize> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--filter-platform", &host_triple()?])
+        .current_dir(project)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let metadata: Value = serde_json::from_slice(&output.stdout).ok()?;
+    metadata["resolve"]["nodes"].as_array().map(Vec::len)
+}
+
+/// Target triple of the host, e.g. `x86_64-unknown-linux-gnu`, from `rustc -vV`.
+fn host_triple() -> Option<String> {
+    let output = Command::new("rustc").arg("-vV").output().ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(String::from)
+}
+
+/// Compilation time of each unit, slowest first, from the `UNIT_DATA` of cargo's `--timings` report.
+pub fn parse_unit_durations(html: &str) -> Vec<(String, f64)> {
+    let start = match html.find("const UNIT_DATA = ") {
+        Some(start) => start + "const UNIT_DATA = ".len(),
+        None => return Vec::new(),
+    };
+    let units = match serde_json::Deserializer::from_str(&html[start..]).into_iter::<Value>().next() {
+        Some(Ok(Value::Array(units))) => units,
+        _ => return Vec::new(),
+    };
+
+    let mut durations: Vec<(String, f64)> = units
+        .iter()
+        .filter_map(|unit| {
+            let name = format!(
+                "{} v{}{}",
+                unit["name"].as_str()?,
+                unit["version"].as_str().unwrap_or_default(),
+                unit["target"].as_str().unwrap_or_default()
+            );
+            Some((name, unit["duration"].as_f64()?))
+        })
+        .collect();
+    durations.sort_by(|a, b| b.1.total_cmp(&a.1));
+    durations
+}
+
+fn record_build(project: &Path, mut record: Value) -> Result<(), Box<dyn Error>> {
+    record["timestamp"] = json!(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+    let path = history_path(project);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", record)?;
+    Ok(())
+}
+
+/// `<paths.cache>/build-history/<project>-<hash of its path>.jsonl`
+fn history_path(project: &Path) -> PathBuf {
+    let project = project.canonicalize().unwrap_or_else(|_| project.to_path_buf());
+    let name = project.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let hash = hex::encode(hashing::twox_64(project.display().to_string().as_bytes()));
+    config::current().path("paths.cache").join(HISTORY_DIR).join(format!("{}-{}.jsonl", name, hash))
+}
+
+fn read_history(project: &Path) -> Vec<Value> {
+    fs::read_to_string(history_path(project))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Prints the recorded builds of a project, oldest first.
+pub fn print_history(project: &Path, json: bool) -> Result<(), Box<dyn Error>> {
+    let history = read_history(project);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&history)?);
+        return Ok(());
+    }
+    if history.is_empty() {
+        println!("No builds recorded yet for {}, run `dot build` first", project.display());
+        return Ok(());
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    println!("{:<12} {:<12} {:<24} {:>10} {:>8} {:>12}", "WHEN", "PROFILE", "FEATURES", "DURATION", "CRATES", "RUNTIME");
+    for record in history {
+        println!(
+            "{:<12} {:<12} {:<24} {:>10} {:>8} {:>12}",
+            format_age(now.saturating_sub(record["timestamp"].as_u64().unwrap_or_default())),
+            record["profile"].as_str().unwrap_or_default(),
+            record["features"].as_str().unwrap_or("-"),
+            if record["success"] == true { format_duration(record["seconds"].as_f64().unwrap_or_default()) } else { "failed".to_string() },
+            record["crates"].as_u64().map(|crates| crates.to_string()).unwrap_or_default(),
+            record["wasm_size"].as_u64().map(|size| format!("{} KiB", size / 1024)).unwrap_or_default(),
+        );
+    }
+    Ok(())
+}
+
+pub fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+/// Starts the node binary built by `dot build` with `--dev` and the extra arguments, without cargo.
+pub fn run(project: &Path, profile: &str, args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let metadata = cargo_metadata(project)?;
+    let binary = node_binary(&metadata, profile)?;
+    if !binary.exists() {
+        return Err(format!("{} not found, run `dot build --profile {}` first", binary.display(), profile).into());
+    }
+
+    println!("Running {}...", binary.display());
+    let status = Command::new(&binary)
+        .arg("--dev")
+        .args(args)
+        .current_dir(project)
+        .status()
+        .map_err(|e| format!("Failed to run {}: {}", binary.display(), e))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", binary.display(), status).into());
+    }
+    Ok(())
+}
+
+pub fn cargo_metadata(project: &Path) -> Result<Value, Box<dyn Error>> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(project)
+        .output()
+        .map_err(|e| format!("Failed to run cargo metadata: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("cargo metadata failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Directory of `target/` cargo writes a profile's artifacts to.
+pub fn profile_dir(profile: &str) -> &str {
+    match profile {
+        "dev" | "test" => "debug",
+        "bench" => "release",
+        profile => profile,
+    }
+}
+
+/// Path of the node binary of the workspace for a profile. The binary of a `*-node` package is
+/// preferred when the workspace has several.
+pub fn node_binary(metadata: &Value, profile: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let target_dir = metadata["target_directory"].as_str().ok_or("cargo metadata has no target_directory")?;
+    let mut binaries: Vec<(&str, &str)> = Vec::new();
+    for package in metadata["packages"].as_array().into_iter().flatten() {
+        let package_name = package["name"].as_str().unwrap_or_default();
+        for target in package["targets"].as_array().into_iter().flatten() {
+            let is_bin = target["kind"].as_array().is_some_and(|kinds| kinds.iter().any(|kind| kind == "bin"));
+            if let (true, Some(name)) = (is_bin, target["name"].as_str()) {
+                binaries.push((package_name, name));
+            }
+        }
+    }
+
+    let binary = match binaries.as_slice() {
+        [] => return Err("The project has no binary to run".into()),
+        [(_, name)] => *name,
+        _ => binaries
+            .iter()
+            .find(|(package, _)| package.ends_with("-node"))
+            .map(|(_, name)| *name)
+            .ok_or("The project has several binaries and none of them in a *-node package")?,
+    };
+    Ok(Path::new(target_dir).join(profile_dir(profile)).join(binary))
+}
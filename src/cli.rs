@@ -0,0 +1,626 @@
+use std::path::PathBuf;
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use toml::Value;
+use crate::config::{self, Origin};
+use crate::keystore;
+use crate::rpc;
+use crate::seal::SealMode;
+use crate::template;
+
+/// CLI tool for Polkadot
+#[derive(Debug, Parser)]
+#[clap(name = "dot", version)]
+pub struct Cli {
+    /// Profile of the config files to use, e.g. ci for the [profile.ci] tables [env: DOT_PROFILE]
+    #[clap(long, global = true, value_name = "NAME")]
+    pub config_profile: Option<String>,
+
+    /// Project config to use instead of the dot.toml found in the current directory or its parents
+    #[clap(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Output format of the commands that print data [default: output.format of the config, text]
+    #[clap(long, global = true, value_enum, value_name = "FORMAT")]
+    pub output: Option<OutputFormat>,
+
+    /// Print the commands run by dot, e.g. wget and cargo
+    #[clap(short, long, global = true, conflicts_with = "quiet")]
+    pub verbose: bool,
+
+    /// Only print results and errors, without progress messages
+    #[clap(short, long, global = true)]
+    pub quiet: bool,
+
+    #[clap(subcommand)]
+    pub command: Commands,
+}
+
+impl Cli {
+    /// How the config is loaded: the profile and file to use and the settings given as flags.
+    pub fn config_options(&self) -> config::Options {
+        let mut flags = Vec::new();
+        if let Some(format) = self.output {
+            flags.push(flag("output.format", format.name(), "--output"));
+        }
+        if self.verbose {
+            flags.push(flag("output.verbosity", "verbose", "--verbose"));
+        }
+        if self.quiet {
+            flags.push(flag("output.verbosity", "quiet", "--quiet"));
+        }
+        config::Options {
+            profile: self.config_profile.clone(),
+            // `config set` may create the profile
+            allow_new: matches!(self.command, Commands::Config(ConfigCommand::Set { .. })),
+            file: self.config.clone(),
+            flags,
+        }
+    }
+}
+
+fn flag(key: &str, value: &str, name: &str) -> config::Entry {
+    (key.to_string(), Value::String(value.to_string()), Origin::Flag(name.to_string()))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+        }
+    }
+}
+
+/// Cargo profiles of the template projects.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum CargoProfile {
+    Dev,
+    Release,
+    Production,
+}
+
+impl CargoProfile {
+    pub fn name(self) -> &'static str {
+        match self {
+            CargoProfile::Dev => "dev",
+            CargoProfile::Release => "release",
+            CargoProfile::Production => "production",
+        }
+    }
+}
+
+/// Runtimes of the Westend system parachains, published with the polkadot-sdk releases.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Runtime {
+    #[clap(name = "asset-hub-westend")]
+    AssetHub,
+    #[clap(name = "bridge-hub-westend")]
+    BridgeHub,
+    #[clap(name = "collectives-westend")]
+    Collectives,
+    #[clap(name = "coretime-westend")]
+    Coretime,
+    #[clap(name = "people-westend")]
+    People,
+}
+
+impl Runtime {
+    /// Name of the wasm release asset, like `chain.runtime`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Runtime::AssetHub => "asset_hub_westend_runtime",
+            Runtime::BridgeHub => "bridge_hub_westend_runtime",
+            Runtime::Collectives => "collectives_westend_runtime",
+            Runtime::Coretime => "coretime_westend_runtime",
+            Runtime::People => "people_westend_runtime",
+        }
+    }
+}
+
+/// Checks `--template` against the built-in templates and those of the user config.
+fn template_name(name: &str) -> Result<String, String> {
+    let names = template::names().map_err(|e| e.to_string())?;
+    if !names.iter().any(|known| known == name) {
+        return Err(format!("available templates: {}", names.join(", ")));
+    }
+    Ok(name.to_string())
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Install the polkadot-sdk binaries and chain spec, or install and run a template
+    Install {
+        /// Template to install and run, see `dot template list`
+        #[clap(long, value_parser = template_name)]
+        template: Option<String>,
+        /// Tag, branch or commit of the template to clone [default: the template's ref or default branch]
+        #[clap(long = "ref", requires = "template", value_name = "REF")]
+        git_ref: Option<String>,
+        /// Build only the runtime of the template and run it with polkadot-omni-node
+        #[clap(long, requires = "template")]
+        omni: bool,
+        /// Extra arguments passed to the node of the template
+        #[clap(last = true, requires = "template")]
+        args: Vec<String>,
+    },
+    /// Serve polkadot-omni-node with the chain spec of the config, or with a runtime crate
+    Serve {
+        /// Build the runtime crate at this path and serve its wasm
+        #[clap(long)]
+        runtime_crate: Option<PathBuf>,
+        /// Rebuild the runtime and restart the node when its sources change
+        #[clap(long, requires = "runtime-crate")]
+        watch: bool,
+        /// Block production: instant (one block per extrinsic), manual (on `dot seal`) or interval=<ms>
+        #[clap(long, value_parser = SealMode::parse)]
+        seal: Option<SealMode>,
+        /// Extra arguments passed to the node
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+    /// Generate chain specs for polkadot-omni-node
+    #[clap(subcommand)]
+    ChainSpec(ChainSpecCommand),
+    /// List and add the networks that --rpc takes by name
+    #[clap(subcommand)]
+    Network(NetworkCommand),
+    /// Show the pallets, calls, storage items and events of a runtime
+    Metadata(MetadataArgs),
+    /// Sign and submit an extrinsic to a running node
+    Tx {
+        /// Name of the pallet, e.g. Balances
+        pallet: String,
+        /// Name of the call, e.g. transfer_keep_alive
+        call: String,
+        /// Call arguments as JSON values or plain strings
+        args: Vec<String>,
+        /// Secret URI of the signing key [default: keys.signer of the config, //Alice]
+        #[clap(long)]
+        signer: Option<String>,
+        /// RPC url of the node or a network name from the config
+        #[clap(long, default_value = rpc::DEFAULT_RPC_URL)]
+        rpc: String,
+        /// Wait until the extrinsic is in a block or finalized
+        #[clap(long, default_value = "in-block", value_parser = ["in-block", "finalized"])]
+        wait: String,
+    },
+    /// Read a storage item from a running node
+    Query {
+        /// Name of the pallet, e.g. System
+        pallet: String,
+        /// Name of the storage item, e.g. Account
+        item: String,
+        /// Map keys as JSON values or plain strings; omit keys to list all entries
+        keys: Vec<String>,
+        /// Block hash or number to query at (defaults to the best block)
+        #[clap(long)]
+        at: Option<String>,
+        /// RPC url of the node or a network name from the config
+        #[clap(long, default_value = rpc::DEFAULT_RPC_URL)]
+        rpc: String,
+    },
+    /// Stream blocks or events from a running node
+    Watch {
+        /// What to watch
+        #[clap(default_value = "blocks", value_parser = ["blocks", "events", "finalized"])]
+        mode: String,
+        /// Only show events of this pallet
+        #[clap(long)]
+        pallet: Option<String>,
+        /// RPC url of the node or a network name from the config
+        #[clap(long, default_value = rpc::DEFAULT_RPC_URL)]
+        rpc: String,
+    },
+    /// Generate, inspect and store keys, or insert them into a running node
+    Key(KeyArgs),
+    /// Set up a collator on a running node
+    #[clap(subcommand)]
+    Collator(CollatorCommand),
+    /// Manage parachains on a local relay chain
+    #[clap(subcommand)]
+    Para(ParaCommand),
+    /// Inspect and upgrade runtimes
+    #[clap(subcommand)]
+    Runtime(RuntimeCommand),
+    /// Test runtime upgrades and migrations against a snapshot of a chain
+    #[clap(subcommand)]
+    TryRuntime(TryRuntimeCommand),
+    /// Run pallet benchmarks and generate weights.rs files
+    Bench {
+        /// Runtime wasm built with the runtime-benchmarks feature
+        #[clap(long)]
+        runtime: PathBuf,
+        /// Pallet to benchmark, e.g. pallet_template [default: all pallets]
+        #[clap(long)]
+        pallet: Option<String>,
+        /// Extrinsic to benchmark, requires --weights [default: all extrinsics]
+        #[clap(long)]
+        extrinsic: Option<String>,
+        /// Number of steps across component ranges
+        #[clap(long, default_value_t = 50)]
+        steps: u32,
+        /// Number of repetitions of each step
+        #[clap(long, default_value_t = 20)]
+        repeat: u32,
+        /// Project containing the pallet crates
+        #[clap(long, default_value = ".")]
+        path: PathBuf,
+        /// Write the weights to this file instead of <pallet crate>/src/weights.rs
+        #[clap(long, requires = "pallet", value_name = "FILE")]
+        weights: Option<PathBuf>,
+    },
+    /// Run a local dev node on the state of a live chain, saved in a JSON snapshot file
+    #[clap(group(ArgGroup::new("snapshot").args(&["from-snapshot", "save-snapshot"]).required(true)))]
+    Fork {
+        /// Serve a dev chain whose genesis is the state saved in this .json snapshot
+        #[clap(long)]
+        from_snapshot: Option<PathBuf>,
+        /// Save the state of the chain at --rpc into this .json snapshot file
+        #[clap(long)]
+        save_snapshot: Option<PathBuf>,
+        /// WebSocket RPC endpoint or network name of the chain to save
+        #[clap(long, default_value = rpc::DEFAULT_RPC_URL)]
+        rpc: String,
+        /// Block hash or number to save [default: best block]
+        #[clap(long, requires = "save-snapshot")]
+        at: Option<String>,
+        /// Extra arguments passed to the node
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+    /// Produce blocks on a node served with --seal manual or instant
+    Seal {
+        /// Number of blocks to produce
+        #[clap(long, default_value_t = 1)]
+        count: u32,
+        /// Finalize the new blocks
+        #[clap(long)]
+        finalize: bool,
+        /// WebSocket RPC endpoint or network name of the node
+        #[clap(long, default_value = rpc::DEFAULT_RPC_URL)]
+        rpc: String,
+    },
+    /// Manage the templates available to install and new
+    #[clap(subcommand)]
+    Template(TemplateCommand),
+    /// Create a new project from a template, without building it
+    New {
+        /// Name of the project, used for its directory and crate names
+        name: String,
+        /// Template to start from, see `dot template list`
+        #[clap(long, default_value = "minimal", value_parser = template_name)]
+        template: String,
+        /// Tag, branch or commit of the template to clone [default: the template's ref or default branch]
+        #[clap(long = "ref", value_name = "REF")]
+        git_ref: Option<String>,
+        /// Directory in which the project directory is created
+        #[clap(long, default_value = ".")]
+        path: PathBuf,
+    },
+    /// Build the node of a project with a cargo profile
+    Build {
+        /// Cargo profile to build with
+        #[clap(long, value_enum, default_value_t = CargoProfile::Release)]
+        profile: CargoProfile,
+        /// Comma separated cargo features, e.g. runtime-benchmarks
+        #[clap(long)]
+        features: Option<String>,
+        /// Project to build
+        #[clap(long, default_value = ".")]
+        path: PathBuf,
+        /// Show the recorded build times of the project instead of building
+        #[clap(long)]
+        history: bool,
+    },
+    /// Run the node binary built with `dot build` in dev mode
+    Run {
+        /// Cargo profile the node was built with
+        #[clap(long, value_enum, default_value_t = CargoProfile::Release)]
+        profile: CargoProfile,
+        /// Project of the node
+        #[clap(long, default_value = ".")]
+        path: PathBuf,
+        /// Extra arguments passed to the node
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+    /// Add pallets to the runtime of a template project
+    Pallet(PalletArgs),
+    /// Read and change the project settings in dot.toml
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+}
+
+impl Commands {
+    /// Whether the command prints its result as JSON with `--output json`. Other commands reject the flag.
+    pub fn has_json_output(&self) -> bool {
+        match self {
+            Commands::Network(command) => matches!(command, NetworkCommand::List),
+            Commands::Metadata(_) | Commands::Tx { .. } | Commands::Query { .. } => true,
+            Commands::Key(args) => !matches!(args.command, KeyCommand::Insert { .. }),
+            Commands::Runtime(command) => matches!(command, RuntimeCommand::Info { .. }),
+            Commands::Template(command) => matches!(command, TemplateCommand::Status { .. }),
+            Commands::Build { history, .. } => *history,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ChainSpecCommand {
+    /// Generate the chain spec of chain.spec from a release runtime or a runtime wasm
+    Generate {
+        /// Runtime to download from the release [default: chain.runtime of the config]
+        #[clap(long, value_enum)]
+        runtime: Option<Runtime>,
+        /// Runtime wasm to use instead, e.g. built from a template
+        #[clap(long, conflicts_with = "runtime")]
+        wasm: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum NetworkCommand {
+    /// List the networks with their RPC urls
+    List,
+    /// Add a network to dot.toml, or change its url
+    Add {
+        /// Name of the network, e.g. westend
+        name: String,
+        /// WebSocket RPC url, e.g. wss://westend-rpc.polkadot.io
+        url: String,
+        /// Write to the user config instead, shared by all projects
+        #[clap(long)]
+        global: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct MetadataArgs {
+    /// RPC url or network name of the node to fetch the metadata from [default: local]
+    #[clap(long, global = true)]
+    pub rpc: Option<String>,
+    /// Extract the metadata from a runtime wasm file instead
+    #[clap(long, global = true, conflicts_with = "rpc")]
+    pub wasm: Option<PathBuf>,
+    #[clap(subcommand)]
+    pub command: Option<MetadataCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MetadataCommand {
+    /// Show the details of a single pallet
+    Pallet {
+        /// Name of the pallet
+        name: String,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct KeyArgs {
+    /// Signature scheme of the key
+    #[clap(long, global = true, default_value = "sr25519", value_parser = ["sr25519", "ed25519", "ecdsa"])]
+    pub scheme: String,
+    /// SS58 prefix used to encode addresses
+    #[clap(long, global = true, default_value_t = 42)]
+    pub ss58_prefix: u16,
+    /// Path of the local keystore file
+    #[clap(long, global = true, default_value = keystore::DEFAULT_KEYSTORE_PATH)]
+    pub keystore: PathBuf,
+    #[clap(subcommand)]
+    pub command: KeyCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeyCommand {
+    /// Generate a new mnemonic and print its keys
+    Generate {
+        /// Number of words of the mnemonic
+        #[clap(long, default_value = "12", value_parser = ["12", "15", "18", "21", "24"])]
+        words: String,
+        /// Encrypt the key and save it in the keystore under this name
+        #[clap(long)]
+        save: Option<String>,
+    },
+    /// Print the keys of a secret URI, e.g. //Alice or "<mnemonic>//stash"
+    Inspect {
+        /// Secret URI, or a public key / SS58 address with --public
+        uri: String,
+        /// Treat the argument as a public key or address
+        #[clap(long)]
+        public: bool,
+    },
+    /// Insert a key into the keystore of a running node
+    Insert {
+        /// Four character key type, e.g. aura, gran, babe, imon
+        #[clap(long)]
+        key_type: String,
+        /// Secret URI of the key
+        #[clap(long, required_unless_present = "name", conflicts_with = "name")]
+        suri: Option<String>,
+        /// Name of a key saved in the local keystore
+        #[clap(long)]
+        name: Option<String>,
+        /// RPC url of the node or a network name from the config
+        #[clap(long, default_value = rpc::DEFAULT_RPC_URL)]
+        rpc: String,
+    },
+    /// List the keys saved in the local keystore
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CollatorCommand {
+    /// Rotate the session keys of the node and register them with Session.set_keys
+    Setup {
+        /// Secret URI of the collator account [default: keys.signer of the config, //Alice]
+        #[clap(long)]
+        signer: Option<String>,
+        /// RPC url of the node or a network name from the config
+        #[clap(long, default_value = rpc::DEFAULT_RPC_URL)]
+        rpc: String,
+        /// Wait until the extrinsic is in a block or finalized
+        #[clap(long, default_value = "in-block", value_parser = ["in-block", "finalized"])]
+        wait: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ParaCommand {
+    /// Register and onboard a parachain, then wait until it produces blocks
+    Register {
+        /// RPC url of the relay chain node
+        #[clap(long)]
+        relay: String,
+        /// Id of the parachain
+        #[clap(long)]
+        para_id: u32,
+        /// Genesis head file, e.g. from export-genesis-head
+        #[clap(long)]
+        genesis_head: PathBuf,
+        /// Validation code file, e.g. from export-genesis-wasm
+        #[clap(long)]
+        wasm: PathBuf,
+        /// Secret URI of the sudo key [default: keys.signer of the config, //Alice]
+        #[clap(long)]
+        signer: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RuntimeCommand {
+    /// Show the version, APIs, sizes and code hash of a runtime wasm, or compare two
+    Info {
+        /// Path of the runtime wasm, compressed or not
+        wasm: PathBuf,
+        /// Second runtime wasm to compare with
+        compare: Option<PathBuf>,
+    },
+    /// Upgrade the runtime of a running chain and wait until the new code is enacted
+    Upgrade {
+        /// Path of the new runtime wasm
+        #[clap(long)]
+        wasm: PathBuf,
+        /// Secret URI of the signing key [default: keys.signer of the config, //Alice]
+        #[clap(long)]
+        signer: Option<String>,
+        /// Dispatch the upgrade as root: the code through Sudo.sudo_unchecked_weight, the authorization through Sudo.sudo
+        #[clap(long)]
+        sudo: bool,
+        /// RPC url of the node or a network name from the config
+        #[clap(long, default_value = rpc::DEFAULT_RPC_URL)]
+        rpc: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TryRuntimeCommand {
+    /// Save the state of a running chain to a snapshot file
+    CreateSnapshot {
+        /// RPC url of the node or a network name from the config
+        #[clap(long, default_value = rpc::DEFAULT_RPC_URL)]
+        rpc: String,
+        /// Path of the snapshot file
+        #[clap(long, default_value = "./snapshots/chain.snap")]
+        snapshot: PathBuf,
+    },
+    /// Run the runtime upgrade hooks and migration checks of a runtime against a snapshot
+    OnRuntimeUpgrade {
+        /// Path of the snapshot file
+        #[clap(long, default_value = "./snapshots/chain.snap")]
+        snapshot: PathBuf,
+        /// Runtime wasm built with the try-runtime feature
+        #[clap(long)]
+        wasm: PathBuf,
+        /// Which try-runtime checks to run
+        #[clap(long, default_value = "all", value_parser = ["all", "pre-and-post", "try-state", "none"])]
+        checks: String,
+        /// Block time of the chain in milliseconds
+        #[clap(long, default_value_t = 6000)]
+        blocktime: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TemplateCommand {
+    /// List the built-in and user-defined templates
+    List,
+    /// Show how far the template of a project is behind upstream
+    Status {
+        /// Project or template checkout containing dot.lock
+        #[clap(long, default_value = ".")]
+        path: PathBuf,
+    },
+    /// Rebase a template checkout, or apply the template changes to a project, at the latest upstream commit or another ref
+    Update {
+        /// Project or template checkout containing dot.lock
+        #[clap(long, default_value = ".")]
+        path: PathBuf,
+        /// Tag, branch or commit to move to [default: the locked ref or default branch]
+        #[clap(long = "ref", value_name = "REF")]
+        git_ref: Option<String>,
+        /// Clone the template again instead of rebasing
+        #[clap(long)]
+        reclone: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct PalletArgs {
+    /// Root of the project
+    #[clap(long, global = true, default_value = ".")]
+    pub path: PathBuf,
+    /// Print the changes as a diff instead of writing them
+    #[clap(long, global = true)]
+    pub dry_run: bool,
+    #[clap(subcommand)]
+    pub command: PalletCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PalletCommand {
+    /// Generate a new pallet crate and add it to the runtime
+    New {
+        /// Name of the pallet, e.g. voting for the pallet-voting crate
+        name: String,
+    },
+    /// Add a FRAME pallet to the runtime with its features and a default configuration
+    Add {
+        /// Name of the pallet, e.g. assets for the pallet-assets crate
+        name: String,
+        /// Add a pallet without a known default configuration, leaving its Config items to fill in
+        #[clap(long)]
+        allow_incomplete: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the value of a setting, e.g. node.rpc-port
+    Get {
+        /// Setting to print
+        key: String,
+    },
+    /// Write a setting to dot.toml, creating the file in the current directory if needed
+    Set {
+        /// Setting to change, e.g. node.rpc-port or networks.westend
+        key: String,
+        /// New value, as TOML for numbers and lists, e.g. 9955 or '["--dev", "--tmp"]'
+        value: String,
+        /// Write to the user config instead, shared by all projects
+        #[clap(long)]
+        global: bool,
+    },
+    /// Print every setting with its value
+    Show {
+        /// Show which file, profile or environment variable each value comes from
+        #[clap(long)]
+        origin: bool,
+    },
+}
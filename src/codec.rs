@@ -0,0 +1,386 @@
+use parity_scale_codec::{Compact, Decode, Encode};
+use scale_info::form::PortableForm;
+use scale_info::{Field, Type, TypeDef, TypeDefPrimitive};
+use serde_json::{json, Map, Value};
+use crate::keys;
+use crate::metadata::Metadata;
+use crate::rpc;
+
+/// Dynamic SCALE encoding and decoding of JSON values against the runtime type registry.
+///
+/// Values follow a few conventions: composites are objects (or arrays for unnamed
+/// fields, or the inner value for single field wrappers), enum variants are
+/// `"Name"` or `{ "Name": fields }`, byte sequences are `0x` hex strings and
+/// account ids are SS58 addresses.
+impl Metadata {
+    pub fn encode_value(&self, ty: u32, value: &Value, out: &mut Vec<u8>) -> Result<(), String> {
+        let resolved = self.resolve(ty)?;
+
+        if is_account_id(resolved) {
+            let account = match value {
+                Value::String(account) => keys::parse_account(account).map_err(|e| e.to_string())?,
+                _ => return Err(format!("Expected an account, got {}", value)),
+            };
+            out.extend_from_slice(&account);
+            return Ok(());
+        }
+
+        match &resolved.type_def {
+            TypeDef::Composite(def) => self.encode_fields(&def.fields, value, out),
+            TypeDef::Variant(def) => {
+                let is_option = resolved.path.segments.last().is_some_and(|name| name == "Option");
+                if is_option {
+                    if value.is_null() {
+                        out.push(0);
+                        return Ok(());
+                    }
+                    let some = def.variants.iter().find(|variant| variant.name == "Some").ok_or("Invalid Option type")?;
+                    out.push(some.index);
+                    return self.encode_fields(&some.fields, value, out);
+                }
+
+                let (name, inner) = match value {
+                    Value::String(name) => {
+                        // Accounts passed where a `MultiAddress` is expected map to its `Id` variant
+                        let is_address = def.variants.iter().any(|variant| variant.name == "Id")
+                            && !def.variants.iter().any(|variant| variant.name.eq_ignore_ascii_case(name));
+                        if is_address {
+                            ("Id", value)
+                        } else {
+                            (name.as_str(), &Value::Null)
+                        }
+                    }
+                    Value::Object(map) if map.len() == 1 => {
+                        let (name, inner) = map.iter().next().unwrap();
+                        (name.as_str(), inner)
+                    }
+                    _ => return Err(format!("Expected an enum variant for {}, got {}", self.type_name(ty), value)),
+                };
+                let variant = def
+                    .variants
+                    .iter()
+                    .find(|variant| variant.name.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("Unknown variant {} for {}", name, self.type_name(ty)))?;
+                out.push(variant.index);
+                self.encode_fields(&variant.fields, inner, out)
+            }
+            TypeDef::Sequence(def) => {
+                if self.is_u8(def.type_param.id) {
+                    let bytes = bytes_from_value(value)?;
+                    Compact(bytes.len() as u32).encode_to(out);
+                    out.extend_from_slice(&bytes);
+                    return Ok(());
+                }
+                let items = value.as_array().ok_or_else(|| format!("Expected an array, got {}", value))?;
+                Compact(items.len() as u32).encode_to(out);
+                for item in items {
+                    self.encode_value(def.type_param.id, item, out)?;
+                }
+                Ok(())
+            }
+            TypeDef::Array(def) => {
+                if self.is_u8(def.type_param.id) {
+                    let bytes = bytes_from_value(value)?;
+                    if bytes.len() != def.len as usize {
+                        return Err(format!("Expected {} bytes, got {}", def.len, bytes.len()));
+                    }
+                    out.extend_from_slice(&bytes);
+                    return Ok(());
+                }
+                let items = value.as_array().ok_or_else(|| format!("Expected an array, got {}", value))?;
+                if items.len() != def.len as usize {
+                    return Err(format!("Expected {} items, got {}", def.len, items.len()));
+                }
+                for item in items {
+                    self.encode_value(def.type_param.id, item, out)?;
+                }
+                Ok(())
+            }
+            TypeDef::Tuple(def) => {
+                if def.fields.is_empty() {
+                    return Ok(());
+                }
+                if def.fields.len() == 1 && !value.is_array() {
+                    return self.encode_value(def.fields[0].id, value, out);
+                }
+                let items = value.as_array().ok_or_else(|| format!("Expected a tuple array, got {}", value))?;
+                if items.len() != def.fields.len() {
+                    return Err(format!("Expected {} tuple items, got {}", def.fields.len(), items.len()));
+                }
+                for (field, item) in def.fields.iter().zip(items) {
+                    self.encode_value(field.id, item, out)?;
+                }
+                Ok(())
+            }
+            TypeDef::Primitive(primitive) => encode_primitive(primitive, value, out),
+            TypeDef::Compact(_) => {
+                Compact(parse_uint(value)?).encode_to(out);
+                Ok(())
+            }
+            TypeDef::BitSequence(_) => Err("Bit sequences are not supported".to_string()),
+        }
+    }
+
+    fn encode_fields(&self, fields: &[Field<PortableForm>], value: &Value, out: &mut Vec<u8>) -> Result<(), String> {
+        // Zero sized fields such as `PhantomData` encode to nothing and may be omitted
+        let significant: Vec<&Field<PortableForm>> = fields.iter().filter(|field| !self.is_empty_type(field.ty.id)).collect();
+        if significant.is_empty() {
+            return Ok(());
+        }
+
+        let named = significant.iter().all(|field| field.name.is_some());
+        let by_name = match value {
+            Value::Object(map) => named && significant.iter().any(|field| map.contains_key(field.name.as_ref().unwrap())),
+            _ => false,
+        };
+        if by_name {
+            for field in &significant {
+                let name = field.name.as_ref().unwrap();
+                let item = value.get(name).ok_or_else(|| format!("Missing field {}", name))?;
+                self.encode_value(field.ty.id, item, out)?;
+            }
+            return Ok(());
+        }
+
+        if significant.len() == 1 {
+            return self.encode_value(significant[0].ty.id, value, out);
+        }
+        match value {
+            Value::Array(items) if items.len() == significant.len() => {
+                for (field, item) in significant.iter().zip(items) {
+                    self.encode_value(field.ty.id, item, out)?;
+                }
+                Ok(())
+            }
+            _ => Err(format!("Expected {} fields, got {}", significant.len(), value)),
+        }
+    }
+
+    pub fn decode_value(&self, ty: u32, input: &mut &[u8]) -> Result<Value, String> {
+        let resolved = self.resolve(ty)?;
+
+        if is_account_id(resolved) {
+            let account = <[u8; 32]>::decode(input).map_err(|e| e.to_string())?;
+            return Ok(Value::String(keys::ss58_encode(&account, self.ss58_prefix).map_err(|e| e.to_string())?));
+        }
+
+        match &resolved.type_def {
+            TypeDef::Composite(def) => self.decode_fields(&def.fields, input),
+            TypeDef::Variant(def) => {
+                let index = u8::decode(input).map_err(|e| e.to_string())?;
+                let variant = def
+                    .variants
+                    .iter()
+                    .find(|variant| variant.index == index)
+                    .ok_or_else(|| format!("Unknown variant index {} for {}", index, self.type_name(ty)))?;
+
+                let is_option = resolved.path.segments.last().is_some_and(|name| name == "Option");
+                if is_option {
+                    return match variant.name.as_str() {
+                        "None" => Ok(Value::Null),
+                        _ => self.decode_fields(&variant.fields, input),
+                    };
+                }
+                if variant.fields.is_empty() {
+                    return Ok(Value::String(variant.name.clone()));
+                }
+                Ok(json!({ variant.name.clone(): self.decode_fields(&variant.fields, input)? }))
+            }
+            TypeDef::Sequence(def) => {
+                let len = Compact::<u32>::decode(input).map_err(|e| e.to_string())?.0 as usize;
+                if self.is_u8(def.type_param.id) {
+                    return Ok(Value::String(rpc::hex_encode(&take(input, len)?)));
+                }
+                let mut items = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    items.push(self.decode_value(def.type_param.id, input)?);
+                }
+                Ok(Value::Array(items))
+            }
+            TypeDef::Array(def) => {
+                if self.is_u8(def.type_param.id) {
+                    return Ok(Value::String(rpc::hex_encode(&take(input, def.len as usize)?)));
+                }
+                let mut items = Vec::with_capacity(def.len as usize);
+                for _ in 0..def.len {
+                    items.push(self.decode_value(def.type_param.id, input)?);
+                }
+                Ok(Value::Array(items))
+            }
+            TypeDef::Tuple(def) => {
+                if def.fields.is_empty() {
+                    return Ok(Value::Null);
+                }
+                let mut items = Vec::with_capacity(def.fields.len());
+                for field in &def.fields {
+                    items.push(self.decode_value(field.id, input)?);
+                }
+                Ok(Value::Array(items))
+            }
+            TypeDef::Primitive(primitive) => decode_primitive(primitive, input),
+            TypeDef::Compact(_) => {
+                let value = Compact::<u128>::decode(input).map_err(|e| e.to_string())?.0;
+                Ok(uint_value(value))
+            }
+            TypeDef::BitSequence(_) => Err("Bit sequences are not supported".to_string()),
+        }
+    }
+
+    fn decode_fields(&self, fields: &[Field<PortableForm>], input: &mut &[u8]) -> Result<Value, String> {
+        let significant: Vec<&Field<PortableForm>> = fields.iter().filter(|field| !self.is_empty_type(field.ty.id)).collect();
+        if significant.is_empty() {
+            return Ok(Value::Null);
+        }
+        if significant.len() == 1 {
+            let value = self.decode_value(significant[0].ty.id, input)?;
+            return Ok(match &significant[0].name {
+                Some(name) => json!({ name.clone(): value }),
+                None => value,
+            });
+        }
+        if significant.iter().all(|field| field.name.is_some()) {
+            let mut map = Map::new();
+            for field in significant {
+                map.insert(field.name.clone().unwrap(), self.decode_value(field.ty.id, input)?);
+            }
+            return Ok(Value::Object(map));
+        }
+        let mut items = Vec::with_capacity(significant.len());
+        for field in significant {
+            items.push(self.decode_value(field.ty.id, input)?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    /// Whether a type always encodes to zero bytes, e.g. `()` or `PhantomData<T>`.
+    fn is_empty_type(&self, ty: u32) -> bool {
+        match self.types.resolve(ty).map(|ty| &ty.type_def) {
+            Some(TypeDef::Tuple(def)) => def.fields.iter().all(|field| self.is_empty_type(field.id)),
+            Some(TypeDef::Composite(def)) => def.fields.iter().all(|field| self.is_empty_type(field.ty.id)),
+            Some(TypeDef::Array(def)) => def.len == 0,
+            _ => false,
+        }
+    }
+
+    fn resolve(&self, ty: u32) -> Result<&Type<PortableForm>, String> {
+        self.types.resolve(ty).ok_or_else(|| format!("Type {} not found in metadata", ty))
+    }
+
+    fn is_u8(&self, ty: u32) -> bool {
+        matches!(self.types.resolve(ty).map(|ty| &ty.type_def), Some(TypeDef::Primitive(TypeDefPrimitive::U8)))
+    }
+}
+
+fn is_account_id(ty: &Type<PortableForm>) -> bool {
+    ty.path.segments.last().is_some_and(|name| name == "AccountId32")
+}
+
+fn take(input: &mut &[u8], len: usize) -> Result<Vec<u8>, String> {
+    if input.len() < len {
+        return Err("Not enough data to decode".to_string());
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes.to_vec())
+}
+
+/// Accepts `0x` hex, plain text or an array of numbers as bytes.
+fn bytes_from_value(value: &Value) -> Result<Vec<u8>, String> {
+    match value {
+        Value::String(text) if text.starts_with("0x") => rpc::hex_decode(text).map_err(|e| e.to_string()),
+        Value::String(text) => Ok(text.as_bytes().to_vec()),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| parse_uint(item).and_then(|byte| u8::try_from(byte).map_err(|e| e.to_string())))
+            .collect(),
+        _ => Err(format!("Expected bytes, got {}", value)),
+    }
+}
+
+fn parse_uint(value: &Value) -> Result<u128, String> {
+    match value {
+        Value::Number(number) => number.as_u64().map(u128::from).ok_or_else(|| format!("Expected an unsigned integer, got {}", number)),
+        Value::String(text) => text.replace('_', "").parse::<u128>().map_err(|_| format!("Expected an unsigned integer, got {}", text)),
+        _ => Err(format!("Expected an unsigned integer, got {}", value)),
+    }
+}
+
+fn parse_int(value: &Value) -> Result<i128, String> {
+    match value {
+        Value::Number(number) => number.as_i64().map(i128::from).ok_or_else(|| format!("Expected an integer, got {}", number)),
+        Value::String(text) => text.replace('_', "").parse::<i128>().map_err(|_| format!("Expected an integer, got {}", text)),
+        _ => Err(format!("Expected an integer, got {}", value)),
+    }
+}
+
+/// Integers that do not fit into a JSON number are rendered as strings.
+fn uint_value(value: u128) -> Value {
+    match u64::try_from(value) {
+        Ok(value) => json!(value),
+        Err(_) => Value::String(value.to_string()),
+    }
+}
+
+fn int_value(value: i128) -> Value {
+    match i64::try_from(value) {
+        Ok(value) => json!(value),
+        Err(_) => Value::String(value.to_string()),
+    }
+}
+
+fn encode_primitive(primitive: &TypeDefPrimitive, value: &Value, out: &mut Vec<u8>) -> Result<(), String> {
+    let out_of_range = |_| format!("Value {} is out of range", value);
+    match primitive {
+        TypeDefPrimitive::Bool => match value {
+            Value::Bool(flag) => flag.encode_to(out),
+            Value::String(text) => text.parse::<bool>().map_err(|e| e.to_string())?.encode_to(out),
+            _ => return Err(format!("Expected a bool, got {}", value)),
+        },
+        TypeDefPrimitive::Char => {
+            let text = value.as_str().ok_or_else(|| format!("Expected a char, got {}", value))?;
+            let ch = text.chars().next().ok_or("Expected a char, got an empty string")?;
+            (ch as u32).encode_to(out);
+        }
+        TypeDefPrimitive::Str => match value {
+            Value::String(text) => text.encode_to(out),
+            other => other.to_string().encode_to(out),
+        },
+        TypeDefPrimitive::U8 => u8::try_from(parse_uint(value)?).map_err(out_of_range)?.encode_to(out),
+        TypeDefPrimitive::U16 => u16::try_from(parse_uint(value)?).map_err(out_of_range)?.encode_to(out),
+        TypeDefPrimitive::U32 => u32::try_from(parse_uint(value)?).map_err(out_of_range)?.encode_to(out),
+        TypeDefPrimitive::U64 => u64::try_from(parse_uint(value)?).map_err(out_of_range)?.encode_to(out),
+        TypeDefPrimitive::U128 => parse_uint(value)?.encode_to(out),
+        TypeDefPrimitive::I8 => i8::try_from(parse_int(value)?).map_err(out_of_range)?.encode_to(out),
+        TypeDefPrimitive::I16 => i16::try_from(parse_int(value)?).map_err(out_of_range)?.encode_to(out),
+        TypeDefPrimitive::I32 => i32::try_from(parse_int(value)?).map_err(out_of_range)?.encode_to(out),
+        TypeDefPrimitive::I64 => i64::try_from(parse_int(value)?).map_err(out_of_range)?.encode_to(out),
+        TypeDefPrimitive::I128 => parse_int(value)?.encode_to(out),
+        TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => return Err("256 bit integers are not supported".to_string()),
+    }
+    Ok(())
+}
+
+fn decode_primitive(primitive: &TypeDefPrimitive, input: &mut &[u8]) -> Result<Value, String> {
+    let value = match primitive {
+        TypeDefPrimitive::Bool => json!(bool::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::Char => {
+            let code = u32::decode(input).map_err(|e| e.to_string())?;
+            json!(char::from_u32(code).map(String::from).unwrap_or_default())
+        }
+        TypeDefPrimitive::Str => json!(String::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::U8 => json!(u8::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::U16 => json!(u16::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::U32 => json!(u32::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::U64 => json!(u64::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::U128 => uint_value(u128::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::I8 => json!(i8::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::I16 => json!(i16::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::I32 => json!(i32::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::I64 => json!(i64::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::I128 => int_value(i128::decode(input).map_err(|e| e.to_string())?),
+        TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => {
+            Value::String(rpc::hex_encode(&take(input, 32)?))
+        }
+    };
+    Ok(value)
+}
@@ -0,0 +1,51 @@
+use std::error::Error;
+use serde_json::{json, Value};
+use crate::keys::{self, Pair};
+use crate::metadata;
+use crate::output;
+use crate::query;
+use crate::rpc::{self, RpcClient};
+use crate::tx;
+
+/// Rotates the session keys of the node, registers them for `signer` with `Session.set_keys`
+/// and checks that they are queued in `Session.NextKeys`.
+pub fn setup(rpc_url: &str, signer: &str, finalized: bool) -> Result<(), Box<dyn Error>> {
+    let mut client = RpcClient::connect(rpc_url)?;
+    let metadata = metadata::from_rpc(&mut client)?;
+    let pair = Pair::from_uri(signer)?;
+    let account = keys::ss58_encode(&pair.account_id(), metadata.ss58_prefix)?;
+
+    let session = metadata.pallet("Session")?;
+    let set_keys = metadata
+        .variant(session.calls, "set_keys")
+        .ok_or("Call Session.set_keys not found in runtime metadata")?;
+    let keys_ty = set_keys.fields.first().ok_or("Unexpected Session.set_keys signature")?.ty.id;
+
+    let rotated = client.request("author_rotateKeys", json!([]))?;
+    let rotated = rotated.as_str().ok_or("Unexpected author_rotateKeys response")?.to_string();
+    println!("Rotated session keys: {}", rotated);
+
+    // The node returns the SCALE encoded `SessionKeys`, decode them so they can be passed as a call argument.
+    let session_keys = metadata.decode_value(keys_ty, &mut &rpc::hex_decode(&rotated)?[..])?;
+    for (name, public) in session_keys.as_object().into_iter().flatten() {
+        println!("  {}: {}", name, public);
+    }
+
+    output::progress(format_args!("\nRegistering session keys for {}", account));
+    let call_data = tx::encode_call(&metadata, "Session", "set_keys", &[session_keys.clone(), json!("0x")])?;
+    let outcome = tx::submit(&mut client, &metadata, &pair, &call_data, finalized)?;
+
+    let queued = query::fetch(
+        &mut client,
+        &metadata,
+        "Session",
+        "NextKeys",
+        &[Value::String(account.clone())],
+        Some(&outcome.block_hash),
+    )?;
+    if queued != session_keys {
+        return Err(format!("Session.NextKeys of {} does not match the rotated keys: {}", account, queued).into());
+    }
+    println!("✓ Session keys are queued in Session.NextKeys and take effect from the next session");
+    Ok(())
+}
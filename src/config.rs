@@ -0,0 +1,544 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::OnceLock;
+use toml::Value;
+
+/// Project configuration file, looked up in the current directory and its parents.
+pub const CONFIG_FILE: &str = "dot.toml";
+
+/// Environment variables `DOT_<KEY>` override the config, e.g. `DOT_NODE_RPC_PORT` for `node.rpc-port`.
+const ENV_PREFIX: &str = "DOT_";
+
+/// Selects a profile like `--config-profile`.
+const PROFILE_ENV: &str = "DOT_PROFILE";
+
+/// Settings and their defaults, as TOML values. Relative paths are relative to the directory of
+/// the `dot.toml`, `~/` to the home directory.
+const DEFAULTS: &[(&str, &str)] = &[
+    ("sdk.release", "\"polkadot-stable2412\""),
+    ("sdk.mirror", "\"https://github.com/paritytech/polkadot-sdk/releases/download\""),
+    ("paths.binaries", "\"./binaries\""),
+    ("paths.nodes", "\"./nodes\""),
+    ("paths.templates", "\"./templates\""),
+    ("paths.script", "\"./script/omni-node.sh\""),
+    ("paths.cache", "\"~/.cache/dot\""),
+    ("chain.spec", "\"./chain-specs/chain_spec.json\""),
+    ("chain.runtime", "\"asset_hub_westend_runtime\""),
+    ("chain.relay", "\"westend2\""),
+    ("chain.para-id", "1000"),
+    ("node.flags", "[\"--dev\"]"),
+    ("node.rpc-port", "9944"),
+    ("node.p2p-port", "30333"),
+    ("template.source", "\"https://github.com/paritytech/polkadot-sdk-{name}-template.git\""),
+    ("keys.signer", "\"//Alice\""),
+    ("output.format", "\"text\""),
+    ("output.verbosity", "\"normal\""),
+];
+
+/// Settings limited to a few values.
+const CHOICES: &[(&str, &[&str])] = &[
+    ("output.format", &["text", "json"]),
+    ("output.verbosity", &["quiet", "normal", "verbose"]),
+];
+
+/// Networks are RPC urls that can be passed by name to `--rpc`, e.g. `networks.westend`.
+const NETWORKS: &str = "networks";
+
+/// Network of the node started by `dot serve`, following `node.rpc-port`.
+pub const LOCAL_NETWORK: &str = "local";
+
+/// Named sets of settings, e.g. `[profile.ci.node]`, applied over the rest of their file.
+const PROFILES: &str = "profile";
+
+/// User templates, read from the user config only by `template::registry`.
+const TEMPLATES: &str = "templates";
+
+static CURRENT: OnceLock<Config> = OnceLock::new();
+
+static OPTIONS: OnceLock<Options> = OnceLock::new();
+
+/// Command line options changing how `current` loads the config.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// `--config-profile`, instead of `DOT_PROFILE`.
+    pub profile: Option<String>,
+    /// Whether the profile may be missing from the config files, e.g. to create it with `dot config set`.
+    pub allow_new: bool,
+    /// `--config`, used instead of the `dot.toml` found from the current directory.
+    pub file: Option<PathBuf>,
+    /// Settings given as flags, e.g. `--output json` for `output.format`.
+    pub flags: Vec<Entry>,
+}
+
+/// Where the value of a setting comes from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Origin {
+    Default,
+    File(PathBuf),
+    Profile(PathBuf, String),
+    Env(String),
+    Flag(String),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::File(path) => write!(f, "{}", path.display()),
+            Origin::Profile(path, name) => write!(f, "{} [{}.{}]", path.display(), PROFILES, name),
+            Origin::Env(name) => write!(f, "environment variable {}", name),
+            Origin::Flag(flag) => write!(f, "command line flag {}", flag),
+        }
+    }
+}
+
+/// A setting with its value and origin.
+pub type Entry = (String, Value, Origin);
+
+#[derive(Debug)]
+struct Layer {
+    origin: Origin,
+    values: toml::Table,
+}
+
+/// Configuration of the project the CLI runs in: the user config, then the project `dot.toml`,
+/// each followed by its section of the selected profile.
+#[derive(Debug)]
+pub struct Config {
+    /// Directory of the `dot.toml`, or the current directory without one.
+    pub root: PathBuf,
+    /// The `dot.toml` in use, if any.
+    pub file: Option<PathBuf>,
+    /// The selected profile.
+    pub profile: Option<String>,
+    /// Profiles defined in the config files.
+    pub profiles: Vec<String>,
+    /// Settings given on the command line, overriding everything else.
+    pub flags: Vec<Entry>,
+    /// Lowest priority first.
+    layers: Vec<Layer>,
+}
+
+/// The configuration of the current directory, loaded on first use.
+pub fn current() -> &'static Config {
+    CURRENT.get_or_init(|| {
+        Config::load().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        })
+    })
+}
+
+/// Sets the command line options used by `current`, which must not have been called yet.
+pub fn use_options(options: Options) {
+    let _ = OPTIONS.set(options);
+}
+
+/// `$XDG_CONFIG_HOME/dot/config.toml`, falling back to `~/.config/dot/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir).join("dot").join("config.toml")),
+        None => env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("dot").join("config.toml")),
+    }
+}
+
+/// The closest `dot.toml` in `dir` or one of its parents.
+pub fn discover(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().map(|dir| dir.join(CONFIG_FILE)).find(|path| path.is_file())
+}
+
+impl Config {
+    pub fn new(root: PathBuf, profile: Option<String>) -> Config {
+        Config { root, file: None, profile, profiles: Vec::new(), flags: Vec::new(), layers: Vec::new() }
+    }
+
+    pub fn load() -> Result<Config, Box<dyn Error>> {
+        let options = OPTIONS.get().cloned().unwrap_or_default();
+        let profile = options.profile.or_else(|| env::var(PROFILE_ENV).ok().filter(|name| !name.is_empty()));
+        let mut config = Config::new(PathBuf::from("."), profile);
+        config.flags = options.flags;
+        if let Some(path) = user_config_path().filter(|path| path.is_file()) {
+            let content = fs::read_to_string(&path)?;
+            config.add_user_layer(&path, &content).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        }
+        let cwd = env::current_dir()?;
+        let file = match options.file {
+            Some(file) if file.is_file() => Some(file),
+            Some(file) => return Err(format!("Config file {} not found", file.display()).into()),
+            None => discover(&cwd),
+        };
+        if let Some(file) = file {
+            config.root = match file.parent() {
+                Some(dir) if dir != cwd && !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            config.add_file(&file)?;
+            config.file = Some(file);
+        }
+
+        let missing = config.profile.as_ref().filter(|profile| !options.allow_new && !config.profiles.contains(profile));
+        if let Some(profile) = missing {
+            return Err(match config.profiles.is_empty() {
+                true => format!("Unknown profile {}, add a [{}.{}] table to {} or the user config", profile, PROFILES, profile, CONFIG_FILE),
+                false => format!("Unknown profile {}, available profiles: {}", profile, config.profiles.join(", ")),
+            }
+            .into());
+        }
+        Ok(config)
+    }
+
+    fn add_file(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        self.add_layer(path, &content).map_err(|e| format!("Invalid {}: {}", path.display(), e).into())
+    }
+
+    /// Adds the user config like `add_layer`, skipping its templates.
+    pub fn add_user_layer(&mut self, file: &Path, content: &str) -> Result<(), Box<dyn Error>> {
+        let mut values: toml::Table = content.parse()?;
+        values.remove(TEMPLATES);
+        self.add_values(file, values)
+    }
+
+    /// Adds the settings of a config file over the previous ones, then its section of the
+    /// selected profile.
+    pub fn add_layer(&mut self, file: &Path, content: &str) -> Result<(), Box<dyn Error>> {
+        self.add_values(file, content.parse()?)
+    }
+
+    fn add_values(&mut self, file: &Path, mut values: toml::Table) -> Result<(), Box<dyn Error>> {
+        let profiles = match values.remove(PROFILES) {
+            Some(Value::Table(profiles)) => profiles,
+            Some(_) => return Err(format!("`{}` must be a table", PROFILES).into()),
+            None => toml::Table::new(),
+        };
+        check_settings(&values)?;
+        self.layers.push(Layer { origin: Origin::File(file.to_path_buf()), values });
+
+        for (name, values) in profiles {
+            let values = match values {
+                Value::Table(values) => values,
+                _ => return Err(format!("`{}.{}` must be a table", PROFILES, name).into()),
+            };
+            check_settings(&values).map_err(|e| format!("{}.{}: {}", PROFILES, name, e))?;
+            if self.profile.as_ref() == Some(&name) {
+                self.layers.push(Layer { origin: Origin::Profile(file.to_path_buf(), name.clone()), values });
+            }
+            if !self.profiles.contains(&name) {
+                self.profiles.push(name);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Result<Value, Box<dyn Error>> {
+        self.lookup(key).map(|(value, _)| value)
+    }
+
+    /// Value of a setting and where it comes from, the command line overriding the environment,
+    /// the environment the config files and the config files the defaults.
+    pub fn lookup(&self, key: &str) -> Result<(Value, Origin), Box<dyn Error>> {
+        let default = default(key)?;
+        if let Some((_, value, origin)) = self.flags.iter().find(|(name, _, _)| name == key) {
+            return Ok((value.clone(), origin.clone()));
+        }
+        if let Ok(value) = env::var(env_name(key)) {
+            let value = parse_value(&value);
+            check_type(key, &value).map_err(|e| format!("Invalid {}: {}", env_name(key), e))?;
+            return Ok((value, Origin::Env(env_name(key))));
+        }
+        let (table, name) = key.split_once('.').unwrap_or_default();
+        for layer in self.layers.iter().rev() {
+            if let Some(value) = layer.values.get(table).and_then(|table| table.get(name)) {
+                return Ok((value.clone(), layer.origin.clone()));
+            }
+        }
+        if key == format!("{}.{}", NETWORKS, LOCAL_NETWORK) {
+            return Ok((Value::String(format!("ws://127.0.0.1:{}", self.integer("node.rpc-port"))), Origin::Default));
+        }
+        default.map(|value| (value, Origin::Default)).ok_or_else(|| format!("{} is not set", key).into())
+    }
+
+    pub fn string(&self, key: &str) -> String {
+        match self.get(key) {
+            Ok(Value::String(value)) => value,
+            Ok(value) => value.to_string(),
+            Err(e) => exit(e),
+        }
+    }
+
+    pub fn integer(&self, key: &str) -> i64 {
+        match self.get(key) {
+            Ok(value) => value.as_integer().unwrap_or_default(),
+            Err(e) => exit(e),
+        }
+    }
+
+    pub fn strings(&self, key: &str) -> Vec<String> {
+        match self.get(key) {
+            Ok(Value::Array(values)) => values.iter().filter_map(Value::as_str).map(String::from).collect(),
+            Ok(_) => Vec::new(),
+            Err(e) => exit(e),
+        }
+    }
+
+    /// A path setting, relative ones resolved against the project root.
+    pub fn path(&self, key: &str) -> PathBuf {
+        let value = self.string(key);
+        if let (Some(rest), Some(home)) = (value.strip_prefix("~/"), env::var_os("HOME")) {
+            return PathBuf::from(home).join(rest);
+        }
+        let path = PathBuf::from(value);
+        if path.is_absolute() || self.root == Path::new(".") {
+            return path;
+        }
+        self.root.join(path.strip_prefix(".").unwrap_or(&path))
+    }
+
+    /// Every setting with its value and origin, the networks last.
+    pub fn entries(&self) -> Result<Vec<Entry>, Box<dyn Error>> {
+        let mut keys: Vec<String> = DEFAULTS.iter().map(|(key, _)| key.to_string()).collect();
+        keys.push(format!("{}.{}", NETWORKS, LOCAL_NETWORK));
+        for layer in &self.layers {
+            if let Some(networks) = layer.values.get(NETWORKS).and_then(Value::as_table) {
+                for name in networks.keys() {
+                    let key = format!("{}.{}", NETWORKS, name);
+                    if !keys.contains(&key) {
+                        keys.push(key);
+                    }
+                }
+            }
+        }
+        keys.into_iter().map(|key| self.lookup(&key).map(|(value, origin)| (key, value, origin))).collect()
+    }
+
+    /// RPC url of a network name from the config, or `url` itself when it is not a name.
+    pub fn rpc_url(&self, url: &str) -> Result<String, Box<dyn Error>> {
+        if url.contains("://") {
+            return Ok(url.to_string());
+        }
+        match self.get(&format!("{}.{}", NETWORKS, url)) {
+            Ok(Value::String(url)) => Ok(url),
+            _ => Err(format!("Unknown network {}, add it with `dot config set {}.{} <url>`", url, NETWORKS, url).into()),
+        }
+    }
+}
+
+fn exit(e: Box<dyn Error>) -> ! {
+    eprintln!("{}", e);
+    process::exit(1);
+}
+
+/// `None` for networks, they have no default.
+fn default(key: &str) -> Result<Option<Value>, Box<dyn Error>> {
+    if key.strip_prefix(NETWORKS).and_then(|name| name.strip_prefix('.')).is_some_and(|name| !name.is_empty()) {
+        return Ok(None);
+    }
+    if key == "paths.cache" {
+        if let Some(dir) = env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+            return Ok(Some(Value::String(PathBuf::from(dir).join("dot").display().to_string())));
+        }
+    }
+    match DEFAULTS.iter().find(|(name, _)| *name == key) {
+        Some((_, value)) => Ok(Some(parse_value(value))),
+        None => {
+            let keys: Vec<&str> = DEFAULTS.iter().map(|(key, _)| *key).collect();
+            Err(format!("Unknown setting {}, available settings: {}, {}.<name>", key, keys.join(", "), NETWORKS).into())
+        }
+    }
+}
+
+fn check_settings(values: &toml::Table) -> Result<(), Box<dyn Error>> {
+    for (table, entries) in values {
+        if table == TEMPLATES {
+            return Err("[templates] tables are only read from the user config, move them there".into());
+        }
+        let entries = entries.as_table().ok_or(format!("`{}` must be a table", table))?;
+        for (key, value) in entries {
+            check_type(&format!("{}.{}", table, key), value)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_type(key: &str, value: &Value) -> Result<(), Box<dyn Error>> {
+    let expected = match default(key)? {
+        Some(default) => default,
+        // Networks
+        None => Value::String(String::new()),
+    };
+    let valid = match (&expected, value) {
+        (Value::Array(_), Value::Array(values)) => values.iter().all(Value::is_str),
+        (expected, value) => expected.same_type(value),
+    };
+    if !valid {
+        return Err(format!("{} must be {}", key, type_name(&expected)).into());
+    }
+    if let Some((_, choices)) = CHOICES.iter().find(|(name, _)| *name == key) {
+        if !value.as_str().is_some_and(|value| choices.contains(&value)) {
+            return Err(format!("{} must be one of {}", key, choices.join(", ")).into());
+        }
+    }
+    Ok(())
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Integer(_) => "an integer",
+        Value::Array(_) => "a list of strings",
+        _ => "a string",
+    }
+}
+
+/// `DOT_NODE_RPC_PORT` for `node.rpc-port`.
+pub fn env_name(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.replace(['.', '-'], "_").to_uppercase())
+}
+
+/// Reads a value given on the command line or in the environment: TOML such as `9955` or
+/// `["--dev", "--tmp"]`, anything else is a string.
+pub fn parse_value(value: &str) -> Value {
+    format!("value = {}", value)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+/// Sets a setting in the content of a config file, in the section of `profile` if given, keeping
+/// its formatting and comments.
+pub fn set_value(content: &str, key: &str, value: &str, profile: Option<&str>) -> Result<String, Box<dyn Error>> {
+    let parsed = parse_value(value);
+    check_type(key, &parsed)?;
+    let (table, name) = key.split_once('.').ok_or(format!("Invalid setting {}", key))?;
+
+    let mut document: toml_edit::DocumentMut = content.parse()?;
+    let mut tables = Vec::new();
+    if let Some(profile) = profile {
+        tables.extend([PROFILES, profile]);
+    }
+    tables.push(table);
+    let mut table = document.as_table_mut();
+    for (depth, name) in tables.iter().enumerate() {
+        if !table.contains_key(name) {
+            let mut new_table = toml_edit::Table::new();
+            // `[profile.ci.node]` without empty `[profile]` and `[profile.ci]` headers
+            new_table.set_implicit(depth + 1 < tables.len());
+            table.insert(name, toml_edit::Item::Table(new_table));
+        }
+        table = table
+            .get_mut(name)
+            .and_then(toml_edit::Item::as_table_mut)
+            .ok_or(format!("`{}` must be a table", tables[..=depth].join(".")))?;
+    }
+    let mut value = match parsed {
+        Value::String(value) => toml_edit::Value::from(value),
+        other => other.to_string().parse::<toml_edit::Value>()?,
+    };
+    // Keep the comments around the previous value
+    if let Some(previous) = table.get(name).and_then(toml_edit::Item::as_value) {
+        *value.decor_mut() = previous.decor().clone();
+    }
+    table.insert(name, toml_edit::Item::Value(value));
+    Ok(document.to_string())
+}
+
+pub fn print_value(key: &str) -> Result<(), Box<dyn Error>> {
+    match current().get(key)? {
+        Value::String(value) => println!("{}", value),
+        value => println!("{}", value),
+    }
+    Ok(())
+}
+
+/// Writes a setting to the project `dot.toml`, creating one in the current directory if needed,
+/// or with `global` to the user config. With a profile selected, it goes to its section.
+pub fn set(key: &str, value: &str, global: bool) -> Result<(), Box<dyn Error>> {
+    let config = current();
+    let path = match global {
+        true => user_config_path().ok_or("Cannot locate the user config, set XDG_CONFIG_HOME or HOME")?,
+        false => config.file.clone().unwrap_or_else(|| PathBuf::from(CONFIG_FILE)),
+    };
+    let content = if path.exists() { fs::read_to_string(&path)? } else { String::new() };
+    let updated = set_value(&content, key, value, config.profile.as_deref())?;
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, updated)?;
+    match &config.profile {
+        Some(profile) => println!("✓ Set {} of profile {} in {}", key, profile, path.display()),
+        None => println!("✓ Set {} in {}", key, path.display()),
+    }
+    if env::var_os(env_name(key)).is_some() {
+        println!("⚠ {} is set and overrides it", env_name(key));
+    } else if global && config.file.is_some() {
+        if let Ok((_, origin @ (Origin::File(_) | Origin::Profile(..)))) = config.lookup(key) {
+            if Some(&path) != origin_file(&origin) {
+                println!("⚠ {} is also set in {} and overrides it", key, origin);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the networks that `--rpc` takes by name with their urls.
+pub fn list_networks(json: bool) -> Result<(), Box<dyn Error>> {
+    let prefix = format!("{}.", NETWORKS);
+    let networks: Vec<(String, Value)> = current()
+        .entries()?
+        .into_iter()
+        .filter_map(|(key, value, _)| key.strip_prefix(&prefix).map(|name| (name.to_string(), value)))
+        .collect();
+    if json {
+        let networks: serde_json::Map<String, serde_json::Value> =
+            networks.into_iter().map(|(name, url)| (name, serde_json::Value::String(url.as_str().unwrap_or_default().to_string()))).collect();
+        println!("{}", serde_json::to_string_pretty(&networks)?);
+    } else {
+        for (name, url) in networks {
+            println!("{:<16} {}", name, url.as_str().unwrap_or_default());
+        }
+    }
+    Ok(())
+}
+
+/// Adds a network to the project `dot.toml`, or with `global` to the user config.
+pub fn add_network(name: &str, url: &str, global: bool) -> Result<(), Box<dyn Error>> {
+    if name.is_empty() || name.contains('.') {
+        return Err(format!("Invalid network name {}", name).into());
+    }
+    if !url.starts_with("ws://") && !url.starts_with("wss://") {
+        return Err(format!("Invalid url {}, expected a WebSocket url such as wss://westend-rpc.polkadot.io", url).into());
+    }
+    set(&format!("{}.{}", NETWORKS, name), url, global)
+}
+
+fn origin_file(origin: &Origin) -> Option<&PathBuf> {
+    match origin {
+        Origin::File(path) | Origin::Profile(path, _) => Some(path),
+        _ => None,
+    }
+}
+
+/// Prints every setting, with `origin` followed by where its value comes from.
+pub fn show(origin: bool) -> Result<(), Box<dyn Error>> {
+    let config = current();
+    for layer in &config.layers {
+        println!("# {}", layer.origin);
+    }
+    if config.layers.is_empty() {
+        println!("# No {} or user config found, showing the defaults", CONFIG_FILE);
+    }
+    let entries = config.entries()?;
+    let lines: Vec<String> = entries.iter().map(|(key, value, _)| format!("{} = {}", key, value)).collect();
+    let width = lines.iter().map(String::len).max().unwrap_or_default();
+    for (line, (_, _, source)) in lines.iter().zip(&entries) {
+        match origin {
+            true => println!("{:<width$}  # {}", line, source, width = width),
+            false => println!("{}", line),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::error::Error;
+use frame_metadata::v14::StorageEntryType;
+use serde_json::{json, Value};
+use crate::metadata::Metadata;
+use crate::query;
+use crate::rpc::{self, RpcClient};
+
+/// A decoded entry of `System::Events`.
+pub struct EventRecord {
+    /// Index of the extrinsic that emitted the event, if any.
+    pub extrinsic_index: Option<u64>,
+    pub pallet: String,
+    pub name: String,
+    pub fields: Value,
+}
+
+impl EventRecord {
+    pub fn is(&self, pallet: &str, name: &str) -> bool {
+        self.pallet == pallet && self.name == name
+    }
+}
+
+/// Storage key of `System::Events`.
+pub fn system_events_key() -> Vec<u8> {
+    query::storage_prefix("System", "Events")
+}
+
+/// Fetches and decodes the events emitted in the given block.
+pub fn fetch_events(client: &mut RpcClient, metadata: &Metadata, block_hash: &str) -> Result<Vec<EventRecord>, Box<dyn Error>> {
+    let key = rpc::hex_encode(&system_events_key());
+    let result = client.request("state_getStorage", json!([key, block_hash]))?;
+    match result.as_str() {
+        Some(data) => decode_events(metadata, &rpc::hex_decode(data)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Decodes the SCALE encoded value of `System::Events`.
+pub fn decode_events(metadata: &Metadata, data: &[u8]) -> Result<Vec<EventRecord>, Box<dyn Error>> {
+    let entry = metadata
+        .pallet("System")?
+        .storage
+        .iter()
+        .flat_map(|storage| storage.entries.iter())
+        .find(|entry| entry.name == "Events")
+        .ok_or("System::Events not found in metadata")?;
+    let ty = match &entry.ty {
+        StorageEntryType::Plain(ty) => ty.id,
+        _ => return Err("Unexpected System::Events storage type".into()),
+    };
+
+    let decoded = metadata.decode_value(ty, &mut &data[..])?;
+    let records = decoded.as_array().cloned().unwrap_or_default();
+    Ok(records.iter().map(event_record).collect())
+}
+
+fn event_record(record: &Value) -> EventRecord {
+    let extrinsic_index = record.pointer("/phase/ApplyExtrinsic").and_then(Value::as_u64);
+    let (pallet, event) = split_variant(&record["event"]);
+    let (name, fields) = split_variant(&event);
+    EventRecord { extrinsic_index, pallet, name, fields }
+}
+
+/// Splits `{ "Name": inner }` or `"Name"` into the variant name and its fields.
+fn split_variant(value: &Value) -> (String, Value) {
+    match value {
+        Value::String(name) => (name.clone(), Value::Null),
+        Value::Object(map) if map.len() == 1 => {
+            let (name, inner) = map.iter().next().unwrap();
+            (name.clone(), inner.clone())
+        }
+        other => (other.to_string(), Value::Null),
+    }
+}
+
+/// An event as `{ "pallet", "name", "fields", "extrinsic_index" }` for JSON output.
+pub fn event_json(event: &EventRecord) -> Value {
+    json!({ "pallet": event.pallet, "name": event.name, "fields": event.fields, "extrinsic_index": event.extrinsic_index })
+}
+
+pub fn format_event(event: &EventRecord) -> String {
+    if event.fields.is_null() {
+        return format!("{}.{}", event.pallet, event.name);
+    }
+    format!("{}.{} {}", event.pallet, event.name, event.fields)
+}
+
+/// Renders a `DispatchError`, resolving module errors to their pallet error name and docs.
+pub fn format_dispatch_error(metadata: &Metadata, error: &Value) -> String {
+    let module = match error.get("Module") {
+        Some(module) => module,
+        None => return error.to_string(),
+    };
+
+    let pallet_index = module.get("index").and_then(Value::as_u64);
+    let error_index = match module.get("error") {
+        Some(Value::String(bytes)) => rpc::hex_decode(bytes).ok().and_then(|bytes| bytes.first().copied()),
+        Some(Value::Number(index)) => index.as_u64().map(|index| index as u8),
+        _ => None,
+    };
+
+    let pallet = metadata.pallets.iter().find(|pallet| Some(pallet.index as u64) == pallet_index);
+    let variant = pallet.and_then(|pallet| {
+        metadata.variants(pallet.error).iter().find(|variant| Some(variant.index) == error_index)
+    });
+    match (pallet, variant) {
+        (Some(pallet), Some(variant)) => {
+            let docs = variant.docs.join(" ");
+            format!("{}.{}: {}", pallet.name, variant.name, docs.trim())
+        }
+        _ => module.to_string(),
+    }
+}
@@ -0,0 +1,319 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Map, Value};
+use crate::config;
+use crate::install;
+use crate::keys::{Pair, Scheme};
+use crate::metadata::{self, Metadata};
+use crate::output;
+use crate::query;
+use crate::rpc::{self, RpcClient};
+use crate::serve;
+use crate::wasm;
+
+/// Chain spec written by `dot fork --from-snapshot` next to `chain.spec` and served instead of it.
+const FORK_CHAIN_SPEC: &str = "fork.json";
+
+/// Free balance given to the dev accounts in the fork.
+const DEV_BALANCE: &str = "1000000000000000000000";
+
+const KEYS_PAGE_SIZE: u32 = 1000;
+
+/// Storage values that tie a parachain to its real relay chain and would make the first
+/// locally authored block fail, e.g. because the mocked relay block number is lower.
+const RELAY_BOUND_ITEMS: &[(&str, &str)] = &[
+    ("ParachainSystem", "LastRelayChainBlockNumber"),
+    ("ParachainSystem", "UnincludedSegment"),
+    ("ParachainSystem", "AggregatedUnincludedSegment"),
+    ("AuraExt", "SlotInfo"),
+];
+
+/// Extension of fork snapshots. try-runtime snapshots (`.snap`) use a different, binary format.
+const SNAPSHOT_EXTENSION: &str = "json";
+
+/// Prefix of the top level keys holding the roots of default child tries, e.g. of contracts.
+const CHILD_STORAGE_PREFIX: &[u8] = b":child_storage:default:";
+
+/// Saves the raw storage of a running chain, with its child tries, into a JSON snapshot file.
+pub fn save_snapshot(rpc_url: &str, output: &Path, at: Option<&str>) -> Result<(), Box<dyn Error>> {
+    check_extension(output)?;
+    let mut client = RpcClient::connect(rpc_url)?;
+    let block_hash = match query::resolve_block(&mut client, at)? {
+        Some(hash) => hash,
+        None => client
+            .request("chain_getBlockHash", json!([]))?
+            .as_str()
+            .ok_or("Unexpected chain_getBlockHash response")?
+            .to_string(),
+    };
+    let header = client.request("chain_getHeader", json!([block_hash]))?;
+    output::progress(format_args!("Saving the state of {} at block {}...", rpc_url, block_hash));
+
+    let top = fetch_storage(&mut client, None, &block_hash)?;
+
+    let mut children = Map::new();
+    for child_key in top.keys().filter(|key| is_child_storage_key(key)) {
+        output::progress(format_args!("Saving child trie {}...", child_key));
+        let child = fetch_storage(&mut client, Some(child_key), &block_hash)?;
+        children.insert(unprefixed_child_key(child_key)?, Value::Object(child));
+    }
+
+    let snapshot = json!({
+        "version": 1,
+        "chain": client.request("system_chain", json!([]))?,
+        "properties": client.request("system_properties", json!([]))?,
+        "block": { "hash": block_hash, "number": header["number"] },
+        "top": top,
+        "childrenDefault": children,
+    });
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(output, serde_json::to_string(&snapshot)?)?;
+    println!("✓ Saved {} storage items and {} child tries to {}", top.len(), children.len(), output.display());
+    Ok(())
+}
+
+/// Fetches all key/value pairs of the top level storage, or of a child trie given its prefixed key.
+fn fetch_storage(client: &mut RpcClient, child_key: Option<&str>, block_hash: &str) -> Result<Map<String, Value>, Box<dyn Error>> {
+    let mut storage = Map::new();
+    let mut start_key: Option<String> = None;
+    loop {
+        let page = match child_key {
+            Some(child_key) => client.request("childstate_getKeysPaged", json!([child_key, "0x", KEYS_PAGE_SIZE, start_key, block_hash]))?,
+            None => client.request("state_getKeysPaged", json!(["0x", KEYS_PAGE_SIZE, start_key, block_hash]))?,
+        };
+        let page: Vec<String> = page
+            .as_array()
+            .map(|keys| keys.iter().filter_map(|key| key.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        if page.is_empty() {
+            break;
+        }
+
+        match child_key {
+            Some(child_key) => {
+                let values = client.request("childstate_getStorageEntries", json!([child_key, page, block_hash]))?;
+                for (key, value) in page.iter().zip(values.as_array().into_iter().flatten()) {
+                    if let Some(value) = value.as_str() {
+                        storage.insert(key.clone(), json!(value));
+                    }
+                }
+            }
+            None => {
+                let changes = client.request("state_queryStorageAt", json!([page, block_hash]))?;
+                for change in changes.pointer("/0/changes").and_then(Value::as_array).into_iter().flatten() {
+                    if let (Some(key), Some(value)) = (change[0].as_str(), change[1].as_str()) {
+                        storage.insert(key.to_string(), json!(value));
+                    }
+                }
+            }
+        }
+        if !output::quiet() {
+            print!("\r  {} keys", storage.len());
+        }
+
+        if (page.len() as u32) < KEYS_PAGE_SIZE {
+            break;
+        }
+        start_key = page.last().cloned();
+    }
+    if !output::quiet() {
+        println!();
+    }
+    Ok(storage)
+}
+
+fn check_extension(path: &Path) -> Result<(), Box<dyn Error>> {
+    if path.extension().is_some_and(|extension| extension == SNAPSHOT_EXTENSION) {
+        return Ok(());
+    }
+    Err(format!(
+        "Fork snapshots are JSON files, use a .{} path instead of {} (.snap files are try-runtime snapshots)",
+        SNAPSHOT_EXTENSION,
+        path.display()
+    )
+    .into())
+}
+
+fn is_child_storage_key(key: &str) -> bool {
+    key.starts_with(&rpc::hex_encode(CHILD_STORAGE_PREFIX))
+}
+
+/// The key of a child trie in `childrenDefault` of a raw chain spec: its storage key without the prefix.
+fn unprefixed_child_key(key: &str) -> Result<String, Box<dyn Error>> {
+    let bytes = rpc::hex_decode(key)?;
+    Ok(rpc::hex_encode(&bytes[CHILD_STORAGE_PREFIX.len()..]))
+}
+
+/// Builds a dev chain spec from a snapshot and serves it with omni-node. Works offline.
+pub fn from_snapshot(snapshot_path: &Path, args: &[&str]) -> Result<(), Box<dyn Error>> {
+    check_extension(snapshot_path)?;
+    let snapshot: Value = serde_json::from_str(
+        &fs::read_to_string(snapshot_path).map_err(|e| format!("Failed to read {}: {}", snapshot_path.display(), e))?,
+    )
+    .map_err(|e| format!("Invalid snapshot {}: {}", snapshot_path.display(), e))?;
+
+    let code = snapshot
+        .pointer(&format!("/top/{}", rpc::hex_encode(b":code")))
+        .and_then(Value::as_str)
+        .ok_or("The snapshot does not contain the runtime code")?;
+    output::progress("Reading runtime metadata from the snapshot...");
+    let metadata = metadata::decode(&wasm::metadata_bytes(&wasm::decompress(&rpc::hex_decode(code)?)?)?)?;
+
+    let chain_spec = build_chain_spec(&snapshot, &metadata)?;
+    let chain_spec_path = fork_chain_spec_path();
+    fs::create_dir_all(chain_spec_path.parent().unwrap())?;
+    fs::write(&chain_spec_path, serde_json::to_string_pretty(&chain_spec)?)?;
+    output::progress(format_args!("Wrote {}", chain_spec_path.display()));
+
+    let omni_node = install::binary_path("polkadot-omni-node");
+    if !omni_node.exists() {
+        return Err(format!("{} not found, run `dot install` first", omni_node.display()).into());
+    }
+    serve::run_with_chain_spec(&chain_spec_path, args)
+}
+
+fn fork_chain_spec_path() -> PathBuf {
+    config::current().path("chain.spec").with_file_name(FORK_CHAIN_SPEC)
+}
+
+/// Turns a snapshot into a raw chain spec with sudo, authorities and session keys set to `//Alice`.
+pub fn build_chain_spec(snapshot: &Value, metadata: &Metadata) -> Result<Value, Box<dyn Error>> {
+    let mut top = snapshot["top"].as_object().cloned().ok_or("The snapshot has no storage")?;
+
+    // Child trie roots are computed from `childrenDefault` at genesis and must not be set in `top`.
+    let children = snapshot["childrenDefault"].as_object().cloned().unwrap_or_default();
+    let child_keys: Vec<String> = top.keys().filter(|key| is_child_storage_key(key)).cloned().collect();
+    for child_key in child_keys {
+        if !children.contains_key(&unprefixed_child_key(&child_key)?) {
+            return Err(format!("The snapshot lacks the contents of child trie {}, save it again with `dot fork --save-snapshot`", child_key).into());
+        }
+        top.remove(&child_key);
+    }
+
+    for (pallet, item) in RELAY_BOUND_ITEMS {
+        top.remove(&rpc::hex_encode(&query::storage_prefix(pallet, item)));
+    }
+    for change in dev_overrides(metadata, &top)? {
+        output::progress(format_args!("  {}", change.0));
+        top.insert(change.1, json!(change.2));
+    }
+
+    let para_id_key = rpc::hex_encode(&query::storage_prefix("ParachainInfo", "ParachainId"));
+    let para_id = match top.get(&para_id_key).and_then(Value::as_str) {
+        Some(value) => {
+            let bytes: [u8; 4] = rpc::hex_decode(value)?.try_into().map_err(|_| "Invalid ParachainInfo.ParachainId")?;
+            u32::from_le_bytes(bytes)
+        }
+        None => 1000,
+    };
+
+    let chain = snapshot["chain"].as_str().unwrap_or("Chain");
+    Ok(json!({
+        "name": format!("{} (fork)", chain),
+        "id": "dot_fork",
+        "chainType": "Development",
+        "bootNodes": [],
+        "telemetryEndpoints": null,
+        "protocolId": null,
+        "properties": snapshot["properties"],
+        "relay_chain": "westend2",
+        "para_id": para_id,
+        "codeSubstitutes": {},
+        "genesis": { "raw": { "top": top, "childrenDefault": children } },
+    }))
+}
+
+/// Description, hex key and hex value of a storage item replaced in the fork.
+type StorageOverride = (String, String, String);
+
+/// Storage overrides for the pallets present in the runtime.
+fn dev_overrides(metadata: &Metadata, top: &Map<String, Value>) -> Result<Vec<StorageOverride>, Box<dyn Error>> {
+    let alice = Pair::from_uri("//Alice")?;
+    let alice_account = json!(rpc::hex_encode(&alice.account_id()));
+    let mut overrides = Vec::new();
+
+    let mut set = |pallet: &str, item: &str, keys: &[Value], value: &Value| -> Result<(), Box<dyn Error>> {
+        if metadata.pallet(pallet).is_err() {
+            return Ok(());
+        }
+        let (prefix, entry) = query::storage_entry(metadata, pallet, item)?;
+        let key = query::storage_key(metadata, &prefix, entry, keys)?;
+        let mut encoded = Vec::new();
+        metadata.encode_value(storage_value_type(entry), value, &mut encoded)?;
+        overrides.push((format!("{}.{} = {}", pallet, item, value), rpc::hex_encode(&key), rpc::hex_encode(&encoded)));
+        Ok(())
+    };
+
+    set("Sudo", "Key", &[], &alice_account)?;
+    set("Aura", "Authorities", &[], &json!([alice_account]))?;
+    set("CollatorSelection", "Invulnerables", &[], &json!([alice_account]))?;
+    if let Some(session_keys) = session_keys(metadata)? {
+        set("Session", "Validators", &[], &json!([alice_account]))?;
+        set("Session", "QueuedKeys", &[], &json!([[alice_account, session_keys]]))?;
+        set("Session", "NextKeys", std::slice::from_ref(&alice_account), &session_keys)?;
+    }
+    if let Some(account) = funded_account(metadata, top, &alice_account)? {
+        set("System", "Account", std::slice::from_ref(&alice_account), &account)?;
+    }
+    Ok(overrides)
+}
+
+/// `SessionKeys` with every key derived from `//Alice`, using ed25519 for GRANDPA and ecdsa for BEEFY.
+fn session_keys(metadata: &Metadata) -> Result<Option<Value>, Box<dyn Error>> {
+    let entry = match metadata.pallet("Session") {
+        Ok(_) => query::storage_entry(metadata, "Session", "NextKeys")?.1,
+        Err(_) => return Ok(None),
+    };
+    let fields = match metadata.types.resolve(storage_value_type(entry)).map(|ty| &ty.type_def) {
+        Some(scale_info::TypeDef::Composite(composite)) => &composite.fields,
+        _ => return Err("Unexpected Session keys type".into()),
+    };
+
+    let mut keys = Map::new();
+    for field in fields {
+        let name = field.name.clone().unwrap_or_default();
+        let scheme = if name.contains("grandpa") {
+            Scheme::Ed25519
+        } else if name.contains("beefy") {
+            Scheme::Ecdsa
+        } else {
+            Scheme::Sr25519
+        };
+        let public = Pair::from_uri_with_scheme("//Alice", scheme)?.public();
+        keys.insert(name, json!(rpc::hex_encode(&public)));
+    }
+    Ok(Some(Value::Object(keys)))
+}
+
+/// The `System.Account` entry of `account` with its free balance topped up.
+fn funded_account(metadata: &Metadata, top: &Map<String, Value>, account: &Value) -> Result<Option<Value>, Box<dyn Error>> {
+    if metadata.pallet("System").is_err() {
+        return Ok(None);
+    }
+    let (prefix, entry) = query::storage_entry(metadata, "System", "Account")?;
+    let key = rpc::hex_encode(&query::storage_key(metadata, &prefix, entry, std::slice::from_ref(account))?);
+    let encoded = match top.get(&key).and_then(Value::as_str) {
+        Some(value) => rpc::hex_decode(value)?,
+        None => entry.default.clone(),
+    };
+
+    let mut info = query::decode_storage_value(metadata, entry, &encoded)?;
+    if info.pointer("/data/free").is_none() {
+        return Ok(None);
+    }
+    info["data"]["free"] = json!(DEV_BALANCE);
+    if info["providers"].as_u64() == Some(0) {
+        info["providers"] = json!(1);
+    }
+    Ok(Some(info))
+}
+
+fn storage_value_type(entry: &frame_metadata::v14::StorageEntryMetadata<scale_info::form::PortableForm>) -> u32 {
+    match &entry.ty {
+        frame_metadata::v14::StorageEntryType::Plain(ty) => ty.id,
+        frame_metadata::v14::StorageEntryType::Map { value, .. } => value.id,
+    }
+}
@@ -0,0 +1,49 @@
+use std::hash::Hasher;
+use blake2::digest::consts::{U16, U32, U8};
+use blake2::{Blake2b, Blake2b512, Digest};
+use twox_hash::XxHash64;
+
+pub fn blake2_64(data: &[u8]) -> [u8; 8] {
+    Blake2b::<U8>::digest(data).into()
+}
+
+pub fn blake2_128(data: &[u8]) -> [u8; 16] {
+    Blake2b::<U16>::digest(data).into()
+}
+
+pub fn blake2_256(data: &[u8]) -> [u8; 32] {
+    Blake2b::<U32>::digest(data).into()
+}
+
+pub fn blake2_512(data: &[u8]) -> [u8; 64] {
+    Blake2b512::digest(data).into()
+}
+
+pub fn twox_64(data: &[u8]) -> [u8; 8] {
+    let mut hash = [0u8; 8];
+    hash.copy_from_slice(&twox(data, 1));
+    hash
+}
+
+pub fn twox_128(data: &[u8]) -> [u8; 16] {
+    let mut hash = [0u8; 16];
+    hash.copy_from_slice(&twox(data, 2));
+    hash
+}
+
+pub fn twox_256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&twox(data, 4));
+    hash
+}
+
+/// Concatenates `rounds` xxHash64 digests seeded 0, 1, 2... as done by Substrate.
+fn twox(data: &[u8], rounds: u64) -> Vec<u8> {
+    let mut hash = Vec::with_capacity(rounds as usize * 8);
+    for seed in 0..rounds {
+        let mut hasher = XxHash64::with_seed(seed);
+        hasher.write(data);
+        hash.extend_from_slice(&hasher.finish().to_le_bytes());
+    }
+    hash
+}
@@ -1,40 +1,103 @@
-use std::process::Command;
+use std::process::{Command, ExitStatus};
 use std::fs;
 use std::process::Stdio;
 use std::path::{Path, PathBuf};
 use std::error::Error;
+use crate::config;
 use crate::os_check;
+use crate::output;
 
-pub fn install(_template: &str){
-    let mut results: Vec<(Result<(), Box<dyn Error>>, &str)> = Vec::new();
+type StepResult = Result<(), Box<dyn Error>>;
 
-    results.push((install_polkadot(), "$ Polkadot installation"));
-    results.push((install_chain_spec_builder(), "$ Chain spec builder installation"));
-    results.push((install_omni_node(), "$ Omni-node installation"));
-    results.push((run_download_script(), "$ Wasm file download script"));
-    results.push((gen_chain_spec(), "$ Chain spec script"));
+/// Binary installed by `install_try_runtime` the first time `dot try-runtime` runs.
+pub const TRY_RUNTIME: &str = "try-runtime";
+
+/// Binary installed by `install_omni_bencher`.
+pub const OMNI_BENCHER: &str = "frame-omni-bencher";
+
+/// Path of an installed binary in the `paths.binaries` directory of the config.
+pub fn binary_path(name: &str) -> PathBuf {
+    config::current().path("paths.binaries").join(name)
+}
+
+/// Download url of an asset of the polkadot-sdk release set by `sdk.release`, from `sdk.mirror`.
+fn release_url(asset: &str) -> String {
+    let config = config::current();
+    format!("{}/{}/{}", config.string("sdk.mirror").trim_end_matches('/'), config.string("sdk.release"), asset)
+}
+
+/// Downloads `url` with wget into the `paths.cache` directory, shared by all projects, unless it
+/// is already there, then copies it to `destination`.
+fn download(url: &str, destination: &Path) -> Result<ExitStatus, Box<dyn Error>> {
+    let relative = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let cached = config::current().path("paths.cache").join("downloads").join(relative);
+    if cached.exists() {
+        output::progress(format_args!("Using the cached {}", cached.display()));
+    } else {
+        fs::create_dir_all(cached.parent().unwrap())?;
+        // Only complete downloads end up in the cache
+        let partial = PathBuf::from(format!("{}.part", cached.display()));
+        let mut wget = Command::new("wget");
+        wget.arg("-O").arg(&partial).arg(url);
+        output::command(&wget);
+        let result = wget.output().map_err(|e| format!("Failed to execute wget: {}", e))?;
+        if !result.status.success() {
+            let _ = fs::remove_file(&partial);
+            return Ok(result.status);
+        }
+        fs::rename(&partial, &cached)?;
+    }
+    Command::new("cp")
+        .arg(&cached)
+        .arg(destination)
+        .status()
+        .map_err(|e| format!("Failed to copy {}: {}", cached.display(), e).into())
+}
+
+/// Where `download_runtime` puts the wasm of a runtime release asset.
+pub fn runtime_wasm_path(runtime: &str) -> PathBuf {
+    config::current().path("paths.nodes").join(format!("{}.compact.compressed.wasm", runtime))
+}
+
+/// Installs the binaries, runtime and chain spec used by `dot serve`, failing if any step failed.
+pub fn install(_template: &str) -> Result<(), Box<dyn Error>> {
+    let results: Vec<(StepResult, &str)> = vec![
+        (install_polkadot(), "$ Polkadot installation"),
+        (install_chain_spec_builder(), "$ Chain spec builder installation"),
+        (install_omni_node(), "$ Omni-node installation"),
+        (run_download_script(), "$ Wasm file download script"),
+        (gen_chain_spec(), "$ Chain spec script"),
+    ];
 
     println!(" ");
     println!("===========================================================================");
     println!(" ");
+    let mut failed = 0;
     for (result, message) in results {
         match result {
             Ok(_) => println!("{} success ✓", message),
-            Err(_e) => println!("{} failed ✗", message),
+            Err(_e) => {
+                println!("{} failed ✗", message);
+                failed += 1;
+            }
         }
     }
     println!(" ");
     println!("===========================================================================");
     println!(" ");
+    if failed > 0 {
+        return Err(format!("{} installation steps failed", failed).into());
+    }
+    Ok(())
 }
 
 pub fn install_polkadot() -> Result<(), Box<dyn Error>>{
-    println!("Installing Polkadot via curl");
+    output::progress("Installing Polkadot via curl");
 
     let url = "https://raw.githubusercontent.com/paritytech/polkadot-sdk/refs/heads/master/scripts/getting-started.sh"; 
     
     // Run the curl command and pipe its output to bash
-    let curl = Command::new("curl")
+    let mut curl = Command::new("curl")
         .arg("--proto")
         .arg("=https")
         .arg("--tlsv1.2")
@@ -45,181 +108,255 @@ pub fn install_polkadot() -> Result<(), Box<dyn Error>>{
         .expect("Failed to start curl");
     
     let status = Command::new("bash")
-        .stdin(curl.stdout.unwrap())
+        .stdin(curl.stdout.take().unwrap())
         .status()
         .expect("Failed to run bash");
+    let _ = curl.wait();
 
     if !status.success() {
-        return Err(format!("Failed to run Polkadot-sdk").into());
+        return Err("Failed to run Polkadot-sdk".into());
     }
 
-    println!("Polkadot-sdk is now installed.");
+    output::progress("Polkadot-sdk is now installed.");
     Ok(()) 
 }
 
 pub fn install_chain_spec_builder() -> Result<(), Box<dyn Error>> {
-    println!("Installing chain-spec-builder");
+    output::progress("Installing chain-spec-builder");
 
     // Determine the operating system and set the appropriate URL
     let os_info = os_check::get_os_info();
     let url;
     if os_info.as_str() == "linux" {
-        url = "https://github.com/paritytech/polkadot-sdk/releases/download/polkadot-stable2412/chain-spec-builder";
+        url = release_url("chain-spec-builder");
     } else if os_info.as_str() == "macos" {
-        url = "https://binary.xode.net/chain-spec-builder";
+        url = "https://binary.xode.net/chain-spec-builder".to_string();
     } else {
         return Err(format!("Unsupported OS: {}", os_info).into());
     }
 
     // Destination file path
-    let destination = Path::new("./binaries/chain-spec-builder");
+    let destination = &binary_path("chain-spec-builder");
     if destination.exists() {
-        println!("Chain-spec-builder binary is available");
+        output::progress("Chain-spec-builder binary is available");
         return Ok(());
     }
 
     // Check if the 'binaries' directory exists, if not, create it
-    let binaries_dir = Path::new("./binaries");
+    let binaries_dir = &config::current().path("paths.binaries");
     if !binaries_dir.exists() {
-        println!("'binaries' directory does not exist. Creating it...");
+        output::progress("'binaries' directory does not exist. Creating it...");
         if let Err(e) = fs::create_dir_all(binaries_dir) {
             return Err(format!("Failed to create 'binaries' directory: {}", e).into());
         }
     }
 
-    println!("Downloading...");
-    let output = Command::new("wget")
-        .arg("-O")
-        .arg(destination)
-        .arg(url)
-        .output()
-        .map_err(|e| format!("Failed to execute wget: {}", e))?;
+    output::progress("Downloading...");
+    let status = download(&url, destination)?;
 
     // Check if the download was successful
-    if output.status.success() {
-        println!("Download successful: {:?}", destination);
+    if status.success() {
+        output::progress(format_args!("Download successful: {:?}", destination));
 
         let destination_str = destination.to_str().expect("Failed to convert path to str");
 
         let _chmod_status = Command::new("chmod")
-            .args(&["755", destination_str])
+            .args(["755", destination_str])
             .status()
             .expect("Failed to run chmod");
 
-        return Ok(());
+        Ok(())
     } else {
-        return Err(format!(
+        Err(format!(
             "Download failed with exit code: {:?}",
-            output.status.code()
+            status.code()
         )
-        .into());
+        .into())
     }
 }
 
 
 
 pub fn install_omni_node() -> Result<(), Box<dyn Error>> {
-    println!("Installing polkadot-omni-node");
+    output::progress("Installing polkadot-omni-node");
 
     // Determine the operating system and set the appropriate URL
     let os_info = os_check::get_os_info();
     let url;
     if os_info.as_str() == "linux" {
-        url = "https://github.com/paritytech/polkadot-sdk/releases/download/polkadot-stable2412/polkadot-omni-node";
+        url = release_url("polkadot-omni-node");
     } else if os_info.as_str() == "macos" {
-        url = "https://binary.xode.net/polkadot-omni-node";
+        url = "https://binary.xode.net/polkadot-omni-node".to_string();
     } else {
         return Err(format!("Unsupported OS: {}", os_info).into());
 }
 
     // Destination file path
-    let destination = Path::new("./binaries/polkadot-omni-node");
+    let destination = &binary_path("polkadot-omni-node");
     if destination.exists() {
-        println!("Omni-node binary is available");
+        output::progress("Omni-node binary is available");
         return Ok(());
     }
 
     // Check if the 'binaries' directory exists, if not, create it
-    let binaries_dir = Path::new("./binaries");
+    let binaries_dir = &config::current().path("paths.binaries");
     if !binaries_dir.exists() {
-        println!("'binaries' directory does not exist. Creating it...");
+        output::progress("'binaries' directory does not exist. Creating it...");
         if let Err(e) = fs::create_dir_all(binaries_dir) {
             return Err(format!("Failed to create 'binaries' directory: {}", e).into());
         }
     }
 
-    println!("Downloading...");
-    let output = Command::new("wget")
-        .arg("-O")
-        .arg(destination)
-        .arg(url)
-        .output()
-        .map_err(|e| format!("Failed to execute wget: {}", e))?;
+    output::progress("Downloading...");
+    let status = download(&url, destination)?;
 
     // Check if the download was successful
-    if output.status.success() {
-        println!("Download successful: {:?}", destination);
+    if status.success() {
+        output::progress(format_args!("Download successful: {:?}", destination));
 
         let destination_str = destination.to_str().expect("Failed to convert path to str");
 
         let _chmod_status = Command::new("chmod")
-            .args(&["755", destination_str])
+            .args(["755", destination_str])
             .status()
             .expect("Failed to run chmod");
 
+        Ok(())
+    } else {
+        Err(format!(
+            "Download failed with exit code: {:?}",
+            status.code()
+        )
+        .into())
+    }
+}
+
+pub fn install_try_runtime() -> Result<(), Box<dyn Error>> {
+    output::progress("Installing try-runtime");
+
+    // try-runtime-cli only publishes a static linux binary, on macOS it has to be built with cargo
+    let os_info = os_check::get_os_info();
+    match os_info.as_str() {
+        "linux" => download_binary(
+            "https://github.com/paritytech/try-runtime-cli/releases/download/v0.8.0/try-runtime-x86_64-unknown-linux-musl",
+            &binary_path(TRY_RUNTIME),
+        ),
+        "macos" => Err("No try-runtime binary for macOS, install it with: cargo install --git https://github.com/paritytech/try-runtime-cli --locked".into()),
+        _ => Err(format!("Unsupported OS: {}", os_info).into()),
+    }
+}
+
+pub fn install_omni_bencher() -> Result<(), Box<dyn Error>> {
+    output::progress("Installing frame-omni-bencher");
+
+    let os_info = os_check::get_os_info();
+    match os_info.as_str() {
+        "linux" => download_binary(&release_url(OMNI_BENCHER), &binary_path(OMNI_BENCHER)),
+        "macos" => Err("No frame-omni-bencher binary for macOS, install it with: cargo install frame-omni-bencher --locked".into()),
+        _ => Err(format!("Unsupported OS: {}", os_info).into()),
+    }
+}
+
+/// Downloads an executable into the binaries directory unless it is already there.
+fn download_binary(url: &str, destination: &Path) -> Result<(), Box<dyn Error>> {
+    if destination.exists() {
+        output::progress(format_args!("{} binary is available", destination.display()));
         return Ok(());
+    }
+
+    // Check if the 'binaries' directory exists, if not, create it
+    let binaries_dir = &config::current().path("paths.binaries");
+    if !binaries_dir.exists() {
+        output::progress("'binaries' directory does not exist. Creating it...");
+        if let Err(e) = fs::create_dir_all(binaries_dir) {
+            return Err(format!("Failed to create 'binaries' directory: {}", e).into());
+        }
+    }
+
+    output::progress("Downloading...");
+    let status = download(url, destination)?;
+
+    // Check if the download was successful
+    if status.success() {
+        output::progress(format_args!("Download successful: {:?}", destination));
+
+        let _chmod_status = Command::new("chmod")
+            .arg("755")
+            .arg(destination)
+            .status()
+            .expect("Failed to run chmod");
+
+        Ok(())
     } else {
-        return Err(format!(
+        // Do not leave an empty file behind, it would be mistaken for an installed binary
+        let _ = fs::remove_file(destination);
+        Err(format!(
             "Download failed with exit code: {:?}",
-            output.status.code()
+            status.code()
         )
-        .into());
+        .into())
     }
 }
 
 pub fn run_download_script() -> Result<(), Box<dyn Error>>{
-    let url = "https://github.com/paritytech/polkadot-sdk/releases/download/polkadot-stable2412/asset_hub_westend_runtime.compact.compressed.wasm";
-    
-    // Destination file path
-    let destination = Path::new("./nodes/asset_hub_westend_runtime.compact.compressed.wasm");
+    download_runtime(&config::current().string("chain.runtime")).map(|_| ())
+}
+
+/// Downloads the wasm of a runtime of the `sdk.release` unless it is already there and returns its path.
+pub fn download_runtime(runtime: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let destination = &runtime_wasm_path(runtime);
+    let url = release_url(&destination.file_name().unwrap().to_string_lossy());
+
     if destination.exists() {
-        println!("Wasm file is available");
-        return Ok(())
+        output::progress("Wasm file is available");
+        return Ok(destination.clone())
     }
     
     // Check if the 'binaries' directory exists, if not, create it
-    let nodes_dir = Path::new("./nodes");
+    let nodes_dir = &config::current().path("paths.nodes");
     if !nodes_dir.exists() {
-        println!("'nodes' directory does not exist. Creating it...");
+        output::progress("'nodes' directory does not exist. Creating it...");
         if let Err(e) = fs::create_dir_all(nodes_dir) {
             return Err(format!("Failed to create 'nodes' directory: {}", e).into());
         }
     }
     
-    println!("Downloading...");
-    let output = Command::new("wget")
-        .arg("-O")
-        .arg(destination)
-        .arg(url)
-        .output()
-        .map_err(|e| format!("Failed to execute wget: {}", e))?;
+    output::progress("Downloading...");
+    let status = download(&url, destination)?;
     
     // Check if the download was successful
-    if output.status.success() {
-        println!("Download successful: {:?}", destination);
-        return Ok(())
+    if status.success() {
+        output::progress(format_args!("Download successful: {:?}", destination));
+        Ok(destination.clone())
     } else {
-        return Err(format!(
+        Err(format!(
             "Download failed with exit code: {:?}",
-            output.status.code()
-        ).into());
+            status.code()
+        ).into())
     }
 }
 
 
+/// Generates the `chain.spec` chain spec from a runtime wasm, or else from a runtime of the
+/// release, by default `chain.runtime`, and returns its path.
+pub fn generate_chain_spec(wasm: Option<&Path>, runtime: Option<&str>) -> Result<PathBuf, Box<dyn Error>> {
+    install_chain_spec_builder()?;
+    let wasm = match wasm {
+        Some(wasm) => wasm.to_path_buf(),
+        None => download_runtime(&runtime.map(String::from).unwrap_or_else(|| config::current().string("chain.runtime")))?,
+    };
+    gen_chain_spec_from_wasm(&wasm)?;
+    Ok(config::current().path("chain.spec"))
+}
+
 pub fn gen_chain_spec() -> Result<(), Box<dyn Error>>{
-    let wasm_source_path =  Path::new("./nodes/asset_hub_westend_runtime.compact.compressed.wasm");
-    let chain_spec_builder_path = Path::new("./binaries/chain-spec-builder");
+    gen_chain_spec_from_wasm(&runtime_wasm_path(&config::current().string("chain.runtime")))
+}
+
+/// Generates the `chain.spec` chain spec for the given runtime wasm.
+pub fn gen_chain_spec_from_wasm(wasm_source_path: &Path) -> Result<(), Box<dyn Error>>{
+    let config = config::current();
+    let chain_spec_builder_path = &binary_path("chain-spec-builder");
 
     // Check if the WASM file exists
     if !wasm_source_path.exists() {
@@ -228,43 +365,43 @@ pub fn gen_chain_spec() -> Result<(), Box<dyn Error>>{
     }
 
     let chmod_status = Command::new("chmod")
-        .args(&["+r", wasm_source_path.to_str().unwrap()])
+        .args(["+r", wasm_source_path.to_str().unwrap()])
         .status()
         .expect("Failed to run chmod");
 
     if !chmod_status.success() {
         eprintln!("Failed to add read permissions to the WASM file");
-        return Err(format!("Failed to add read permissions to the WASM file").into());
+        return Err("Failed to add read permissions to the WASM file".into());
     }
 
     // Add execute permissions to the chain-spec-builder binary
     let chmod_chain_spec_status = Command::new("chmod")
-        .args(&["+x", chain_spec_builder_path.to_str().unwrap()])
+        .args(["+x", chain_spec_builder_path.to_str().unwrap()])
         .status()
         .expect("Failed to run chmod on chain-spec-builder");
 
     if !chmod_chain_spec_status.success() {
         eprintln!("Failed to add execute permissions to the chain-spec-builder");
-        return Err(format!("Failed to add execute permissions to the chain-spec-builder").into());
+        return Err("Failed to add execute permissions to the chain-spec-builder".into());
     }
 
     // let chain_spec_status = Command::new("chain-spec-builder")
-    let chain_spec_status = Command::new("./binaries/chain-spec-builder")
-        .args(&[
-            "create",
-            "-t", "development",
-            "--relay-chain", "westend2",
-            "--para-id", "1000",
-            "--runtime", wasm_source_path.to_str().unwrap(),
-            "named-preset", "development"
-        ])
-        .status()
-        .expect("Failed to run chain-spec-builder");
+    let mut chain_spec_builder = Command::new(chain_spec_builder_path);
+    chain_spec_builder.args([
+        "create",
+        "-t", "development",
+        "--relay-chain", &config.string("chain.relay"),
+        "--para-id", &config.integer("chain.para-id").to_string(),
+        "--runtime", wasm_source_path.to_str().unwrap(),
+        "named-preset", "development"
+    ]);
+    output::command(&chain_spec_builder);
+    let chain_spec_status = chain_spec_builder.status().expect("Failed to run chain-spec-builder");
 
     if !chain_spec_status.success() {
-        return Err(format!("Failed to run chain-spec-builder").into());
+        return Err("Failed to run chain-spec-builder".into());
     }
-    let _ = move_chain_spec();
+    move_chain_spec()?;
     Ok(()) 
 }
 
@@ -286,11 +423,11 @@ pub fn move_chain_spec() -> Result<(), String>{
     let chain_spec_source_path = match chain_spec_source_path {
         Some(path) => path,
         None => {
-            return Err(format!("chain_spec.json not found in the specified directories."));
+            return Err("chain_spec.json not found in the specified directories.".to_string());
         }
     };
     
-    let chain_spec_destination_path = Path::new("./chain-specs/chain_spec.json");
+    let chain_spec_destination_path = &config::current().path("chain.spec");
 
     // Create the chain-specs directory if it does not exist
     if let Err(e) = fs::create_dir_all(chain_spec_destination_path.parent().unwrap()) {
@@ -298,7 +435,7 @@ pub fn move_chain_spec() -> Result<(), String>{
     }
     
     // Move the chain_spec.json file to the chain-specs directory
-    if let Err(e) = fs::rename(&chain_spec_source_path, &chain_spec_destination_path) {
+    if let Err(e) = fs::rename(&chain_spec_source_path, chain_spec_destination_path) {
         return Err(format!("Failed to move chain_spec.json: {}", e));
     }
     Ok(())
@@ -0,0 +1,307 @@
+use std::error::Error;
+use bip39::Mnemonic;
+use k256::ecdsa;
+use parity_scale_codec::Encode;
+use schnorrkel::derive::{ChainCode, Derivation};
+use schnorrkel::{ExpansionMode, Keypair, MiniSecretKey};
+use sha2::Sha512;
+use crate::hashing;
+use crate::rpc;
+
+/// Phrase behind the well-known development accounts (`//Alice`, `//Bob`, ...).
+pub const DEV_PHRASE: &str = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+
+/// Generic Substrate SS58 prefix.
+pub const DEFAULT_SS58_PREFIX: u16 = 42;
+
+const SIGNING_CONTEXT: &[u8] = b"substrate";
+
+/// Signature schemes supported by Substrate based chains.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Scheme {
+    Sr25519,
+    Ed25519,
+    Ecdsa,
+}
+
+impl Scheme {
+    pub fn parse(name: &str) -> Result<Scheme, Box<dyn Error>> {
+        match name.to_lowercase().as_str() {
+            "sr25519" => Ok(Scheme::Sr25519),
+            "ed25519" => Ok(Scheme::Ed25519),
+            "ecdsa" => Ok(Scheme::Ecdsa),
+            _ => Err(format!("Unknown key scheme {}, expected sr25519, ed25519 or ecdsa", name).into()),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scheme::Sr25519 => "sr25519",
+            Scheme::Ed25519 => "ed25519",
+            Scheme::Ecdsa => "ecdsa",
+        }
+    }
+}
+
+/// A key pair that can sign extrinsics.
+pub enum Pair {
+    Sr25519(Keypair),
+    Ed25519(ed25519_dalek::SigningKey),
+    Ecdsa(ecdsa::SigningKey),
+}
+
+enum Junction {
+    Hard([u8; 32]),
+    Soft([u8; 32]),
+}
+
+/// A parsed secret URI: `<phrase or 0x seed>//hard/soft///password`.
+struct SecretUri {
+    phrase: String,
+    junctions: Vec<Junction>,
+    password: String,
+}
+
+impl Pair {
+    /// Creates an sr25519 key pair from a secret URI such as `//Alice` or `<mnemonic>//stash`.
+    pub fn from_uri(uri: &str) -> Result<Pair, Box<dyn Error>> {
+        Pair::from_uri_with_scheme(uri, Scheme::Sr25519)
+    }
+
+    /// Creates a key pair of the given scheme. ed25519 and ecdsa keys only support hard derivation.
+    pub fn from_uri_with_scheme(uri: &str, scheme: Scheme) -> Result<Pair, Box<dyn Error>> {
+        let uri = parse_uri(uri)?;
+        let seed = seed_from_phrase(&uri.phrase, &uri.password)?;
+
+        if scheme == Scheme::Sr25519 {
+            let mut keypair = MiniSecretKey::from_bytes(&seed)
+                .map_err(|e| format!("Invalid seed: {}", e))?
+                .expand_to_keypair(ExpansionMode::Ed25519);
+            for junction in &uri.junctions {
+                keypair = match junction {
+                    Junction::Hard(cc) => keypair
+                        .hard_derive_mini_secret_key(Some(ChainCode(*cc)), b"")
+                        .0
+                        .expand_to_keypair(ExpansionMode::Ed25519),
+                    Junction::Soft(cc) => keypair.derived_key_simple(ChainCode(*cc), []).0,
+                };
+            }
+            return Ok(Pair::Sr25519(keypair));
+        }
+
+        let context = if scheme == Scheme::Ed25519 { "Ed25519HDKD" } else { "Secp256k1HDKD" };
+        let mut secret = seed;
+        for junction in &uri.junctions {
+            secret = match junction {
+                Junction::Hard(cc) => hashing::blake2_256(&(context, secret, cc).encode()),
+                Junction::Soft(_) => {
+                    return Err(format!("Soft derivation is not supported for {} keys", scheme.name()).into())
+                }
+            };
+        }
+        match scheme {
+            Scheme::Ed25519 => Ok(Pair::Ed25519(ed25519_dalek::SigningKey::from_bytes(&secret))),
+            _ => Ok(Pair::Ecdsa(
+                ecdsa::SigningKey::from_bytes(&secret.into()).map_err(|e| format!("Invalid ecdsa seed: {}", e))?,
+            )),
+        }
+    }
+
+    pub fn scheme(&self) -> Scheme {
+        match self {
+            Pair::Sr25519(_) => Scheme::Sr25519,
+            Pair::Ed25519(_) => Scheme::Ed25519,
+            Pair::Ecdsa(_) => Scheme::Ecdsa,
+        }
+    }
+
+    /// Raw public key; 33 bytes (compressed) for ecdsa, 32 bytes otherwise.
+    pub fn public(&self) -> Vec<u8> {
+        match self {
+            Pair::Sr25519(keypair) => keypair.public.to_bytes().to_vec(),
+            Pair::Ed25519(key) => key.verifying_key().to_bytes().to_vec(),
+            Pair::Ecdsa(key) => key.verifying_key().to_encoded_point(true).as_bytes().to_vec(),
+        }
+    }
+
+    pub fn account_id(&self) -> [u8; 32] {
+        account_from_public(&self.public()).expect("public keys are 32 or 33 bytes long")
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            Pair::Sr25519(keypair) => keypair.sign_simple(SIGNING_CONTEXT, message).to_bytes().to_vec(),
+            Pair::Ed25519(key) => {
+                use ed25519_dalek::Signer;
+                key.sign(message).to_bytes().to_vec()
+            }
+            Pair::Ecdsa(key) => {
+                let (signature, recovery_id) = key
+                    .sign_prehash_recoverable(&hashing::blake2_256(message))
+                    .expect("signing a 32 byte prehash cannot fail");
+                let mut bytes = signature.to_bytes().to_vec();
+                bytes.push(recovery_id.to_byte());
+                bytes
+            }
+        }
+    }
+
+    /// Name of the matching `MultiSignature` variant.
+    pub fn signature_variant(&self) -> &'static str {
+        match self {
+            Pair::Sr25519(_) => "Sr25519",
+            Pair::Ed25519(_) => "Ed25519",
+            Pair::Ecdsa(_) => "Ecdsa",
+        }
+    }
+}
+
+/// Account id of a public key; ecdsa accounts are the blake2 hash of the 33 byte public key.
+pub fn account_from_public(public: &[u8]) -> Result<[u8; 32], Box<dyn Error>> {
+    match public.len() {
+        32 => Ok(public.try_into().unwrap()),
+        33 => Ok(hashing::blake2_256(public)),
+        len => Err(format!("Invalid public key length {}", len).into()),
+    }
+}
+
+/// Generates a new BIP39 mnemonic with 12, 15, 18, 21 or 24 words.
+pub fn generate_phrase(words: usize) -> Result<String, Box<dyn Error>> {
+    if !matches!(words, 12 | 15 | 18 | 21 | 24) {
+        return Err("The number of words must be 12, 15, 18, 21 or 24".into());
+    }
+    let mut entropy = vec![0u8; words / 3 * 4];
+    getrandom::getrandom(&mut entropy)?;
+    Ok(Mnemonic::from_entropy(&entropy)?.to_string())
+}
+
+/// The 32 byte secret seed of a secret URI, before applying derivation junctions.
+pub fn secret_seed(uri: &str) -> Result<[u8; 32], Box<dyn Error>> {
+    let uri = parse_uri(uri)?;
+    seed_from_phrase(&uri.phrase, &uri.password)
+}
+
+fn parse_uri(uri: &str) -> Result<SecretUri, Box<dyn Error>> {
+    let (rest, password) = match uri.find("///") {
+        Some(index) => (&uri[..index], &uri[index + 3..]),
+        None => (uri, ""),
+    };
+    let (phrase, mut path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, ""),
+    };
+
+    let mut junctions = Vec::new();
+    while !path.is_empty() {
+        let hard = path.starts_with("//");
+        path = path.trim_start_matches('/');
+        let end = path.find('/').unwrap_or(path.len());
+        let name = &path[..end];
+        if name.is_empty() {
+            return Err(format!("Invalid derivation path in secret URI: {}", uri).into());
+        }
+        let cc = chain_code(name);
+        junctions.push(if hard { Junction::Hard(cc) } else { Junction::Soft(cc) });
+        path = &path[end..];
+    }
+
+    let phrase = phrase.trim();
+    Ok(SecretUri {
+        phrase: if phrase.is_empty() { DEV_PHRASE.to_string() } else { phrase.to_string() },
+        junctions,
+        password: password.to_string(),
+    })
+}
+
+/// Chain code of a junction: a SCALE encoded number or string, hashed when longer than 32 bytes.
+fn chain_code(name: &str) -> [u8; 32] {
+    let encoded = match name.parse::<u64>() {
+        Ok(number) => number.encode(),
+        Err(_) => name.encode(),
+    };
+    if encoded.len() > 32 {
+        return hashing::blake2_256(&encoded);
+    }
+    let mut cc = [0u8; 32];
+    cc[..encoded.len()].copy_from_slice(&encoded);
+    cc
+}
+
+/// Returns the 32 byte mini secret for a `0x` seed or a BIP39 mnemonic.
+fn seed_from_phrase(phrase: &str, password: &str) -> Result<[u8; 32], Box<dyn Error>> {
+    let mut seed = [0u8; 32];
+    if phrase.starts_with("0x") {
+        let bytes = rpc::hex_decode(phrase)?;
+        if bytes.len() != 32 {
+            return Err("A hex seed must be 32 bytes long".into());
+        }
+        seed.copy_from_slice(&bytes);
+        return Ok(seed);
+    }
+
+    let mnemonic = Mnemonic::parse_normalized(phrase).map_err(|e| format!("Invalid secret phrase: {}", e))?;
+    let salt = format!("mnemonic{}", password);
+    let mut full_seed = [0u8; 64];
+    pbkdf2::pbkdf2_hmac::<Sha512>(&mnemonic.to_entropy(), salt.as_bytes(), 2048, &mut full_seed);
+    seed.copy_from_slice(&full_seed[..32]);
+    Ok(seed)
+}
+
+/// Encodes an account as an SS58 address. Prefixes are 14 bit, so at most 16383.
+pub fn ss58_encode(account: &[u8], prefix: u16) -> Result<String, Box<dyn Error>> {
+    let mut bytes = match prefix {
+        0..=63 => vec![prefix as u8],
+        64..=16383 => vec![
+            ((prefix & 0b0000_0000_1111_1100) as u8 >> 2) | 0b0100_0000,
+            (prefix >> 8) as u8 | ((prefix & 0b0000_0000_0000_0011) as u8) << 6,
+        ],
+        _ => return Err(format!("Invalid SS58 prefix {}: must be at most 16383", prefix).into()),
+    };
+    bytes.extend_from_slice(account);
+    let checksum = ss58_checksum(&bytes);
+    bytes.extend_from_slice(&checksum[..2]);
+    Ok(bs58::encode(bytes).into_string())
+}
+
+/// Decodes an SS58 address into its 32 byte account id.
+pub fn ss58_decode(address: &str) -> Result<[u8; 32], Box<dyn Error>> {
+    let bytes = bs58::decode(address)
+        .into_vec()
+        .map_err(|e| format!("Invalid SS58 address {}: {}", address, e))?;
+    let prefix_len = match bytes.first() {
+        Some(0..=63) => 1,
+        Some(64..=127) => 2,
+        _ => return Err(format!("Invalid SS58 prefix in {}", address).into()),
+    };
+    if bytes.len() != prefix_len + 32 + 2 {
+        return Err(format!("Invalid SS58 address length for {}", address).into());
+    }
+
+    let checksum = ss58_checksum(&bytes[..prefix_len + 32]);
+    if bytes[prefix_len + 32..] != checksum[..2] {
+        return Err(format!("Invalid SS58 checksum for {}", address).into());
+    }
+    let mut account = [0u8; 32];
+    account.copy_from_slice(&bytes[prefix_len..prefix_len + 32]);
+    Ok(account)
+}
+
+fn ss58_checksum(data: &[u8]) -> [u8; 64] {
+    let mut preimage = b"SS58PRE".to_vec();
+    preimage.extend_from_slice(data);
+    hashing::blake2_512(&preimage)
+}
+
+/// Parses an account given as SS58 address, `0x` hex or secret URI such as `//Bob`.
+pub fn parse_account(value: &str) -> Result<[u8; 32], Box<dyn Error>> {
+    if value.starts_with("0x") {
+        let bytes = rpc::hex_decode(value)?;
+        return bytes
+            .try_into()
+            .map_err(|_| format!("Account {} is not 32 bytes long", value).into());
+    }
+    if value.starts_with("//") {
+        return Ok(Pair::from_uri(value)?.account_id());
+    }
+    ss58_decode(value)
+}
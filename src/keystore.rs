@@ -0,0 +1,270 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::XChaCha20Poly1305;
+use serde_json::{json, Value};
+use sha2::Sha512;
+use crate::keys::{self, Pair, Scheme};
+use crate::output;
+use crate::rpc::{self, RpcClient};
+
+/// Default location of the local keystore, next to `./binaries` and `./nodes`.
+pub const DEFAULT_KEYSTORE_PATH: &str = "./keystore/keys.json";
+
+/// Environment variable read for the keystore password before prompting.
+pub const PASSWORD_ENV: &str = "DOT_KEYSTORE_PASSWORD";
+
+const KDF_ITERATIONS: u32 = 210_000;
+
+/// A keystore file holding secret URIs encrypted with a password.
+///
+/// Public keys are stored in the clear so `dot key list` does not need the password.
+pub struct Keystore {
+    path: PathBuf,
+    keys: Vec<Value>,
+}
+
+impl Keystore {
+    pub fn open(path: &Path) -> Result<Keystore, Box<dyn Error>> {
+        let keys = match fs::read_to_string(path) {
+            Ok(contents) => {
+                let file: Value = serde_json::from_str(&contents)
+                    .map_err(|e| format!("Invalid keystore {}: {}", path.display(), e))?;
+                file["keys"].as_array().cloned().unwrap_or_default()
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read keystore {}: {}", path.display(), e).into()),
+        };
+        Ok(Keystore { path: path.to_path_buf(), keys })
+    }
+
+    /// Encrypts the secret URI of a key and saves it under `name`.
+    pub fn add(&mut self, name: &str, suri: &str, pair: &Pair, password: &str) -> Result<(), Box<dyn Error>> {
+        if self.find(name).is_some() {
+            return Err(format!("A key named {} already exists in {}", name, self.path.display()).into());
+        }
+
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 24];
+        getrandom::getrandom(&mut salt)?;
+        getrandom::getrandom(&mut nonce)?;
+        let cipher = XChaCha20Poly1305::new(&derive_key(password, &salt, KDF_ITERATIONS).into());
+        let ciphertext = cipher
+            .encrypt(&nonce.into(), suri.as_bytes())
+            .map_err(|_| "Failed to encrypt key")?;
+
+        self.keys.push(json!({
+            "name": name,
+            "scheme": pair.scheme().name(),
+            "public": rpc::hex_encode(&pair.public()),
+            "crypto": {
+                "kdf": "pbkdf2-sha512",
+                "iterations": KDF_ITERATIONS,
+                "salt": rpc::hex_encode(&salt),
+                "cipher": "xchacha20poly1305",
+                "nonce": rpc::hex_encode(&nonce),
+                "ciphertext": rpc::hex_encode(&ciphertext),
+            },
+        }));
+        self.save()
+    }
+
+    /// Decrypts a stored key, returning its secret URI and scheme.
+    pub fn unlock(&self, name: &str, password: &str) -> Result<(String, Scheme), Box<dyn Error>> {
+        let key = self
+            .find(name)
+            .ok_or_else(|| format!("No key named {} in {}", name, self.path.display()))?;
+        let crypto = &key["crypto"];
+        let field = |name: &str| -> Result<Vec<u8>, Box<dyn Error>> {
+            rpc::hex_decode(crypto[name].as_str().ok_or("Corrupted keystore entry")?)
+        };
+
+        let iterations = crypto["iterations"].as_u64().unwrap_or(KDF_ITERATIONS as u64) as u32;
+        let nonce: [u8; 24] = field("nonce")?.try_into().map_err(|_| "Corrupted keystore entry")?;
+        let cipher = XChaCha20Poly1305::new(&derive_key(password, &field("salt")?, iterations).into());
+        let suri = cipher
+            .decrypt(&nonce.into(), field("ciphertext")?.as_slice())
+            .map_err(|_| format!("Wrong password for key {}", name))?;
+        let scheme = Scheme::parse(key["scheme"].as_str().unwrap_or_default())?;
+        Ok((String::from_utf8(suri)?, scheme))
+    }
+
+    fn find(&self, name: &str) -> Option<&Value> {
+        self.keys.iter().find(|key| key["name"].as_str() == Some(name))
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = serde_json::to_string_pretty(&json!({ "version": 1, "keys": self.keys }))?;
+
+        // Written to a private temporary file first, so the keystore is never readable by others
+        // and a failed write does not truncate the existing keys.
+        let temp_path = self.path.with_extension("json.tmp");
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&temp_path)?;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+fn derive_key(password: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha512>(password.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+/// Returns `DOT_KEYSTORE_PASSWORD`, else prompts for the password.
+pub fn password(confirm: bool) -> Result<String, Box<dyn Error>> {
+    if let Ok(password) = env::var(PASSWORD_ENV) {
+        return Ok(password);
+    }
+    let password = rpassword::prompt_password("Keystore password: ")?;
+    if confirm && rpassword::prompt_password("Repeat password: ")? != password {
+        return Err("Passwords do not match".into());
+    }
+    Ok(password)
+}
+
+/// `dot key generate`: creates a new mnemonic, prints the key and optionally stores it.
+pub fn generate(scheme: Scheme, words: usize, prefix: u16, save: Option<(&Path, &str)>, json: bool) -> Result<(), Box<dyn Error>> {
+    // Opened first, so an unreadable keystore fails before a phrase is printed that cannot be saved.
+    let keystore = save.map(|(path, _)| Keystore::open(path)).transpose()?;
+    let phrase = keys::generate_phrase(words)?;
+    let pair = Pair::from_uri_with_scheme(&phrase, scheme)?;
+    print_pair(&phrase, &pair, prefix, json)?;
+
+    if let (Some(mut keystore), Some((path, name))) = (keystore, save) {
+        keystore.add(name, &phrase, &pair, &password(true)?)?;
+        output::progress(format_args!("\nSaved key {} to {}", name, path.display()));
+    }
+    Ok(())
+}
+
+/// `dot key inspect`: prints the keys behind a secret URI, or the account of a public key with `public`.
+pub fn inspect(uri: &str, scheme: Scheme, prefix: u16, public: bool, json: bool) -> Result<(), Box<dyn Error>> {
+    if !public {
+        let pair = Pair::from_uri_with_scheme(uri, scheme)?;
+        return print_pair(uri, &pair, prefix, json);
+    }
+
+    let public_key = if uri.starts_with("0x") {
+        rpc::hex_decode(uri)?
+    } else {
+        keys::ss58_decode(uri)?.to_vec()
+    };
+    let account = keys::account_from_public(&public_key)?;
+    if json {
+        let key = json!({
+            "network_id": prefix,
+            "public_key": rpc::hex_encode(&public_key),
+            "account_id": rpc::hex_encode(&account),
+            "ss58_address": keys::ss58_encode(&account, prefix)?,
+        });
+        println!("{}", serde_json::to_string_pretty(&key)?);
+        return Ok(());
+    }
+    println!("Network ID/Version: {}", prefix);
+    println!("  Public key (hex):   {}", rpc::hex_encode(&public_key));
+    println!("  Account ID:         {}", rpc::hex_encode(&account));
+    println!("  SS58 Address:       {}", keys::ss58_encode(&account, prefix)?);
+    Ok(())
+}
+
+fn print_pair(uri: &str, pair: &Pair, prefix: u16, json: bool) -> Result<(), Box<dyn Error>> {
+    if json {
+        return print_pair_json(uri, pair, prefix);
+    }
+    if uri.contains(' ') {
+        println!("Secret phrase:       {}", uri);
+    } else {
+        println!("Secret Key URI `{}` is account:", uri);
+    }
+    println!("  Network ID:        {}", prefix);
+    println!("  Scheme:            {}", pair.scheme().name());
+    if !uri.contains('/') {
+        println!("  Secret seed:       {}", rpc::hex_encode(&keys::secret_seed(uri)?));
+    }
+    println!("  Public key (hex):  {}", rpc::hex_encode(&pair.public()));
+    println!("  Account ID:        {}", rpc::hex_encode(&pair.account_id()));
+    println!("  Public key (SS58): {}", keys::ss58_encode(&pair.public(), prefix)?);
+    println!("  SS58 Address:      {}", keys::ss58_encode(&pair.account_id(), prefix)?);
+    Ok(())
+}
+
+fn print_pair_json(uri: &str, pair: &Pair, prefix: u16) -> Result<(), Box<dyn Error>> {
+    let mut key = json!({
+        "network_id": prefix,
+        "scheme": pair.scheme().name(),
+        "public_key": rpc::hex_encode(&pair.public()),
+        "account_id": rpc::hex_encode(&pair.account_id()),
+        "public_key_ss58": keys::ss58_encode(&pair.public(), prefix)?,
+        "ss58_address": keys::ss58_encode(&pair.account_id(), prefix)?,
+    });
+    match uri.contains(' ') {
+        true => key["secret_phrase"] = json!(uri),
+        false => key["secret_uri"] = json!(uri),
+    }
+    if !uri.contains('/') {
+        key["secret_seed"] = json!(rpc::hex_encode(&keys::secret_seed(uri)?));
+    }
+    println!("{}", serde_json::to_string_pretty(&key)?);
+    Ok(())
+}
+
+/// `dot key insert`: adds a key to the keystore of a running node via `author_insertKey`.
+pub fn insert(rpc_url: &str, key_type: &str, suri: &str, scheme: Scheme) -> Result<(), Box<dyn Error>> {
+    if key_type.len() != 4 {
+        return Err(format!("Key type must be 4 characters long, e.g. aura, gran, babe, imon: {}", key_type).into());
+    }
+    let pair = Pair::from_uri_with_scheme(suri, scheme)?;
+    let public = rpc::hex_encode(&pair.public());
+
+    let mut client = RpcClient::connect(rpc_url)?;
+    client.request("author_insertKey", json!([key_type, suri, public]))?;
+    println!("Inserted {} key {} ({}) into the node at {}", key_type, public, scheme.name(), rpc_url);
+    Ok(())
+}
+
+/// `dot key list`: prints the keys stored in the local keystore.
+pub fn list(path: &Path, prefix: u16, json: bool) -> Result<(), Box<dyn Error>> {
+    let keystore = Keystore::open(path)?;
+    if json {
+        let mut keys = Vec::new();
+        for key in &keystore.keys {
+            let account = keys::account_from_public(&rpc::hex_decode(key["public"].as_str().unwrap_or_default())?)?;
+            keys.push(json!({ "name": key["name"], "scheme": key["scheme"], "address": keys::ss58_encode(&account, prefix)? }));
+        }
+        println!("{}", serde_json::to_string_pretty(&keys)?);
+        return Ok(());
+    }
+    if keystore.keys.is_empty() {
+        println!("No keys in {}", path.display());
+        return Ok(());
+    }
+
+    println!("{:<20} {:<8} ADDRESS", "NAME", "SCHEME");
+    for key in &keystore.keys {
+        let public = rpc::hex_decode(key["public"].as_str().unwrap_or_default())?;
+        let account = keys::account_from_public(&public)?;
+        println!(
+            "{:<20} {:<8} {}",
+            key["name"].as_str().unwrap_or_default(),
+            key["scheme"].as_str().unwrap_or_default(),
+            keys::ss58_encode(&account, prefix)?
+        );
+    }
+    Ok(())
+}
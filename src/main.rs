@@ -1,94 +1,292 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
 use std::process;
-use clap::{App, Command, SubCommand};
+use clap::{CommandFactory, ErrorKind, Parser};
+use cli::{Cli, Commands};
 
+mod cli;
+mod output;
 mod serve;
 mod template;
 mod install;
 mod os_check;
+mod rpc;
+mod wasm;
+mod metadata;
+mod codec;
+mod hashing;
+mod keys;
+mod events;
+mod tx;
+mod query;
+mod watch;
+mod keystore;
+mod collator;
+mod para;
+mod runtime;
+mod try_runtime;
+mod bench;
+mod fork;
+mod seal;
+mod scaffold;
+mod build;
+mod pallet;
+mod config;
 mod test;
 
+type HandlerResult = Result<(), Box<dyn Error>>;
+
+/// Exit code of a command that failed. Invalid arguments exit with 2, like clap.
+const FAILURE: i32 = 1;
 
 fn main() {
-    let matches = App::new("polkadot-cli")
-        .version("0.1.0")
-        .author("Author Name <author@example.com>")
-        .about("CLI tool for Polkadot")
-        .usage("dot [SUBCOMMAND] --template [ minimal | solochain | parachain ]")
-        .subcommand(
-            SubCommand::with_name("install")
-                .about("Installs the polkadot-sdk")
-                .arg(
-                    clap::Arg::new("template")
-                        .help("The template to use for installation")
-                        .long("template")
-                        .takes_value(true),
-                )
-                .arg(
-                    clap::Arg::new("chain")
-                        .help("Use --template to install and run a template")
-                        .required(false)
-                        .index(1), // Adding the possible values,
-                )
-                .arg(
-                    clap::Arg::new("args")
-                        .help("Template available arguments
-                        minimal 
-                        parachain 
-                        solochain
-                        ")
-                        .multiple(true)
-                        .last(true),
-                ),  
-        )
-        .subcommand(
-            Command::new("serve")
-                .about("Serve omni-node using westend asset hub runtime")
-                .arg(
-                    clap::Arg::new("chain")
-                        .help("The name of the component to install")
-                        .required(false)
-                        .index(1),
-                )
-        )
-    .get_matches();
-
-
-    match matches.subcommand() {
-        Some(("install", sub_matches)) => handle_install(sub_matches),
-        Some(("serve", sub_matches)) => handle_serve(sub_matches),
-        _ => {
-            eprintln!("No valid subcommand provided. Use --help for more information.");
-            process::exit(1);
+    let cli = Cli::parse();
+    if cli.output == Some(cli::OutputFormat::Json) && !cli.command.has_json_output() {
+        Cli::command().error(ErrorKind::ArgumentConflict, "--output json is not supported by this command").exit();
+    }
+    config::use_options(cli.config_options());
+
+    let result = match cli.command {
+        Commands::Install { template, git_ref, omni, args } => handle_install(template, git_ref, omni, &args),
+        Commands::Serve { runtime_crate, watch, seal, args } => handle_serve(runtime_crate, watch, seal, &args),
+        Commands::ChainSpec(command) => handle_chain_spec(command),
+        Commands::Network(command) => handle_network(command),
+        Commands::Metadata(args) => handle_metadata(args),
+        Commands::Tx { pallet, call, args, signer, rpc, wait } => handle_tx(&pallet, &call, &args, signer, &rpc, &wait),
+        Commands::Query { pallet, item, keys, at, rpc } => handle_query(&pallet, &item, &keys, at, &rpc),
+        Commands::Watch { mode, pallet, rpc } => handle_watch(&mode, pallet, &rpc),
+        Commands::Key(args) => handle_key(args),
+        Commands::Collator(command) => handle_collator(command),
+        Commands::Para(command) => handle_para(command),
+        Commands::Runtime(command) => handle_runtime(command),
+        Commands::TryRuntime(command) => handle_try_runtime(command),
+        Commands::Bench { runtime, pallet, extrinsic, steps, repeat, path, weights } => {
+            handle_bench(&runtime, pallet, extrinsic, steps, repeat, &path, weights)
+        }
+        Commands::Fork { from_snapshot, save_snapshot, rpc, at, args } => handle_fork(from_snapshot, save_snapshot, &rpc, at, &args),
+        Commands::Seal { count, finalize, rpc } => seal::create_blocks(&rpc, count, finalize),
+        Commands::Template(command) => handle_template(command),
+        Commands::New { name, template, git_ref, path } => handle_new(&name, &template, git_ref, &path),
+        Commands::Build { profile, features, path, history } => handle_build(profile, features, &path, history),
+        Commands::Run { profile, path, args } => build::run(&path, profile.name(), &strs(&args)),
+        Commands::Pallet(args) => handle_pallet(args),
+        Commands::Config(command) => handle_config(command),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(FAILURE);
+    }
+}
+
+fn strs(args: &[String]) -> Vec<&str> {
+    args.iter().map(String::as_str).collect()
+}
+
+fn handle_install(template: Option<String>, git_ref: Option<String>, omni: bool, args: &[String]) -> HandlerResult {
+    let name = match template {
+        Some(name) => name,
+        None => {
+            install::install("default")?;
+            println!("Environment is ready.");
+            return Ok(());
+        }
+    };
+    let mut template = template::find(&name)?;
+    if git_ref.is_some() {
+        template.git_ref = git_ref;
+    }
+    match omni {
+        true => template::run_template_omni(&strs(args), &template),
+        false => template::run_template(&strs(args), &template),
+    }
+}
+
+fn handle_serve(runtime_crate: Option<PathBuf>, watch: bool, seal: Option<seal::SealMode>, args: &[String]) -> HandlerResult {
+    let mut node_args: Vec<String> = Vec::new();
+    if let Some(mode) = seal {
+        node_args = mode.node_args();
+        if mode == seal::SealMode::Instant {
+            seal::spawn_instant_sealer(seal::local_rpc_url(&strs(args)));
+        }
+    }
+    node_args.extend(args.iter().cloned());
+    match runtime_crate {
+        Some(crate_path) => serve::run_runtime_crate(&crate_path, &strs(&node_args), watch),
+        None => serve::run(&strs(&node_args)),
+    }
+}
+
+fn handle_chain_spec(command: cli::ChainSpecCommand) -> HandlerResult {
+    match command {
+        cli::ChainSpecCommand::Generate { runtime, wasm } => {
+            let chain_spec = install::generate_chain_spec(wasm.as_deref(), runtime.map(cli::Runtime::name))?;
+            println!("✓ Generated {}", chain_spec.display());
+            Ok(())
+        }
+    }
+}
+
+fn handle_network(command: cli::NetworkCommand) -> HandlerResult {
+    match command {
+        cli::NetworkCommand::List => config::list_networks(output::json()),
+        cli::NetworkCommand::Add { name, url, global } => config::add_network(&name, &url, global),
+    }
+}
+
+fn handle_metadata(args: cli::MetadataArgs) -> HandlerResult {
+    let metadata = metadata::load(args.rpc.as_deref(), args.wasm.as_deref()).map_err(|e| format!("Failed to load metadata: {}", e))?;
+    match args.command {
+        Some(cli::MetadataCommand::Pallet { name }) => metadata::print_pallet(&metadata, metadata.pallet(&name)?, output::json()),
+        None => metadata::print_tree(&metadata, output::json()),
+    }
+    Ok(())
+}
+
+fn handle_tx(pallet: &str, call: &str, args: &[String], signer: Option<String>, rpc: &str, wait: &str) -> HandlerResult {
+    tx::run(rpc, pallet, call, &strs(args), &signer_or_default(signer), wait == "finalized", output::json())
+}
+
+fn handle_query(pallet: &str, item: &str, keys: &[String], at: Option<String>, rpc: &str) -> HandlerResult {
+    query::run(rpc, pallet, item, &strs(keys), at.as_deref())
+}
+
+fn handle_watch(mode: &str, pallet: Option<String>, rpc: &str) -> HandlerResult {
+    let mode = match mode {
+        "events" => watch::WatchMode::Events,
+        "finalized" => watch::WatchMode::Finalized,
+        _ if pallet.is_some() => watch::WatchMode::Events,
+        _ => watch::WatchMode::Blocks,
+    };
+    watch::run(rpc, mode, pallet.as_deref())
+}
+
+fn handle_key(args: cli::KeyArgs) -> HandlerResult {
+    let scheme = keys::Scheme::parse(&args.scheme)?;
+    let prefix = args.ss58_prefix;
+    let keystore_path = args.keystore.as_path();
+
+    match args.command {
+        cli::KeyCommand::Generate { words, save } => {
+            let save = save.as_deref().map(|name| (keystore_path, name));
+            keystore::generate(scheme, words.parse()?, prefix, save, output::json())
         }
+        cli::KeyCommand::Inspect { uri, public } => keystore::inspect(&uri, scheme, prefix, public, output::json()),
+        cli::KeyCommand::Insert { key_type, suri, name, rpc } => {
+            let (suri, scheme) = match (name, suri) {
+                (Some(name), _) => keystore::Keystore::open(keystore_path)?.unlock(&name, &keystore::password(false)?)?,
+                (None, suri) => (suri.unwrap_or_default(), scheme),
+            };
+            keystore::insert(&rpc, &key_type, &suri, scheme)
+        }
+        cli::KeyCommand::List => keystore::list(keystore_path, prefix, output::json()),
     }
 }
 
+fn handle_collator(command: cli::CollatorCommand) -> HandlerResult {
+    match command {
+        cli::CollatorCommand::Setup { signer, rpc, wait } => collator::setup(&rpc, &signer_or_default(signer), wait == "finalized"),
+    }
+}
 
+fn handle_para(command: cli::ParaCommand) -> HandlerResult {
+    match command {
+        cli::ParaCommand::Register { relay, para_id, genesis_head, wasm, signer } => {
+            para::register(&relay, para_id, &genesis_head, &wasm, &signer_or_default(signer))
+        }
+    }
+}
 
-fn handle_install(matches: &clap::ArgMatches) {
-    if let Some(template_name) = matches.value_of("template") {
-        let args: Vec<&str> = matches.values_of("args").unwrap_or_default().collect();
-        template::run_template(&args, template_name);
-    } else if let Some(chain) = matches.value_of("chain") {
-        match chain {
-            "minimal" | "parachain" | "solochain" => {
-                let args: Vec<&str> = matches.values_of("args").unwrap_or_default().collect();
-                template::run_template(&args, chain);
-            }
-            _ => {
-                eprintln!("Invalid chain provided: {}", chain);
-                process::exit(1);
-            }
+fn handle_runtime(command: cli::RuntimeCommand) -> HandlerResult {
+    match command {
+        cli::RuntimeCommand::Info { wasm, compare } => runtime::info(&wasm, compare.as_deref(), output::json()),
+        cli::RuntimeCommand::Upgrade { wasm, signer, sudo, rpc } => runtime::upgrade(&rpc, &wasm, &signer_or_default(signer), sudo),
+    }
+}
+
+fn handle_try_runtime(command: cli::TryRuntimeCommand) -> HandlerResult {
+    match command {
+        cli::TryRuntimeCommand::CreateSnapshot { rpc, snapshot } => try_runtime::create_snapshot(&rpc, &snapshot),
+        cli::TryRuntimeCommand::OnRuntimeUpgrade { snapshot, wasm, checks, blocktime } => {
+            try_runtime::on_runtime_upgrade(&snapshot, &wasm, &checks, &blocktime.to_string())
         }
-    } else {
-        install::install("default");
-        println!("Environment is ready.");
     }
-    process::exit(0);
 }
 
-fn handle_serve(matches: &clap::ArgMatches) {
-    let args: Vec<&str> = matches.values_of("ARGS").unwrap_or_default().collect();
-    serve::run(&args);
-    process::exit(0);
-}
\ No newline at end of file
+fn handle_bench(
+    runtime: &Path,
+    pallet: Option<String>,
+    extrinsic: Option<String>,
+    steps: u32,
+    repeat: u32,
+    path: &Path,
+    weights: Option<PathBuf>,
+) -> HandlerResult {
+    let options = bench::BenchOptions {
+        runtime,
+        pallet: pallet.as_deref(),
+        extrinsic: extrinsic.as_deref(),
+        steps: &steps.to_string(),
+        repeat: &repeat.to_string(),
+        project: path,
+        output: weights.as_deref(),
+    };
+    bench::run(&options)
+}
+
+fn handle_fork(from_snapshot: Option<PathBuf>, save_snapshot: Option<PathBuf>, rpc: &str, at: Option<String>, args: &[String]) -> HandlerResult {
+    match (save_snapshot, from_snapshot) {
+        (Some(output), _) => fork::save_snapshot(rpc, &output, at.as_deref()),
+        (None, Some(snapshot)) => fork::from_snapshot(&snapshot, &strs(args)),
+        // The snapshot group requires one of them
+        (None, None) => Ok(()),
+    }
+}
+
+fn handle_template(command: cli::TemplateCommand) -> HandlerResult {
+    match command {
+        cli::TemplateCommand::List => template::list(),
+        cli::TemplateCommand::Status { path } => template::status(&path, output::json()),
+        cli::TemplateCommand::Update { path, git_ref, reclone } => template::update(&path, git_ref.as_deref(), reclone),
+    }
+}
+
+fn handle_new(name: &str, template: &str, git_ref: Option<String>, path: &Path) -> HandlerResult {
+    let mut template = template::find(template)?;
+    if git_ref.is_some() {
+        template.git_ref = git_ref;
+    }
+    scaffold::new_project(name, &template, path)
+}
+
+fn handle_build(profile: cli::CargoProfile, features: Option<String>, path: &Path, history: bool) -> HandlerResult {
+    match history {
+        true => build::print_history(path, output::json()),
+        false => build::build(path, profile.name(), features.as_deref()),
+    }
+}
+
+fn handle_pallet(args: cli::PalletArgs) -> HandlerResult {
+    let (name, changes) = match &args.command {
+        cli::PalletCommand::New { name } => (name, pallet::new_pallet(&args.path, name)?),
+        cli::PalletCommand::Add { name, allow_incomplete } => (name, pallet::add_pallet(&args.path, name, args.dry_run || *allow_incomplete)?),
+    };
+    pallet::write_changes(&changes, args.dry_run)?;
+    if !args.dry_run {
+        let names = pallet::PalletNames::new(name)?;
+        println!("\n✓ Added {} to the runtime as {}", names.crate_name, names.type_name);
+    }
+    Ok(())
+}
+
+fn handle_config(command: cli::ConfigCommand) -> HandlerResult {
+    match command {
+        cli::ConfigCommand::Get { key } => config::print_value(&key),
+        cli::ConfigCommand::Set { key, value, global } => config::set(&key, &value, global),
+        cli::ConfigCommand::Show { origin } => config::show(origin),
+    }
+}
+
+/// `--signer`, or else `keys.signer` of the config.
+fn signer_or_default(signer: Option<String>) -> String {
+    signer.unwrap_or_else(|| config::current().string("keys.signer"))
+}
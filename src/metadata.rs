@@ -0,0 +1,431 @@
+use std::error::Error;
+use std::path::Path;
+use frame_metadata::v14::{PalletConstantMetadata, PalletStorageMetadata, StorageEntryMetadata, StorageEntryModifier, StorageEntryType};
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+use parity_scale_codec::{Decode, Encode};
+use scale_info::form::PortableForm;
+use scale_info::{PortableRegistry, TypeDef, TypeDefPrimitive, Variant};
+use serde_json::{json, Value};
+use crate::rpc::{self, RpcClient};
+use crate::keys;
+use crate::wasm;
+
+/// Runtime metadata normalised over the V14 and V15 formats.
+pub struct Metadata {
+    pub version: u32,
+    pub types: PortableRegistry,
+    pub pallets: Vec<Pallet>,
+    pub extrinsic: ExtrinsicInfo,
+    pub ss58_prefix: u16,
+}
+
+pub struct Pallet {
+    pub name: String,
+    pub index: u8,
+    pub docs: Vec<String>,
+    pub calls: Option<u32>,
+    pub event: Option<u32>,
+    pub error: Option<u32>,
+    pub storage: Option<PalletStorageMetadata<PortableForm>>,
+    pub constants: Vec<PalletConstantMetadata<PortableForm>>,
+}
+
+pub struct ExtrinsicInfo {
+    pub version: u8,
+    pub address_ty: u32,
+    pub signature_ty: u32,
+    pub signed_extensions: Vec<SignedExtension>,
+}
+
+pub struct SignedExtension {
+    pub identifier: String,
+    pub ty: u32,
+    pub additional_signed: u32,
+}
+
+/// Loads metadata from a runtime wasm file if given, otherwise from the node at `rpc_url`.
+pub fn load(rpc_url: Option<&str>, wasm_path: Option<&Path>) -> Result<Metadata, Box<dyn Error>> {
+    match wasm_path {
+        Some(path) => from_wasm(path),
+        None => {
+            let mut client = RpcClient::connect(rpc_url.unwrap_or(rpc::DEFAULT_RPC_URL))?;
+            from_rpc(&mut client)
+        }
+    }
+}
+
+pub fn from_rpc(client: &mut RpcClient) -> Result<Metadata, Box<dyn Error>> {
+    let params = json!(["Metadata_metadata_at_version", rpc::hex_encode(&15u32.encode())]);
+    if let Ok(Value::String(result)) = client.request("state_call", params) {
+        if let Ok(Some(bytes)) = Option::<Vec<u8>>::decode(&mut &rpc::hex_decode(&result)?[..]) {
+            return decode(&bytes);
+        }
+    }
+
+    let result = client.request("state_getMetadata", json!([]))?;
+    let bytes = rpc::hex_decode(result.as_str().ok_or("Unexpected state_getMetadata response")?)?;
+    decode(&bytes)
+}
+
+pub fn from_wasm(path: &Path) -> Result<Metadata, Box<dyn Error>> {
+    let code = wasm::read_runtime(path)?;
+    decode(&wasm::metadata_bytes(&code)?)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Metadata, Box<dyn Error>> {
+    let prefixed = RuntimeMetadataPrefixed::decode(&mut &bytes[..])
+        .map_err(|e| format!("Failed to decode runtime metadata: {}", e))?;
+
+    let mut metadata = match prefixed.1 {
+        RuntimeMetadata::V14(metadata) => {
+            // V14 only describes the extrinsic type, whose type parameters hold the address and signature
+            let extrinsic_ty = metadata.types.resolve(metadata.extrinsic.ty.id);
+            let type_param = |name: &str| -> u32 {
+                extrinsic_ty
+                    .and_then(|ty| ty.type_params.iter().find(|param| param.name == name))
+                    .and_then(|param| param.ty)
+                    .map(|ty| ty.id)
+                    .unwrap_or(u32::MAX)
+            };
+            let extrinsic = ExtrinsicInfo {
+                version: metadata.extrinsic.version,
+                address_ty: type_param("Address"),
+                signature_ty: type_param("Signature"),
+                signed_extensions: metadata
+                    .extrinsic
+                    .signed_extensions
+                    .into_iter()
+                    .map(|extension| SignedExtension {
+                        identifier: extension.identifier,
+                        ty: extension.ty.id,
+                        additional_signed: extension.additional_signed.id,
+                    })
+                    .collect(),
+            };
+            Metadata {
+                version: 14,
+                types: metadata.types,
+                pallets: metadata
+                    .pallets
+                    .into_iter()
+                    .map(|pallet| Pallet {
+                        name: pallet.name,
+                        index: pallet.index,
+                        docs: Vec::new(),
+                        calls: pallet.calls.map(|calls| calls.ty.id),
+                        event: pallet.event.map(|event| event.ty.id),
+                        error: pallet.error.map(|error| error.ty.id),
+                        storage: pallet.storage,
+                        constants: pallet.constants,
+                    })
+                    .collect(),
+                extrinsic,
+                ss58_prefix: keys::DEFAULT_SS58_PREFIX,
+            }
+        }
+        RuntimeMetadata::V15(metadata) => Metadata {
+            version: 15,
+            types: metadata.types,
+            pallets: metadata
+                .pallets
+                .into_iter()
+                .map(|pallet| Pallet {
+                    name: pallet.name,
+                    index: pallet.index,
+                    docs: pallet.docs,
+                    calls: pallet.calls.map(|calls| calls.ty.id),
+                    event: pallet.event.map(|event| event.ty.id),
+                    error: pallet.error.map(|error| error.ty.id),
+                    storage: pallet.storage,
+                    constants: pallet.constants,
+                })
+                .collect(),
+            extrinsic: ExtrinsicInfo {
+                version: metadata.extrinsic.version,
+                address_ty: metadata.extrinsic.address_ty.id,
+                signature_ty: metadata.extrinsic.signature_ty.id,
+                signed_extensions: metadata
+                    .extrinsic
+                    .signed_extensions
+                    .into_iter()
+                    .map(|extension| SignedExtension {
+                        identifier: extension.identifier,
+                        ty: extension.ty.id,
+                        additional_signed: extension.additional_signed.id,
+                    })
+                    .collect(),
+            },
+            ss58_prefix: keys::DEFAULT_SS58_PREFIX,
+        },
+        other => return Err(format!("Unsupported metadata version V{}", other.version()).into()),
+    };
+
+    metadata.ss58_prefix = metadata.constant_ss58_prefix().unwrap_or(keys::DEFAULT_SS58_PREFIX);
+    Ok(metadata)
+}
+
+impl Metadata {
+    /// Reads the `System::SS58Prefix` constant of the runtime.
+    fn constant_ss58_prefix(&self) -> Option<u16> {
+        let constant = self.pallet("System").ok()?.constants.iter().find(|c| c.name == "SS58Prefix")?;
+        match constant.value.len() {
+            1 => Some(constant.value[0] as u16),
+            _ => u16::decode(&mut &constant.value[..]).ok(),
+        }
+    }
+
+    /// Looks up a call, event or error variant of a pallet by name, ignoring case and underscores.
+    pub fn variant<'a>(&'a self, ty: Option<u32>, name: &str) -> Option<&'a Variant<PortableForm>> {
+        let normalize = |name: &str| name.replace('_', "").to_lowercase();
+        self.variants(ty).iter().find(|variant| normalize(&variant.name) == normalize(name))
+    }
+
+    /// Looks up a pallet by name, ignoring case.
+    pub fn pallet(&self, name: &str) -> Result<&Pallet, Box<dyn Error>> {
+        self.pallets
+            .iter()
+            .find(|pallet| pallet.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Pallet {} not found in runtime metadata", name).into())
+    }
+
+    /// Returns the variants of an enum type such as a pallet's call, event or error type.
+    pub fn variants(&self, ty: Option<u32>) -> &[Variant<PortableForm>] {
+        match ty.and_then(|id| self.types.resolve(id)).map(|ty| &ty.type_def) {
+            Some(TypeDef::Variant(def)) => &def.variants,
+            _ => &[],
+        }
+    }
+
+    /// Renders a readable name such as `Vec<u8>` or `AccountInfo<u32, AccountData<u128>>`.
+    pub fn type_name(&self, id: u32) -> String {
+        let ty = match self.types.resolve(id) {
+            Some(ty) => ty,
+            None => return format!("<unknown type {}>", id),
+        };
+
+        if let Some(name) = ty.path.segments.last() {
+            let params: Vec<String> = ty
+                .type_params
+                .iter()
+                .filter_map(|param| param.ty.map(|param_ty| self.type_name(param_ty.id)))
+                .collect();
+            if params.is_empty() {
+                return name.clone();
+            }
+            return format!("{}<{}>", name, params.join(", "));
+        }
+
+        match &ty.type_def {
+            TypeDef::Sequence(def) => format!("Vec<{}>", self.type_name(def.type_param.id)),
+            TypeDef::Array(def) => format!("[{}; {}]", self.type_name(def.type_param.id), def.len),
+            TypeDef::Tuple(def) => {
+                let fields: Vec<String> = def.fields.iter().map(|field| self.type_name(field.id)).collect();
+                format!("({})", fields.join(", "))
+            }
+            TypeDef::Compact(def) => format!("Compact<{}>", self.type_name(def.type_param.id)),
+            TypeDef::Primitive(def) => primitive_name(def).to_string(),
+            TypeDef::BitSequence(_) => "BitVec".to_string(),
+            TypeDef::Composite(_) | TypeDef::Variant(_) => format!("<anonymous type {}>", id),
+        }
+    }
+
+    fn variant_signature(&self, variant: &Variant<PortableForm>) -> String {
+        let fields: Vec<String> = variant
+            .fields
+            .iter()
+            .map(|field| match &field.name {
+                Some(name) => format!("{}: {}", name, self.type_name(field.ty.id)),
+                None => self.type_name(field.ty.id),
+            })
+            .collect();
+        format!("{}({})", variant.name, fields.join(", "))
+    }
+
+    fn storage_signature(&self, entry: &StorageEntryMetadata<PortableForm>) -> String {
+        let optional = match entry.modifier {
+            StorageEntryModifier::Optional => "?",
+            StorageEntryModifier::Default => "",
+        };
+        match &entry.ty {
+            StorageEntryType::Plain(value) => format!("{}: {}{}", entry.name, self.type_name(value.id), optional),
+            StorageEntryType::Map { hashers, key, value } => format!(
+                "{}: map [{}] {} => {}{}",
+                entry.name,
+                hashers.iter().map(|hasher| format!("{:?}", hasher)).collect::<Vec<_>>().join(", "),
+                self.type_name(key.id),
+                self.type_name(value.id),
+                optional
+            ),
+        }
+    }
+}
+
+pub fn primitive_name(primitive: &TypeDefPrimitive) -> &'static str {
+    match primitive {
+        TypeDefPrimitive::Bool => "bool",
+        TypeDefPrimitive::Char => "char",
+        TypeDefPrimitive::Str => "String",
+        TypeDefPrimitive::U8 => "u8",
+        TypeDefPrimitive::U16 => "u16",
+        TypeDefPrimitive::U32 => "u32",
+        TypeDefPrimitive::U64 => "u64",
+        TypeDefPrimitive::U128 => "u128",
+        TypeDefPrimitive::U256 => "u256",
+        TypeDefPrimitive::I8 => "i8",
+        TypeDefPrimitive::I16 => "i16",
+        TypeDefPrimitive::I32 => "i32",
+        TypeDefPrimitive::I64 => "i64",
+        TypeDefPrimitive::I128 => "i128",
+        TypeDefPrimitive::I256 => "i256",
+    }
+}
+
+fn first_line(docs: &[String]) -> &str {
+    docs.iter().map(|line| line.trim()).find(|line| !line.is_empty()).unwrap_or("")
+}
+
+/// Prints every pallet with its calls, storage items, events, errors and constants.
+pub fn print_tree(metadata: &Metadata, json: bool) {
+    if json {
+        let pallets: Vec<Value> = metadata.pallets.iter().map(|pallet| pallet_json(metadata, pallet)).collect();
+        println!("{}", serde_json::to_string_pretty(&json!({ "version": metadata.version, "pallets": pallets })).unwrap());
+        return;
+    }
+
+    println!("Runtime metadata V{} ({} pallets)", metadata.version, metadata.pallets.len());
+    for pallet in &metadata.pallets {
+        println!("\n{} [{}]", pallet.name, pallet.index);
+        print_names("calls", metadata.variants(pallet.calls).iter().map(|v| v.name.as_str()));
+        print_names("storage", pallet.storage.iter().flat_map(|s| s.entries.iter()).map(|e| e.name.as_str()));
+        print_names("events", metadata.variants(pallet.event).iter().map(|v| v.name.as_str()));
+        print_names("errors", metadata.variants(pallet.error).iter().map(|v| v.name.as_str()));
+        print_names("constants", pallet.constants.iter().map(|c| c.name.as_str()));
+    }
+}
+
+fn print_names<'a>(label: &str, names: impl Iterator<Item = &'a str>) {
+    let names: Vec<&str> = names.collect();
+    if !names.is_empty() {
+        println!("  ├─ {} ({}): {}", label, names.len(), names.join(", "));
+    }
+}
+
+/// Prints the details of a single pallet including signatures and docs.
+pub fn print_pallet(metadata: &Metadata, pallet: &Pallet, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(&pallet_json(metadata, pallet)).unwrap());
+        return;
+    }
+
+    println!("{} [{}]", pallet.name, pallet.index);
+    if !pallet.docs.is_empty() {
+        println!("  {}", first_line(&pallet.docs));
+    }
+
+    let calls = metadata.variants(pallet.calls);
+    if !calls.is_empty() {
+        println!("\nCalls:");
+        for call in calls {
+            println!("  {}", metadata.variant_signature(call));
+            println!("      {}", first_line(&call.docs));
+        }
+    }
+
+    if let Some(storage) = &pallet.storage {
+        println!("\nStorage ({}):", storage.prefix);
+        for entry in &storage.entries {
+            println!("  {}", metadata.storage_signature(entry));
+            println!("      {}", first_line(&entry.docs));
+        }
+    }
+
+    let events = metadata.variants(pallet.event);
+    if !events.is_empty() {
+        println!("\nEvents:");
+        for event in events {
+            println!("  {}", metadata.variant_signature(event));
+            println!("      {}", first_line(&event.docs));
+        }
+    }
+
+    let errors = metadata.variants(pallet.error);
+    if !errors.is_empty() {
+        println!("\nErrors:");
+        for error in errors {
+            println!("  {}", error.name);
+            println!("      {}", first_line(&error.docs));
+        }
+    }
+
+    if !pallet.constants.is_empty() {
+        println!("\nConstants:");
+        for constant in &pallet.constants {
+            println!("  {}: {} = {}", constant.name, metadata.type_name(constant.ty.id), rpc::hex_encode(&constant.value));
+            println!("      {}", first_line(&constant.docs));
+        }
+    }
+}
+
+fn pallet_json(metadata: &Metadata, pallet: &Pallet) -> Value {
+    let variants_json = |ty: Option<u32>| -> Vec<Value> {
+        metadata
+            .variants(ty)
+            .iter()
+            .map(|variant| {
+                let fields: Vec<Value> = variant
+                    .fields
+                    .iter()
+                    .map(|field| json!({ "name": field.name, "type": metadata.type_name(field.ty.id) }))
+                    .collect();
+                json!({ "name": variant.name, "index": variant.index, "fields": fields, "docs": variant.docs })
+            })
+            .collect()
+    };
+
+    let storage: Vec<Value> = pallet
+        .storage
+        .iter()
+        .flat_map(|storage| storage.entries.iter())
+        .map(|entry| {
+            let modifier = format!("{:?}", entry.modifier);
+            match &entry.ty {
+                StorageEntryType::Plain(value) => json!({
+                    "name": entry.name,
+                    "modifier": modifier,
+                    "value": metadata.type_name(value.id),
+                    "docs": entry.docs,
+                }),
+                StorageEntryType::Map { hashers, key, value } => json!({
+                    "name": entry.name,
+                    "modifier": modifier,
+                    "hashers": hashers.iter().map(|hasher| format!("{:?}", hasher)).collect::<Vec<_>>(),
+                    "key": metadata.type_name(key.id),
+                    "value": metadata.type_name(value.id),
+                    "docs": entry.docs,
+                }),
+            }
+        })
+        .collect();
+
+    let constants: Vec<Value> = pallet
+        .constants
+        .iter()
+        .map(|constant| json!({
+            "name": constant.name,
+            "type": metadata.type_name(constant.ty.id),
+            "value": rpc::hex_encode(&constant.value),
+            "docs": constant.docs,
+        }))
+        .collect();
+
+    json!({
+        "name": pallet.name,
+        "index": pallet.index,
+        "docs": pallet.docs,
+        "calls": variants_json(pallet.calls),
+        "storage": storage,
+        "events": variants_json(pallet.event),
+        "errors": variants_json(pallet.error),
+        "constants": constants,
+    })
+}
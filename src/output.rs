@@ -0,0 +1,56 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::process::Command;
+use crate::config;
+
+/// `--quiet`, or `output.verbosity = "quiet"` in the config.
+pub fn quiet() -> bool {
+    config::current().string("output.verbosity") == "quiet"
+}
+
+/// `--verbose`, or `output.verbosity = "verbose"` in the config.
+pub fn verbose() -> bool {
+    config::current().string("output.verbosity") == "verbose"
+}
+
+/// `--output json`, or `output.format = "json"` in the config.
+pub fn json() -> bool {
+    config::current().string("output.format") == "json"
+}
+
+/// Prints a progress message, hidden by `--quiet`. With `--output json` it goes to stderr, so
+/// stdout only holds the JSON result.
+pub fn progress(message: impl Display) {
+    if quiet() {
+        return;
+    }
+    match json() {
+        true => eprintln!("{}", message),
+        false => println!("{}", message),
+    }
+}
+
+/// Replaces the current progress line in place, e.g. a progress bar, hidden by `--quiet` like `progress`.
+pub fn progress_line(line: impl Display) -> io::Result<()> {
+    if quiet() {
+        return Ok(());
+    }
+    match json() {
+        true => {
+            eprint!("\r\x1b[K{}", line);
+            io::stderr().flush()
+        }
+        false => {
+            print!("\r\x1b[K{}", line);
+            io::stdout().flush()
+        }
+    }
+}
+
+/// Prints a command before it is run, with `--verbose`.
+pub fn command(command: &Command) {
+    if verbose() {
+        let args: Vec<String> = command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect();
+        println!("$ {} {}", command.get_program().to_string_lossy(), args.join(" "));
+    }
+}
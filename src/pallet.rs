@@ -0,0 +1,708 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use toml_edit::{DocumentMut, Item, Value};
+
+/// A file created or modified by a pallet command.
+pub struct FileChange {
+    pub path: PathBuf,
+    /// Content before the change, `None` for new files.
+    pub before: Option<String>,
+    pub after: String,
+}
+
+/// Names of a pallet in its manifest, in Rust code and in the runtime.
+#[derive(Debug, PartialEq)]
+pub struct PalletNames {
+    /// `pallet-voting-booth`
+    pub crate_name: String,
+    /// `pallet_voting_booth`
+    pub module: String,
+    /// `VotingBooth`
+    pub type_name: String,
+}
+
+impl PalletNames {
+    /// Accepts `voting-booth`, `voting_booth` or `pallet-voting-booth`.
+    pub fn new(name: &str) -> Result<Self, Box<dyn Error>> {
+        let base = name.trim_start_matches("pallet-").trim_start_matches("pallet_").replace('_', "-");
+        let valid = base.starts_with(|c: char| c.is_ascii_lowercase())
+            && base.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid {
+            return Err(format!("Invalid pallet name {}, use lowercase letters, digits and -", name).into());
+        }
+        let type_name = base
+            .split('-')
+            .map(|word| {
+                let mut chars = word.chars();
+                chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+            })
+            .collect();
+        Ok(PalletNames {
+            crate_name: format!("pallet-{}", base),
+            module: format!("pallet_{}", base.replace('-', "_")),
+            type_name,
+        })
+    }
+
+    fn dir_name(&self) -> &str {
+        self.crate_name.trim_start_matches("pallet-")
+    }
+}
+
+/// Generates a new pallet crate in `pallets/` of the project by copying the template's own pallet,
+/// then registers it in the workspace and the runtime the same way the template pallet is.
+pub fn new_pallet(project: &Path, name: &str) -> Result<Vec<FileChange>, Box<dyn Error>> {
+    let names = PalletNames::new(name)?;
+    let (source_dir, source_crate) = find_template_pallet(project)?;
+    let source = PalletNames {
+        module: source_crate.replace('-', "_"),
+        crate_name: source_crate,
+        type_name: String::new(),
+    };
+    let destination = project.join("pallets").join(names.dir_name());
+    if destination.exists() {
+        return Err(format!("{} already exists", destination.display()).into());
+    }
+    let runtime_dir = find_runtime(project)?;
+    println!("Generating {} from {} in {}", names.crate_name, source.crate_name, destination.display());
+
+    let mut changes = Vec::new();
+    copy_pallet(&source_dir, &destination, &source, &names, &mut changes)?;
+
+    let workspace_manifest = project.join("Cargo.toml");
+    let before = fs::read_to_string(&workspace_manifest)?;
+    let relative_dir = format!("pallets/{}", names.dir_name());
+    let after = add_workspace_member(&before, &relative_dir, &source, &names)?;
+    changes.push(FileChange { path: workspace_manifest, before: Some(before), after });
+
+    let runtime_manifest = runtime_dir.join("Cargo.toml");
+    let before = fs::read_to_string(&runtime_manifest)?;
+    let after = mirror_dependency(&before, &source, &names, &format!("../{}", relative_dir))?;
+    changes.push(FileChange { path: runtime_manifest, before: Some(before), after });
+
+    for path in rust_files(&runtime_dir.join("src")) {
+        let before = fs::read_to_string(&path)?;
+        let mut after = register_pallet(&before, &names)?.unwrap_or_else(|| before.clone());
+        after = mirror_config(&after, &source.module, &names.module);
+        after = mirror_benchmark(&after, &source.module, &names);
+        if after != before {
+            changes.push(FileChange { path, before: Some(before), after });
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Writes the changes, or only prints them as a unified diff with `dry_run`.
+pub fn write_changes(changes: &[FileChange], dry_run: bool) -> Result<(), Box<dyn Error>> {
+    for change in changes {
+        if dry_run {
+            let path = change.path.display().to_string();
+            let path = path.trim_start_matches("./");
+            let (old_path, new_path) = match change.path.is_absolute() {
+                true => (path.to_string(), path.to_string()),
+                false => (format!("a/{}", path), format!("b/{}", path)),
+            };
+            let before = change.before.as_deref().unwrap_or_default();
+            let diff = similar::TextDiff::from_lines(before, &change.after);
+            let old_header = if change.before.is_some() { old_path } else { "/dev/null".to_string() };
+            print!("{}", diff.unified_diff().context_radius(3).header(&old_header, &new_path));
+            continue;
+        }
+        if let Some(parent) = change.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&change.path, &change.after)?;
+        println!("  {} {}", if change.before.is_some() { "M" } else { "A" }, change.path.display());
+    }
+    Ok(())
+}
+
+/// The first pallet crate in `pallets/`, e.g. `pallets/template` named `pallet-parachain-template`.
+fn find_template_pallet(project: &Path) -> Result<(PathBuf, String), Box<dyn Error>> {
+    let pallets = project.join("pallets");
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&pallets)
+        .map_err(|_| format!("No pallets directory in {}, is it a template project?", project.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    dirs.sort();
+    for dir in dirs {
+        if let Some(name) = package_name(&dir.join("Cargo.toml")) {
+            if name.starts_with("pallet-") {
+                return Ok((dir, name));
+            }
+        }
+    }
+    Err(format!("No pallet crate found in {} to start from", pallets.display()).into())
+}
+
+/// The runtime crate of the project: `runtime/` or the workspace member named `*-runtime`.
+pub fn find_runtime(project: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let manifest: DocumentMut = fs::read_to_string(project.join("Cargo.toml"))?.parse()?;
+    let members = manifest["workspace"]["members"].as_array().map(|members| members.iter().filter_map(|m| m.as_str()).collect::<Vec<_>>());
+    let mut candidates = vec![project.join("runtime")];
+    for member in members.unwrap_or_default() {
+        match member.strip_suffix("/*") {
+            Some(parent) => candidates.extend(fs::read_dir(project.join(parent)).into_iter().flatten().flatten().map(|entry| entry.path())),
+            None => candidates.push(project.join(member)),
+        }
+    }
+    candidates
+        .into_iter()
+        .find(|dir| package_name(&dir.join("Cargo.toml")).is_some_and(|name| name.ends_with("-runtime")))
+        .ok_or_else(|| format!("No runtime crate found in {}", project.display()).into())
+}
+
+fn package_name(manifest: &Path) -> Option<String> {
+    let manifest: DocumentMut = fs::read_to_string(manifest).ok()?.parse().ok()?;
+    manifest["package"]["name"].as_str().map(String::from)
+}
+
+fn copy_pallet(source: &Path, destination: &Path, from: &PalletNames, to: &PalletNames, changes: &mut Vec<FileChange>) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(source)? {
+        let path = entry?.path();
+        let target = destination.join(path.file_name().unwrap());
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name != "target") {
+                copy_pallet(&path, &target, from, to, changes)?;
+            }
+        } else if let Ok(content) = fs::read_to_string(&path) {
+            let after = content.replace(&from.crate_name, &to.crate_name).replace(&from.module, &to.module);
+            changes.push(FileChange { path: target, before: None, after });
+        }
+    }
+    Ok(())
+}
+
+/// Adds the new pallet to the workspace members, unless a glob covers it, and next to the template
+/// pallet in `[workspace.dependencies]`.
+pub fn add_workspace_member(manifest: &str, dir: &str, source: &PalletNames, names: &PalletNames) -> Result<String, Box<dyn Error>> {
+    let mut manifest: DocumentMut = manifest.parse()?;
+    if let Some(members) = manifest.get_mut("workspace").and_then(|workspace| workspace.get_mut("members")).and_then(Item::as_array_mut) {
+        let parent = dir.rsplit_once('/').map(|(parent, _)| format!("{}/*", parent)).unwrap_or_default();
+        if !members.iter().any(|member| member.as_str() == Some(dir) || member.as_str() == Some(&parent)) {
+            let mut member = Value::from(dir);
+            if let Some(last) = members.iter().last() {
+                *member.decor_mut() = last.decor().clone();
+            }
+            members.push_formatted(member);
+        }
+    }
+    if let Some(dependencies) = manifest.get_mut("workspace").and_then(|workspace| workspace.get_mut("dependencies")).and_then(Item::as_table_like_mut) {
+        copy_dependency(dependencies, source, names, &format!("./{}", dir));
+    }
+    Ok(manifest.to_string())
+}
+
+/// Declares the new pallet like the template pallet in `[dependencies]` and next to it in every feature.
+pub fn mirror_dependency(manifest: &str, source: &PalletNames, names: &PalletNames, path: &str) -> Result<String, Box<dyn Error>> {
+    let mut manifest: DocumentMut = manifest.parse()?;
+    if let Some(dependencies) = manifest.get_mut("dependencies").and_then(Item::as_table_like_mut) {
+        copy_dependency(dependencies, source, names, path);
+    }
+    if let Some(features) = manifest.get_mut("features").and_then(Item::as_table_like_mut) {
+        for (_, feature) in features.iter_mut() {
+            if let Some(feature) = feature.as_array_mut() {
+                let prefix = format!("{}/", source.crate_name);
+                let found: Vec<(usize, String)> = feature
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, entry)| entry.as_str()?.strip_prefix(&prefix).map(|flag| (index, flag.to_string())))
+                    .collect();
+                for (offset, (index, flag)) in found.into_iter().enumerate() {
+                    add_feature_entry(feature, index + offset + 1, &format!("{}/{}", names.crate_name, flag));
+                }
+            }
+        }
+    }
+    Ok(manifest.to_string())
+}
+
+/// Inserts `entry` at `position` of a feature array, formatted like its neighbours.
+pub fn add_feature_entry(feature: &mut toml_edit::Array, position: usize, entry: &str) {
+    if feature.iter().any(|existing| existing.as_str() == Some(entry)) {
+        return;
+    }
+    // Multi-line arrays keep one entry per line, inline ones get the default `, ` separator
+    let neighbour = feature.get(position).or_else(|| feature.get(position.saturating_sub(1)));
+    let decor = neighbour.map(|neighbour| neighbour.decor().clone());
+    match decor.filter(|decor| decor.prefix().and_then(|prefix| prefix.as_str()).is_some_and(|prefix| prefix.contains('\n'))) {
+        Some(decor) => {
+            let mut value = Value::from(entry);
+            *value.decor_mut() = decor;
+            feature.insert_formatted(position, value);
+        }
+        None => feature.insert(position, entry),
+    }
+}
+
+/// Inserts `entry` into a feature array before the first entry sorting after it.
+pub fn add_feature_sorted(feature: &mut toml_edit::Array, entry: &str) {
+    let position = feature.iter().position(|existing| existing.as_str().is_some_and(|existing| existing > entry)).unwrap_or(feature.len());
+    add_feature_entry(feature, position, entry);
+}
+
+fn copy_dependency(dependencies: &mut dyn toml_edit::TableLike, source: &PalletNames, names: &PalletNames, path: &str) {
+    let mut dependency = match dependencies.get(&source.crate_name) {
+        Some(dependency) => dependency.clone(),
+        None => return,
+    };
+    if let Some(table) = dependency.as_table_like_mut() {
+        if table.contains_key("path") {
+            table.insert("path", toml_edit::value(path));
+        }
+    }
+    dependencies.insert(&names.crate_name, dependency);
+}
+
+/// Registers a pallet in `#[frame_support::runtime]` or `construct_runtime!` with the next free
+/// index. Returns `None` when the code does not declare the runtime.
+pub fn register_pallet(code: &str, names: &PalletNames) -> Result<Option<String>, Box<dyn Error>> {
+    if code.contains("#[runtime::pallet_index(") {
+        return register_in_runtime_module(code, names).map(Some);
+    }
+    if code.contains("construct_runtime!") {
+        return register_in_construct_runtime(code, names).map(Some);
+    }
+    Ok(None)
+}
+
+fn register_in_runtime_module(code: &str, names: &PalletNames) -> Result<String, Box<dyn Error>> {
+    let marker = "#[runtime::pallet_index(";
+    let mut next_index = 0;
+    let mut last_declaration_end = 0;
+    let mut indent = "";
+    let mut generic = false;
+    for (start, _) in code.match_indices(marker) {
+        let rest = &code[start + marker.len()..];
+        let index: u32 = rest.split(')').next().unwrap_or_default().trim().parse().map_err(|_| "Invalid pallet index")?;
+        next_index = next_index.max(index + 1);
+        let end = start + code[start..].find(';').ok_or("Unterminated pallet declaration")? + 1;
+        if end > last_declaration_end {
+            last_declaration_end = end;
+            let line_start = code[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            indent = &code[line_start..start];
+            generic = code[start..end].contains("::Pallet<Runtime>");
+        }
+    }
+    if code.contains(&format!("pub type {} =", names.type_name)) {
+        return Err(format!("The runtime already declares {}", names.type_name).into());
+    }
+    if next_index > 255 {
+        return Err("No free pallet index left".into());
+    }
+
+    let path = if generic { format!("{}::Pallet<Runtime>", names.module) } else { names.module.clone() };
+    let declaration = format!("\n\n{indent}#[runtime::pallet_index({})]\n{indent}pub type {} = {};", next_index, names.type_name, path);
+    let mut updated = code.to_string();
+    updated.insert_str(last_declaration_end, &declaration);
+    Ok(updated)
+}
+
+fn register_in_construct_runtime(code: &str, names: &PalletNames) -> Result<String, Box<dyn Error>> {
+    let start = code.find("construct_runtime!").unwrap();
+    let mut next_index = 0;
+    let mut last_line_end = None;
+    let mut indent = String::new();
+    let mut offset = start;
+    for line in code[start..].split_inclusive('\n') {
+        let trimmed = line.trim();
+        offset += line.len();
+        // End of the macro
+        if trimmed.starts_with(')') {
+            break;
+        }
+        // `Balances: pallet_balances = 10,`
+        let entry = trimmed.trim_end_matches(',').split_once(':').and_then(|(_, rest)| rest.rsplit_once('='));
+        if let Some(index) = entry.and_then(|(_, index)| index.trim().parse::<u32>().ok()) {
+            next_index = next_index.max(index + 1);
+            last_line_end = Some(offset);
+            indent = line[..line.len() - line.trim_start().len()].to_string();
+        }
+    }
+    let last_line_end = last_line_end.ok_or("No pallet with an index found in construct_runtime!")?;
+    if next_index > 255 {
+        return Err("No free pallet index left".into());
+    }
+
+    let mut updated = code.to_string();
+    updated.insert_str(last_line_end, &format!("{}{}: {} = {},\n", indent, names.type_name, names.module, next_index));
+    Ok(updated)
+}
+
+/// Copies the `impl <source>::Config for Runtime` block of the template pallet for the new pallet.
+fn mirror_config(code: &str, source: &str, module: &str) -> String {
+    let start = match code.find(&format!("impl {}::Config for Runtime", source)) {
+        Some(start) => start,
+        None => return code.to_string(),
+    };
+    let end = match block_end(code, start) {
+        Some(end) => end,
+        None => return code.to_string(),
+    };
+    let block = code[start..end].replace(source, module);
+    let mut updated = code.to_string();
+    updated.insert_str(end, &format!("\n\n{}", block));
+    updated
+}
+
+/// Position after the closing brace of the block opened after `start`.
+pub fn block_end(code: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (offset, c) in code[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(start + offset + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Adds the new pallet next to the template pallet in `define_benchmarks!`.
+fn mirror_benchmark(code: &str, source: &str, names: &PalletNames) -> String {
+    let entry = format!("[{}, ", source);
+    let start = match code.find(&entry) {
+        Some(start) if code[..start].contains("define_benchmarks!") => start,
+        _ => return code.to_string(),
+    };
+    let line_start = code[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = code[start..].find('\n').map(|i| start + i + 1).unwrap_or(code.len());
+    let line = &code[line_start..line_end];
+    let suffix = line[line.find(']').unwrap_or(line.len())..].to_string();
+    let new_line = format!("{}[{}, {}{}", &code[line_start..start], names.module, names.type_name, suffix);
+    let mut updated = code.to_string();
+    updated.insert_str(line_end, &new_line);
+    updated
+}
+
+fn rust_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(rust_files(&path));
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Default `Config` implementations of the FRAME pallets `dot pallet add` knows about. Deposits
+/// assume the 12 decimals `u128` balances of the templates.
+const KNOWN_PALLETS: &[(&str, &str)] = &[
+    (
+        "utility",
+        "impl pallet_utility::Config for Runtime {
+	type RuntimeEvent = crate::RuntimeEvent;
+	type RuntimeCall = crate::RuntimeCall;
+	type PalletsOrigin = crate::OriginCaller;
+	type WeightInfo = pallet_utility::weights::SubstrateWeight<Runtime>;
+}",
+    ),
+    (
+        "multisig",
+        "impl pallet_multisig::Config for Runtime {
+	type RuntimeEvent = crate::RuntimeEvent;
+	type RuntimeCall = crate::RuntimeCall;
+	type Currency = crate::Balances;
+	type DepositBase = frame_support::traits::ConstU128<1_000_000_000_000>;
+	type DepositFactor = frame_support::traits::ConstU128<100_000_000_000>;
+	type MaxSignatories = frame_support::traits::ConstU32<100>;
+	type WeightInfo = pallet_multisig::weights::SubstrateWeight<Runtime>;
+}",
+    ),
+    (
+        "proxy",
+        "impl pallet_proxy::Config for Runtime {
+	type RuntimeEvent = crate::RuntimeEvent;
+	type RuntimeCall = crate::RuntimeCall;
+	type Currency = crate::Balances;
+	// Any proxy may make any call, replace with an enum to restrict proxies
+	type ProxyType = ();
+	type ProxyDepositBase = frame_support::traits::ConstU128<1_000_000_000_000>;
+	type ProxyDepositFactor = frame_support::traits::ConstU128<100_000_000_000>;
+	type MaxProxies = frame_support::traits::ConstU32<32>;
+	type WeightInfo = pallet_proxy::weights::SubstrateWeight<Runtime>;
+	type MaxPending = frame_support::traits::ConstU32<32>;
+	type CallHasher = sp_runtime::traits::BlakeTwo256;
+	type AnnouncementDepositBase = frame_support::traits::ConstU128<1_000_000_000_000>;
+	type AnnouncementDepositFactor = frame_support::traits::ConstU128<100_000_000_000>;
+}",
+    ),
+    (
+        "assets",
+        "impl pallet_assets::Config for Runtime {
+	type RuntimeEvent = crate::RuntimeEvent;
+	type Balance = crate::Balance;
+	type AssetId = u32;
+	type AssetIdParameter = codec::Compact<u32>;
+	type Currency = crate::Balances;
+	type CreateOrigin = frame_support::traits::AsEnsureOriginWithArg<frame_system::EnsureSigned<crate::AccountId>>;
+	type ForceOrigin = frame_system::EnsureRoot<crate::AccountId>;
+	type AssetDeposit = frame_support::traits::ConstU128<10_000_000_000_000>;
+	type AssetAccountDeposit = frame_support::traits::ConstU128<1_000_000_000_000>;
+	type MetadataDepositBase = frame_support::traits::ConstU128<1_000_000_000_000>;
+	type MetadataDepositPerByte = frame_support::traits::ConstU128<10_000_000_000>;
+	type ApprovalDeposit = frame_support::traits::ConstU128<100_000_000_000>;
+	type StringLimit = frame_support::traits::ConstU32<50>;
+	type Freezer = ();
+	type Extra = ();
+	type CallbackHandle = ();
+	type WeightInfo = pallet_assets::weights::SubstrateWeight<Runtime>;
+	type RemoveItemsLimit = frame_support::traits::ConstU32<1000>;
+	#[cfg(feature = \"runtime-benchmarks\")]
+	type BenchmarkHelper = ();
+}",
+    ),
+];
+
+/// Features of the runtime that are propagated to every pallet.
+const PROPAGATED_FEATURES: &[&str] = &["std", "runtime-benchmarks", "try-runtime"];
+
+/// Adds a FRAME pallet such as `pallet-assets` to the runtime of a project: its dependency and
+/// features, a default `Config` implementation and its entry in the runtime. Pallets without a known
+/// configuration are refused unless `incomplete` allows a `Config` stub that does not compile yet.
+pub fn add_pallet(project: &Path, name: &str, incomplete: bool) -> Result<Vec<FileChange>, Box<dyn Error>> {
+    let names = PalletNames::new(name)?;
+    let config = KNOWN_PALLETS.iter().find(|(known, _)| names.module == format!("pallet_{}", known)).map(|(_, config)| config.to_string());
+    if config.is_none() && !incomplete {
+        let known: Vec<&str> = KNOWN_PALLETS.iter().map(|(known, _)| *known).collect();
+        return Err(format!(
+            "No default configuration known for {} (known pallets: {}). Preview the changes with --dry-run, or pass --allow-incomplete to add it with a Config to fill in",
+            names.crate_name,
+            known.join(", ")
+        )
+        .into());
+    }
+    let runtime_dir = find_runtime(project)?;
+    let mut changes = Vec::new();
+
+    let workspace_manifest = project.join("Cargo.toml");
+    let runtime_manifest = runtime_dir.join("Cargo.toml");
+    let workspace_before = fs::read_to_string(&workspace_manifest)?;
+    let runtime_before = fs::read_to_string(&runtime_manifest)?;
+    let mut workspace: DocumentMut = workspace_before.parse()?;
+    let mut runtime: DocumentMut = runtime_before.parse()?;
+    add_dependency(&mut workspace, &mut runtime, &names)?;
+    for (path, before, after) in [(workspace_manifest, workspace_before, workspace.to_string()), (runtime_manifest, runtime_before, runtime.to_string())] {
+        if after != before {
+            changes.push(FileChange { path, before: Some(before), after });
+        }
+    }
+
+    if config.is_none() {
+        eprintln!("⚠ No default configuration known for {}, fill in its Config items in the runtime", names.crate_name);
+    }
+    let config = config.unwrap_or_else(|| {
+        format!("impl {}::Config for Runtime {{\n\ttype RuntimeEvent = crate::RuntimeEvent;\n\t// TODO: the other Config items of {}\n}}", names.module, names.crate_name)
+    });
+
+    let mut registered = false;
+    let mut configured = false;
+    for path in rust_files(&runtime_dir.join("src")) {
+        let before = fs::read_to_string(&path)?;
+        if before.contains(&format!("impl {}::Config for Runtime", names.module)) {
+            return Err(format!("{} is already configured in {}", names.crate_name, path.display()).into());
+        }
+        let mut after = match register_pallet(&before, &names)? {
+            Some(after) => {
+                registered = true;
+                after
+            }
+            None => before.clone(),
+        };
+        if !configured {
+            if let Some(updated) = insert_config(&after, &config) {
+                after = updated;
+                configured = true;
+            }
+        }
+        if after != before {
+            changes.push(FileChange { path, before: Some(before), after });
+        }
+    }
+    if !registered || !configured {
+        return Err(format!("Could not find the runtime declaration and frame_system::Config in {}", runtime_dir.join("src").display()).into());
+    }
+    Ok(changes)
+}
+
+/// Adds the pallet to the `polkadot-sdk` umbrella crate features or, for runtimes depending on
+/// the FRAME crates directly, declares it like `frame-support` and propagates the runtime features.
+fn add_dependency(workspace: &mut DocumentMut, runtime: &mut DocumentMut, names: &PalletNames) -> Result<(), Box<dyn Error>> {
+    let dependencies = runtime.get_mut("dependencies").and_then(Item::as_table_like_mut).ok_or("The runtime has no dependencies")?;
+    if dependencies.contains_key(&names.crate_name) {
+        return Err(format!("The runtime already depends on {}", names.crate_name).into());
+    }
+
+    if let Some(umbrella) = dependencies.get_mut("polkadot-sdk").and_then(Item::as_table_like_mut) {
+        let has_features = umbrella.get("features").and_then(Item::as_array).is_some();
+        let features = match umbrella.get("workspace").and_then(Item::as_bool) {
+            Some(true) if !has_features => workspace
+                .get_mut("workspace")
+                .and_then(|workspace| workspace.get_mut("dependencies"))
+                .and_then(|dependencies| dependencies.get_mut("polkadot-sdk"))
+                .and_then(Item::as_table_like_mut)
+                .ok_or("polkadot-sdk is not declared in the workspace")?,
+            _ => umbrella,
+        };
+        if features.get("features").is_none() {
+            features.insert("features", toml_edit::value(toml_edit::Array::new()));
+        }
+        let features = features.get_mut("features").and_then(Item::as_array_mut).ok_or("Invalid polkadot-sdk features")?;
+        if features.iter().any(|feature| feature.as_str() == Some(&names.crate_name)) {
+            return Err(format!("{} is already enabled in polkadot-sdk", names.crate_name).into());
+        }
+        add_feature_sorted(features, &names.crate_name);
+        return Ok(());
+    }
+
+    let frame_support = dependencies.get("frame-support").ok_or("The runtime depends neither on polkadot-sdk nor on frame-support")?;
+    let from_workspace = frame_support.as_table_like().and_then(|table| table.get("workspace")).and_then(Item::as_bool) == Some(true);
+    let declaration = if from_workspace {
+        let workspace_dependencies = workspace
+            .get_mut("workspace")
+            .and_then(|workspace| workspace.get_mut("dependencies"))
+            .and_then(Item::as_table_like_mut)
+            .ok_or("The workspace has no dependencies")?;
+        let declaration = pallet_declaration(workspace_dependencies.get("frame-support").ok_or("frame-support is not declared in the workspace")?, names)?;
+        if !workspace_dependencies.contains_key(&names.crate_name) {
+            workspace_dependencies.insert(&names.crate_name, declaration);
+        }
+        without_features(frame_support)
+    } else {
+        pallet_declaration(frame_support, names)?
+    };
+    dependencies.insert(&names.crate_name, declaration);
+
+    if let Some(features) = runtime.get_mut("features").and_then(Item::as_table_like_mut) {
+        for feature in PROPAGATED_FEATURES {
+            if let Some(entries) = features.get_mut(feature).and_then(Item::as_array_mut) {
+                add_feature_sorted(entries, &format!("{}/{}", names.crate_name, feature));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Declaration of a FRAME pallet modelled on the `frame-support` one: the same git source, or the
+/// pallet version released for the same `frame-support` major version.
+fn pallet_declaration(frame_support: &Item, names: &PalletNames) -> Result<Item, Box<dyn Error>> {
+    let mut declaration = without_features(frame_support);
+    let table = match declaration.as_table_like_mut() {
+        Some(table) => table,
+        None => {
+            let version = frame_support.as_str().ok_or("Invalid frame-support declaration")?;
+            return Ok(toml_edit::value(resolve_version(&names.crate_name, version)?));
+        }
+    };
+    if let Some(version) = table.get("version").and_then(Item::as_str).map(String::from) {
+        if table.get("git").is_none() {
+            table.insert("version", toml_edit::value(resolve_version(&names.crate_name, &version)?));
+        }
+    }
+    Ok(declaration)
+}
+
+/// A copy of a dependency declaration without its `features`.
+fn without_features(declaration: &Item) -> Item {
+    let mut declaration = declaration.clone();
+    if let Some(table) = declaration.as_table_like_mut() {
+        table.remove("features");
+    }
+    if let Some(table) = declaration.as_inline_table_mut() {
+        table.fmt();
+    }
+    declaration
+}
+
+/// Looks up the crates.io index for the latest version of a FRAME crate built against the
+/// `frame-support` version of the project, directly or through `polkadot-sdk-frame`.
+fn resolve_version(crate_name: &str, frame_support: &str) -> Result<String, Box<dyn Error>> {
+    let wanted = [compatibility(frame_support)];
+    let index = crates_index(crate_name)?;
+    if let Some(version) = pick_version(&index, "frame-support", &wanted) {
+        return Ok(version);
+    }
+    let frame: Vec<String> = matching_releases(&crates_index("polkadot-sdk-frame")?, "frame-support", &wanted)
+        .iter()
+        .map(|version| compatibility(version))
+        .collect();
+    pick_version(&index, "polkadot-sdk-frame", &frame)
+        .ok_or_else(|| format!("No version of {} found for frame-support {}", crate_name, frame_support).into())
+}
+
+fn crates_index(crate_name: &str) -> Result<String, Box<dyn Error>> {
+    let url = format!("https://index.crates.io/{}/{}/{}", &crate_name[..2], &crate_name[2..4], crate_name);
+    let output = Command::new("curl")
+        .args(["-sSf", &url])
+        .output()
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Failed to look up {} on crates.io: {}", crate_name, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Picks, from the lines of a crates.io index file, the latest version depending on a version of
+/// `dependency` that is semver compatible with one of `compatible`.
+pub fn pick_version(index: &str, dependency: &str, compatible: &[String]) -> Option<String> {
+    matching_releases(index, dependency, compatible)
+        .into_iter()
+        .max_by_key(|version| version.split('.').map(|part| part.parse::<u64>().unwrap_or_default()).collect::<Vec<_>>())
+}
+
+fn matching_releases(index: &str, dependency: &str, compatible: &[String]) -> Vec<String> {
+    index
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|release| release["yanked"] != true)
+        .filter(|release| {
+            release["deps"].as_array().into_iter().flatten().any(|entry| {
+                let name = entry["package"].as_str().or(entry["name"].as_str());
+                name == Some(dependency) && entry["req"].as_str().is_some_and(|req| compatible.contains(&compatibility(req)))
+            })
+        })
+        .filter_map(|release| release["vers"].as_str().map(String::from))
+        .filter(|version| !version.contains('-'))
+        .collect()
+}
+
+/// The part of a version that cargo keeps fixed when upgrading: `40` for `40.1.0` and `0.9` for
+/// `^0.9.1`.
+fn compatibility(version: &str) -> String {
+    let mut parts = version.trim_start_matches(['^', '=', '~', ' ']).split('.');
+    match (parts.next().unwrap_or_default(), parts.next()) {
+        ("0", Some(minor)) => format!("0.{}", minor),
+        (major, _) => major.to_string(),
+    }
+}
+
+/// Inserts a `Config` implementation after the last one in a file that configures `frame_system`.
+pub fn insert_config(code: &str, config: &str) -> Option<String> {
+    if !code.contains("impl frame_system::Config for Runtime") {
+        return None;
+    }
+    let mut last_end = None;
+    let mut search = 0;
+    while let Some(found) = code[search..].find("::Config for Runtime") {
+        let start = search + found;
+        let end = block_end(code, start)?;
+        last_end = Some(end);
+        search = end;
+    }
+    let mut updated = code.to_string();
+    updated.insert_str(last_end?, &format!("\n\n{}", config));
+    Some(updated)
+}
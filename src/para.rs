@@ -0,0 +1,118 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use parity_scale_codec::{Compact, Decode};
+use serde_json::{json, Value};
+use crate::keys::{self, Pair};
+use crate::metadata::{self, Metadata};
+use crate::output;
+use crate::query;
+use crate::rpc::{self, RpcClient};
+use crate::tx;
+use crate::watch;
+
+/// Lease periods granted when force-onboarding a parachain on a dev relay chain.
+const LEASE_PERIODS: u32 = 999;
+
+/// Registers a parachain on a local relay chain and waits until it produces blocks.
+///
+/// Free para ids are reserved and registered by the signer; other ids are force registered with sudo.
+/// The para is then upgraded to a lease holding parachain with sudo, as on dev relays there is no auction.
+pub fn register(relay_url: &str, para_id: u32, genesis_head: &Path, wasm: &Path, signer: &str) -> Result<(), Box<dyn Error>> {
+    let genesis_head = read_hex_or_binary(genesis_head)?;
+    let validation_code = read_hex_or_binary(wasm)?;
+
+    let mut client = RpcClient::connect(relay_url)?;
+    let metadata = metadata::from_rpc(&mut client)?;
+    let pair = Pair::from_uri(signer)?;
+    let account = keys::ss58_encode(&pair.account_id(), metadata.ss58_prefix)?;
+
+    if lifecycle(&mut client, &metadata, para_id)?.is_null() {
+        let next_free = query::fetch(&mut client, &metadata, "Registrar", "NextFreeParaId", &[], None)?;
+        let args = [json!(para_id), json!(rpc::hex_encode(&genesis_head)), json!(rpc::hex_encode(&validation_code))];
+
+        if next_free.as_u64() == Some(para_id as u64) {
+            output::progress(format_args!("Reserving para id {} for {}", para_id, account));
+            let reserve = tx::encode_call(&metadata, "Registrar", "reserve", &[])?;
+            tx::submit(&mut client, &metadata, &pair, &reserve, false)?;
+
+            output::progress(format_args!("\nRegistering para {}", para_id));
+            let register = tx::encode_call(&metadata, "Registrar", "register", &args)?;
+            tx::submit(&mut client, &metadata, &pair, &register, false)?;
+        } else {
+            output::progress(format_args!("Force registering para {} with sudo", para_id));
+            let mut force_args = vec![json!(account), json!(0)];
+            force_args.extend(args);
+            let register = tx::encode_call(&metadata, "Registrar", "force_register", &force_args)?;
+            tx::submit(&mut client, &metadata, &pair, &tx::sudo_call(&metadata, &register)?, false)?;
+        }
+    } else {
+        output::progress(format_args!("Para {} is already registered", para_id));
+    }
+
+    if lifecycle(&mut client, &metadata, para_id)? != json!("Parachain") {
+        output::progress(format_args!("\nOnboarding para {} as a parachain with sudo", para_id));
+        let onboard = if metadata.pallet("Slots").is_ok() {
+            tx::encode_call(&metadata, "Slots", "force_lease", &[json!(para_id), json!(account), json!(0), json!(0), json!(LEASE_PERIODS)])?
+        } else {
+            tx::encode_call(&metadata, "ParasSudoWrapper", "sudo_schedule_parathread_upgrade", &[json!(para_id)])?
+        };
+        tx::submit(&mut client, &metadata, &pair, &tx::sudo_call(&metadata, &onboard)?, false)?;
+    }
+
+    wait_for_blocks(&mut client, &metadata, para_id)
+}
+
+/// Follows the relay chain until the head of the parachain advances past its genesis block.
+fn wait_for_blocks(client: &mut RpcClient, metadata: &Metadata, para_id: u32) -> Result<(), Box<dyn Error>> {
+    output::progress(format_args!("\nWaiting for para {} to produce blocks (onboarding takes effect at the next sessions)...", para_id));
+    let subscription = client.subscribe("chain_subscribeNewHeads", json!([]))?;
+    let mut last_status = String::new();
+
+    loop {
+        let header = client.next_notification(&subscription)?;
+        let relay_number = watch::header_number(&header)?;
+
+        let lifecycle = lifecycle(client, metadata, para_id)?;
+        let head = query::fetch(client, metadata, "Paras", "Heads", &[json!(para_id)], None)?;
+        let para_number = match head.as_str() {
+            Some(head) => Some(head_number(&rpc::hex_decode(head)?)?),
+            None => None,
+        };
+
+        let status = format!(
+            "lifecycle {}, head #{}",
+            lifecycle.as_str().unwrap_or("none"),
+            para_number.map(|number| number.to_string()).unwrap_or_else(|| "-".to_string())
+        );
+        if status != last_status {
+            output::progress(format_args!("  relay #{}: {}", relay_number, status));
+            last_status = status;
+        }
+        if para_number.unwrap_or_default() > 0 {
+            println!("\n✓ Para {} is producing blocks", para_id);
+            return Ok(());
+        }
+    }
+}
+
+fn lifecycle(client: &mut RpcClient, metadata: &Metadata, para_id: u32) -> Result<Value, Box<dyn Error>> {
+    query::fetch(client, metadata, "Paras", "ParaLifecycles", &[json!(para_id)], None)
+}
+
+/// Block number of a parachain head: the header starts with the parent hash followed by the compact number.
+pub fn head_number(head: &[u8]) -> Result<u32, Box<dyn Error>> {
+    if head.len() < 33 {
+        return Err("Invalid parachain head".into());
+    }
+    Ok(Compact::<u32>::decode(&mut &head[32..])?.0)
+}
+
+/// Reads a file as produced by `export-genesis-head`/`export-genesis-wasm`: hex text or raw bytes.
+fn read_hex_or_binary(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    match std::str::from_utf8(&bytes) {
+        Ok(text) if text.trim().starts_with("0x") => rpc::hex_decode(text.trim()),
+        _ => Ok(bytes),
+    }
+}
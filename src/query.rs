@@ -0,0 +1,207 @@
+use std::error::Error;
+use frame_metadata::v14::{StorageEntryMetadata, StorageEntryModifier, StorageEntryType, StorageHasher};
+use scale_info::form::PortableForm;
+use scale_info::TypeDef;
+use serde_json::{json, Value};
+use crate::hashing;
+use crate::metadata::{self, Metadata};
+use crate::rpc::{self, RpcClient};
+use crate::tx;
+
+const KEYS_PAGE_SIZE: u32 = 1000;
+
+/// Hashers of a storage map together with the type of each key.
+type MapKeys<'a> = (&'a [StorageHasher], Vec<u32>);
+
+/// Reads `<pallet>.<item>` and prints it as JSON. Maps queried with fewer keys
+/// than they have are iterated and printed as a list of key/value pairs.
+pub fn run(rpc_url: &str, pallet: &str, item: &str, keys: &[&str], at: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut client = RpcClient::connect(rpc_url)?;
+    let metadata = metadata::from_rpc(&mut client)?;
+    let block_hash = resolve_block(&mut client, at)?;
+
+    let keys: Vec<Value> = keys.iter().map(|key| tx::parse_arg(key)).collect();
+    let (prefix, entry) = storage_entry(&metadata, pallet, item)?;
+    let hashers = match &entry.ty {
+        StorageEntryType::Plain(_) => 0,
+        StorageEntryType::Map { hashers, .. } => hashers.len(),
+    };
+    if keys.len() > hashers {
+        return Err(format!("{}.{} takes at most {} keys", pallet, entry.name, hashers).into());
+    }
+
+    let result = if keys.len() == hashers {
+        fetch(&mut client, &metadata, pallet, item, &keys, block_hash.as_deref())?
+    } else {
+        let key_prefix = storage_key(&metadata, &prefix, entry, &keys)?;
+        iterate(&mut client, &metadata, entry, &key_prefix, block_hash.as_deref())?
+    };
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    Ok(())
+}
+
+/// Accepts a block hash or a block number.
+pub fn resolve_block(client: &mut RpcClient, at: Option<&str>) -> Result<Option<String>, Box<dyn Error>> {
+    match at {
+        None => Ok(None),
+        Some(hash) if hash.starts_with("0x") => Ok(Some(hash.to_string())),
+        Some(number) => {
+            let number: u64 = number.parse().map_err(|_| format!("Invalid block {}", number))?;
+            let hash = client.request("chain_getBlockHash", json!([number]))?;
+            match hash.as_str() {
+                Some(hash) => Ok(Some(hash.to_string())),
+                None => Err(format!("Block {} not found", number).into()),
+            }
+        }
+    }
+}
+
+/// Fetches and decodes a single storage value; missing values yield `null` or the declared default.
+pub fn fetch(client: &mut RpcClient, metadata: &Metadata, pallet: &str, item: &str, keys: &[Value], at: Option<&str>) -> Result<Value, Box<dyn Error>> {
+    let (prefix, entry) = storage_entry(metadata, pallet, item)?;
+    let key = storage_key(metadata, &prefix, entry, keys)?;
+    let result = client.request("state_getStorage", json!([rpc::hex_encode(&key), at]))?;
+    match result.as_str() {
+        Some(data) => decode_storage_value(metadata, entry, &rpc::hex_decode(data)?),
+        None => match entry.modifier {
+            StorageEntryModifier::Optional => Ok(Value::Null),
+            StorageEntryModifier::Default => decode_storage_value(metadata, entry, &entry.default),
+        },
+    }
+}
+
+fn iterate(client: &mut RpcClient, metadata: &Metadata, entry: &StorageEntryMetadata<PortableForm>, key_prefix: &[u8], at: Option<&str>) -> Result<Value, Box<dyn Error>> {
+    let prefix_hex = rpc::hex_encode(key_prefix);
+    let mut items = Vec::new();
+    let mut start_key: Option<String> = None;
+
+    loop {
+        let page = client.request("state_getKeysPaged", json!([prefix_hex, KEYS_PAGE_SIZE, start_key, at]))?;
+        let page: Vec<String> = page
+            .as_array()
+            .map(|keys| keys.iter().filter_map(|key| key.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        if page.is_empty() {
+            break;
+        }
+
+        let changes = client.request("state_queryStorageAt", json!([page, at]))?;
+        let changes = changes.pointer("/0/changes").and_then(Value::as_array).cloned().unwrap_or_default();
+        for change in changes {
+            let (key, value) = match (change[0].as_str(), change[1].as_str()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => continue,
+            };
+            let key_bytes = rpc::hex_decode(key)?;
+            items.push(json!({
+                "key": decode_key(metadata, entry, &key_bytes[32..])?,
+                "value": decode_storage_value(metadata, entry, &rpc::hex_decode(value)?)?,
+            }));
+        }
+
+        if (page.len() as u32) < KEYS_PAGE_SIZE {
+            break;
+        }
+        start_key = page.last().cloned();
+    }
+    Ok(Value::Array(items))
+}
+
+/// Finds a storage entry and the storage prefix of its pallet.
+pub fn storage_entry<'a>(metadata: &'a Metadata, pallet: &str, item: &str) -> Result<(String, &'a StorageEntryMetadata<PortableForm>), Box<dyn Error>> {
+    let pallet = metadata.pallet(pallet)?;
+    let storage = pallet.storage.as_ref().ok_or_else(|| format!("Pallet {} has no storage", pallet.name))?;
+    let entry = storage
+        .entries
+        .iter()
+        .find(|entry| entry.name.eq_ignore_ascii_case(item))
+        .ok_or_else(|| format!("Storage item {}.{} not found in runtime metadata", pallet.name, item))?;
+    Ok((storage.prefix.clone(), entry))
+}
+
+/// Builds `twox128(prefix) ++ twox128(item) ++ hasher(key)...` for the given (possibly partial) keys.
+pub fn storage_key(metadata: &Metadata, prefix: &str, entry: &StorageEntryMetadata<PortableForm>, keys: &[Value]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut key = storage_prefix(prefix, &entry.name);
+    if keys.is_empty() {
+        return Ok(key);
+    }
+
+    let (hashers, key_types) = map_keys(metadata, entry)?;
+    for ((hasher, ty), value) in hashers.iter().zip(key_types).zip(keys) {
+        let mut encoded = Vec::new();
+        metadata
+            .encode_value(ty, value, &mut encoded)
+            .map_err(|e| format!("Invalid key {}: {}", value, e))?;
+        key.extend_from_slice(&hash_key(hasher, &encoded));
+    }
+    Ok(key)
+}
+
+pub fn storage_prefix(prefix: &str, item: &str) -> Vec<u8> {
+    let mut key = hashing::twox_128(prefix.as_bytes()).to_vec();
+    key.extend_from_slice(&hashing::twox_128(item.as_bytes()));
+    key
+}
+
+fn map_keys<'a>(metadata: &Metadata, entry: &'a StorageEntryMetadata<PortableForm>) -> Result<MapKeys<'a>, Box<dyn Error>> {
+    let (hashers, key) = match &entry.ty {
+        StorageEntryType::Map { hashers, key, .. } => (hashers, key.id),
+        StorageEntryType::Plain(_) => return Err(format!("{} is not a storage map", entry.name).into()),
+    };
+    if hashers.len() == 1 {
+        return Ok((hashers, vec![key]));
+    }
+    match metadata.types.resolve(key).map(|ty| &ty.type_def) {
+        Some(TypeDef::Tuple(tuple)) if tuple.fields.len() == hashers.len() => {
+            Ok((hashers, tuple.fields.iter().map(|field| field.id).collect()))
+        }
+        _ => Err(format!("Unexpected key type for {}", entry.name).into()),
+    }
+}
+
+pub fn hash_key(hasher: &StorageHasher, encoded: &[u8]) -> Vec<u8> {
+    match hasher {
+        StorageHasher::Blake2_128 => hashing::blake2_128(encoded).to_vec(),
+        StorageHasher::Blake2_256 => hashing::blake2_256(encoded).to_vec(),
+        StorageHasher::Blake2_128Concat => [&hashing::blake2_128(encoded)[..], encoded].concat(),
+        StorageHasher::Twox128 => hashing::twox_128(encoded).to_vec(),
+        StorageHasher::Twox256 => hashing::twox_256(encoded).to_vec(),
+        StorageHasher::Twox64Concat => [&hashing::twox_64(encoded)[..], encoded].concat(),
+        StorageHasher::Identity => encoded.to_vec(),
+    }
+}
+
+/// Recovers the keys of a map entry from the part of the storage key after the prefix.
+/// Keys behind non-concat hashers cannot be recovered and are shown as their hash.
+fn decode_key(metadata: &Metadata, entry: &StorageEntryMetadata<PortableForm>, mut input: &[u8]) -> Result<Value, Box<dyn Error>> {
+    let (hashers, key_types) = map_keys(metadata, entry)?;
+    let mut keys = Vec::new();
+    for (hasher, ty) in hashers.iter().zip(key_types) {
+        let (hash_len, concat) = match hasher {
+            StorageHasher::Blake2_128Concat => (16, true),
+            StorageHasher::Twox64Concat => (8, true),
+            StorageHasher::Identity => (0, true),
+            StorageHasher::Blake2_128 | StorageHasher::Twox128 => (16, false),
+            StorageHasher::Blake2_256 | StorageHasher::Twox256 => (32, false),
+        };
+        if input.len() < hash_len {
+            return Err("Storage key is too short".into());
+        }
+        let (hash, rest) = input.split_at(hash_len);
+        input = rest;
+        if concat {
+            keys.push(metadata.decode_value(ty, &mut input)?);
+        } else {
+            keys.push(Value::String(rpc::hex_encode(hash)));
+        }
+    }
+    Ok(if keys.len() == 1 { keys.remove(0) } else { Value::Array(keys) })
+}
+
+pub fn decode_storage_value(metadata: &Metadata, entry: &StorageEntryMetadata<PortableForm>, data: &[u8]) -> Result<Value, Box<dyn Error>> {
+    let ty = match &entry.ty {
+        StorageEntryType::Plain(ty) => ty.id,
+        StorageEntryType::Map { value, .. } => value.id,
+    };
+    Ok(metadata.decode_value(ty, &mut &data[..])?)
+}
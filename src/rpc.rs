@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::net::TcpStream;
+use serde_json::{json, Value};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+use crate::config;
+
+/// Network of the node started by `dot serve`, see `networks.local` in `dot config show`.
+pub const DEFAULT_RPC_URL: &str = config::LOCAL_NETWORK;
+
+/// Minimal blocking JSON-RPC client over a websocket connection.
+pub struct RpcClient {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    next_id: u64,
+    notifications: VecDeque<Value>,
+}
+
+impl RpcClient {
+    /// Connects to an RPC url or to a network named in the config, e.g. `local`.
+    pub fn connect(url: &str) -> Result<Self, Box<dyn Error>> {
+        let url = config::current().rpc_url(url)?;
+        let url = if let Some(rest) = url.strip_prefix("http://") {
+            format!("ws://{}", rest)
+        } else if let Some(rest) = url.strip_prefix("https://") {
+            format!("wss://{}", rest)
+        } else {
+            url.to_string()
+        };
+
+        let (socket, _) = tungstenite::connect(url.as_str())
+            .map_err(|e| format!("Failed to connect to {}: {}", url, e))?;
+        Ok(RpcClient { socket, next_id: 1, notifications: VecDeque::new() })
+    }
+
+    /// Sends a request and waits for its response, queueing any notifications received meanwhile.
+    pub fn request(&mut self, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.socket.send(Message::Text(request.to_string()))?;
+
+        loop {
+            let message = self.read_json()?;
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                if message.get("method").is_some() {
+                    self.notifications.push_back(message);
+                }
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(format!("{} failed: {}", method, error).into());
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    /// Starts a subscription and returns its id.
+    pub fn subscribe(&mut self, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+        self.request(method, params)
+    }
+
+    /// Waits for the next notification of the given subscription and returns its result.
+    pub fn next_notification(&mut self, subscription: &Value) -> Result<Value, Box<dyn Error>> {
+        let matches = |message: &Value| message.pointer("/params/subscription") == Some(subscription);
+
+        if let Some(position) = self.notifications.iter().position(matches) {
+            let message = self.notifications.remove(position).unwrap();
+            return Ok(message.pointer("/params/result").cloned().unwrap_or(Value::Null));
+        }
+
+        loop {
+            let message = self.read_json()?;
+            if matches(&message) {
+                return Ok(message.pointer("/params/result").cloned().unwrap_or(Value::Null));
+            }
+            if message.get("method").is_some() {
+                self.notifications.push_back(message);
+            }
+        }
+    }
+
+    fn read_json(&mut self) -> Result<Value, Box<dyn Error>> {
+        loop {
+            match self.socket.read()? {
+                Message::Text(text) => return Ok(serde_json::from_str(&text)?),
+                Message::Close(_) => return Err("RPC connection closed".into()),
+                _ => continue,
+            }
+        }
+    }
+}
+
+pub fn hex_decode(value: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(value).map_err(|e| format!("Invalid hex string: {}", e).into())
+}
+
+pub fn hex_encode(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
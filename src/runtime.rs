@@ -0,0 +1,288 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use serde_json::{json, Value};
+use crate::events::{self, EventRecord};
+use crate::hashing;
+use crate::keys::Pair;
+use crate::metadata::{self, Metadata};
+use crate::output;
+use crate::rpc::{self, RpcClient};
+use crate::tx;
+use crate::wasm;
+
+/// Runtime API traits commonly found in Polkadot SDK runtimes, used to name the API ids of a runtime version.
+const KNOWN_APIS: &[&str] = &[
+    "Core",
+    "Metadata",
+    "BlockBuilder",
+    "TaggedTransactionQueue",
+    "OffchainWorkerApi",
+    "SessionKeys",
+    "AccountNonceApi",
+    "TransactionPaymentApi",
+    "TransactionPaymentCallApi",
+    "GenesisBuilder",
+    "AuraApi",
+    "AuraUnincludedSegmentApi",
+    "BabeApi",
+    "GrandpaApi",
+    "BeefyApi",
+    "BeefyMmrApi",
+    "MmrApi",
+    "ParachainHost",
+    "AuthorityDiscoveryApi",
+    "CollectCollationInfo",
+    "GetCoreSelectorApi",
+    "RelayParentOffsetApi",
+    "DryRunApi",
+    "XcmPaymentApi",
+    "LocationToAccountApi",
+    "TrustedQueryApi",
+    "FungiblesApi",
+    "AssetConversionApi",
+    "NominationPoolsApi",
+    "StakingApi",
+    "ValidateStatement",
+    "Benchmark",
+    "TryRuntime",
+];
+
+/// What `dot runtime info` reports about a wasm file.
+struct RuntimeInfo {
+    file_size: usize,
+    compressed: bool,
+    code_size: usize,
+    code_hash: [u8; 32],
+    version: wasm::RuntimeVersion,
+}
+
+impl RuntimeInfo {
+    fn read(path: &Path) -> Result<RuntimeInfo, Box<dyn Error>> {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let code = wasm::decompress(&bytes)?;
+        Ok(RuntimeInfo {
+            file_size: bytes.len(),
+            compressed: wasm::is_compressed(&bytes),
+            code_size: code.len(),
+            // The chain stores the blob as uploaded, so the on-chain code hash is the hash of the file.
+            code_hash: hashing::blake2_256(&bytes),
+            version: wasm::runtime_version(&code)?,
+        })
+    }
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let version = &self.version;
+        vec![
+            ("spec_name", version.spec_name.clone()),
+            ("impl_name", version.impl_name.clone()),
+            ("spec_version", version.spec_version.to_string()),
+            ("impl_version", version.impl_version.to_string()),
+            ("transaction_version", version.transaction_version.to_string()),
+            ("authoring_version", version.authoring_version.to_string()),
+            ("file size", format_size(self.file_size)),
+            ("compressed", if self.compressed { "yes (zstd)".to_string() } else { "no".to_string() }),
+            ("code size", format_size(self.code_size)),
+            ("code hash", rpc::hex_encode(&self.code_hash)),
+        ]
+    }
+
+    fn to_json(&self, path: &Path) -> Value {
+        let version = &self.version;
+        let apis: Vec<Value> = version
+            .apis
+            .iter()
+            .map(|(id, api_version)| json!({ "name": api_name(id), "id": rpc::hex_encode(id), "version": api_version }))
+            .collect();
+        json!({
+            "path": path.display().to_string(),
+            "spec_name": version.spec_name,
+            "impl_name": version.impl_name,
+            "spec_version": version.spec_version,
+            "impl_version": version.impl_version,
+            "transaction_version": version.transaction_version,
+            "authoring_version": version.authoring_version,
+            "file_size": self.file_size,
+            "compressed": self.compressed,
+            "code_size": self.code_size,
+            "code_hash": rpc::hex_encode(&self.code_hash),
+            "apis": apis,
+        })
+    }
+}
+
+/// Prints the version, APIs, sizes and code hash of a runtime wasm, or the differences between two.
+/// As JSON, both runtimes are printed in full.
+pub fn info(path: &Path, compare: Option<&Path>, json: bool) -> Result<(), Box<dyn Error>> {
+    let info = RuntimeInfo::read(path)?;
+    if json {
+        let value = match compare {
+            Some(compare) => json!({ "old": info.to_json(path), "new": RuntimeInfo::read(compare)?.to_json(compare) }),
+            None => info.to_json(path),
+        };
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+    let other = match compare {
+        Some(compare) => RuntimeInfo::read(compare)?,
+        None => {
+            println!("{}", path.display());
+            for (name, value) in info.fields() {
+                println!("  {:<20} {}", name, value);
+            }
+            println!("  APIs:");
+            for (id, version) in &info.version.apis {
+                println!("    {:<30} v{}", api_name(id), version);
+            }
+            return Ok(());
+        }
+    };
+
+    println!("{} -> {}", path.display(), compare.unwrap().display());
+    for ((name, old), (_, new)) in info.fields().into_iter().zip(other.fields()) {
+        if old == new {
+            println!("  {:<20} {}", name, old);
+        } else {
+            println!("  {:<20} {} -> {}", name, old, new);
+        }
+    }
+
+    println!("  APIs:");
+    let mut changes = 0;
+    for (id, version) in &info.version.apis {
+        match other.version.apis.iter().find(|(other_id, _)| other_id == id) {
+            None => println!("    - {:<28} v{}", api_name(id), version),
+            Some((_, new)) if new != version => println!("    ~ {:<28} v{} -> v{}", api_name(id), version, new),
+            Some(_) => continue,
+        }
+        changes += 1;
+    }
+    for (id, version) in &other.version.apis {
+        if !info.version.apis.iter().any(|(old_id, _)| old_id == id) {
+            println!("    + {:<28} v{}", api_name(id), version);
+            changes += 1;
+        }
+    }
+    if changes == 0 {
+        println!("    unchanged ({} APIs)", info.version.apis.len());
+    }
+    Ok(())
+}
+
+fn api_name(id: &[u8; 8]) -> String {
+    KNOWN_APIS
+        .iter()
+        .find(|name| &hashing::blake2_64(name.as_bytes()) == id)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| rpc::hex_encode(id))
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.2} MiB ({} bytes)", bytes as f64 / (1024.0 * 1024.0), bytes)
+    } else {
+        format!("{:.1} KiB ({} bytes)", bytes as f64 / 1024.0, bytes)
+    }
+}
+
+/// Upgrades the runtime of a running chain to the given wasm.
+///
+/// Uses `System.authorize_upgrade` + `System.apply_authorized_upgrade` when the runtime supports it and
+/// `System.set_code` otherwise. With `sudo` the code is submitted through `Sudo.sudo_unchecked_weight`
+/// and `System.authorize_upgrade` through `Sudo.sudo`.
+pub fn upgrade(rpc_url: &str, wasm_path: &Path, signer: &str, sudo: bool) -> Result<(), Box<dyn Error>> {
+    let code = fs::read(wasm_path).map_err(|e| format!("Failed to read {}: {}", wasm_path.display(), e))?;
+    let new_version = wasm::runtime_version(&wasm::decompress(&code)?)?;
+
+    let mut client = RpcClient::connect(rpc_url)?;
+    let metadata = metadata::from_rpc(&mut client)?;
+    let pair = Pair::from_uri(signer)?;
+
+    let old_version = client.request("state_getRuntimeVersion", json!([]))?;
+    let old_spec_version = old_version["specVersion"].as_u64().ok_or("Unexpected state_getRuntimeVersion response")?;
+    if old_version["specName"].as_str() != Some(new_version.spec_name.as_str()) {
+        return Err(format!(
+            "The new runtime is {} but the chain runs {}",
+            new_version.spec_name, old_version["specName"]
+        )
+        .into());
+    }
+    if new_version.spec_version as u64 <= old_spec_version {
+        return Err(format!(
+            "spec_version of the new runtime ({}) must be higher than the current one ({})",
+            new_version.spec_version, old_spec_version
+        )
+        .into());
+    }
+    output::progress(format_args!(
+        "Upgrading {} from spec_version {} to {} ({})",
+        new_version.spec_name, old_spec_version, new_version.spec_version, new_version.impl_name
+    ));
+
+    let privileged = |call_data: Vec<u8>| if sudo { tx::sudo_call(&metadata, &call_data) } else { Ok(call_data) };
+    // The code upload weighs a whole block, so sudo skips its weight check
+    let unchecked = |call_data: Vec<u8>| if sudo { tx::sudo_unchecked_weight_call(&metadata, &call_data) } else { Ok(call_data) };
+    let system = metadata.pallet("System")?;
+    let outcome = if metadata.variant(system.calls, "authorize_upgrade").is_some() {
+        let code_hash = rpc::hex_encode(&hashing::blake2_256(&code));
+        output::progress(format_args!("\nAuthorizing upgrade to code hash {}", code_hash));
+        let authorize = tx::encode_call(&metadata, "System", "authorize_upgrade", &[json!(code_hash)])?;
+        tx::submit(&mut client, &metadata, &pair, &privileged(authorize)?, false)?;
+
+        output::progress(format_args!("\nApplying authorized upgrade ({} bytes)", code.len()));
+        let apply = tx::encode_call(&metadata, "System", "apply_authorized_upgrade", &[json!(rpc::hex_encode(&code))])?;
+        tx::submit(&mut client, &metadata, &pair, &unchecked(apply)?, false)?
+    } else {
+        output::progress(format_args!("\nSetting code ({} bytes)", code.len()));
+        let set_code = tx::encode_call(&metadata, "System", "set_code", &[json!(rpc::hex_encode(&code))])?;
+        tx::submit(&mut client, &metadata, &pair, &unchecked(set_code)?, false)?
+    };
+
+    // Parachains only enact the new code once the relay chain accepted it, in a later block.
+    if !outcome.events.iter().any(is_code_updated) {
+        wait_for_code_updated(&mut client, metadata)?;
+    }
+
+    let version = client.request("state_getRuntimeVersion", json!([]))?;
+    println!(
+        "\n✓ Runtime upgraded: {} {} -> {} (impl_version {} -> {}, transaction_version {} -> {})",
+        new_version.spec_name,
+        old_spec_version,
+        version["specVersion"],
+        old_version["implVersion"],
+        version["implVersion"],
+        old_version["transactionVersion"],
+        version["transactionVersion"]
+    );
+    Ok(())
+}
+
+fn is_code_updated(event: &EventRecord) -> bool {
+    event.is("System", "CodeUpdated")
+}
+
+fn wait_for_code_updated(client: &mut RpcClient, mut metadata: Metadata) -> Result<(), Box<dyn Error>> {
+    output::progress("\nWaiting for System.CodeUpdated...");
+    let key = rpc::hex_encode(&events::system_events_key());
+    let subscription = client.subscribe("state_subscribeStorage", json!([[key]]))?;
+
+    loop {
+        let change = client.next_notification(&subscription)?;
+        let data = match change.pointer("/changes/0/1").and_then(Value::as_str) {
+            Some(data) => rpc::hex_decode(data)?,
+            None => continue,
+        };
+        // Once the code is updated, events are encoded against the new metadata.
+        let records = match events::decode_events(&metadata, &data) {
+            Ok(records) => records,
+            Err(_) => {
+                metadata = metadata::from_rpc(client)?;
+                events::decode_events(&metadata, &data)?
+            }
+        };
+        if let Some(event) = records.iter().find(|event| is_code_updated(event)) {
+            output::progress(format_args!("  {} in block {}", events::format_event(event), change["block"].as_str().unwrap_or_default()));
+            return Ok(());
+        }
+    }
+}
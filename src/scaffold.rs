@@ -0,0 +1,110 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use crate::template::{self, Template};
+
+/// Files that are copied as they are instead of getting the template identifiers renamed.
+const SKIPPED_EXTENSIONS: &[&str] = &["md", "png", "jpg", "svg", "wasm"];
+
+/// Creates a new project from a template: clones it, drops the upstream history, renames the
+/// template crates and identifiers after the project and starts a fresh git repository.
+pub fn new_project(name: &str, template: &Template, parent: &Path) -> Result<(), Box<dyn Error>> {
+    validate_name(name)?;
+    let destination = parent.join(name);
+    if destination.exists() {
+        return Err(format!("{} already exists", destination.display()).into());
+    }
+    fs::create_dir_all(parent)?;
+
+    println!("↓ Cloning the {} template from {}", template.name, template.url);
+    let mut lock = template::clone(template, &destination)?;
+    fs::remove_dir_all(destination.join(".git"))?;
+    lock.project = Some(name.to_string());
+
+    let renamed = rename_files(&destination, &template.name, name)?;
+    println!("✓ Renamed the template identifiers in {} files", renamed);
+
+    lock.write(&destination)?;
+    init_repository(&destination);
+    println!("\n✓ Created {} from the {} template in {}", name, template.name, destination.display());
+    println!("  Template revision {} recorded in {}", &lock.commit[..lock.commit.len().min(10)], template::LOCKFILE);
+    println!("  Build it with `cd {} && {}`", destination.display(), template.build);
+    Ok(())
+}
+
+/// Project names become crate names, so they follow the cargo package naming rules.
+pub fn validate_name(name: &str) -> Result<(), Box<dyn Error>> {
+    if !is_valid_name(name) {
+        return Err(format!("Invalid project name {}, use lowercase letters, digits, - and _", name).into());
+    }
+    Ok(())
+}
+
+/// Lowercase letters, digits, `-` and `_`, starting with a letter. Also keeps template names safe to use in paths.
+pub fn is_valid_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Replaces `<template>-template` and `<template>_template`, e.g. `parachain-template-node` and
+/// `parachain_template_runtime`, with the project name. Links to the upstream
+/// `polkadot-sdk-<template>-template` repository are kept.
+pub fn rename_identifiers(content: &str, template: &str, project: &str) -> String {
+    let upstream = format!("polkadot-sdk-{}-template", template);
+    content
+        .split(&upstream)
+        .map(|part| {
+            part.replace(&format!("{}-template", template), &project.replace('_', "-"))
+                .replace(&format!("{}_template", template), &project.replace('-', "_"))
+        })
+        .collect::<Vec<_>>()
+        .join(&upstream)
+}
+
+/// Renames the template identifiers in every text file below `dir`, returning how many changed.
+pub fn rename_files(dir: &Path, template: &str, project: &str) -> Result<usize, Box<dyn Error>> {
+    let mut renamed = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name != "target") {
+                renamed += rename_files(&path, template, project)?;
+            }
+            continue;
+        }
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        if SKIPPED_EXTENSIONS.contains(&extension) {
+            continue;
+        }
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            // Binary file
+            Err(_) => continue,
+        };
+        let updated = rename_identifiers(&content, template, project);
+        if updated != content {
+            fs::write(&path, updated)?;
+            renamed += 1;
+        }
+    }
+    Ok(renamed)
+}
+
+fn init_repository(dir: &Path) {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    };
+    if !git(&["init", "--quiet"]) {
+        eprintln!("Failed to initialise a git repository, skipping");
+        return;
+    }
+    if !(git(&["add", "-A"]) && git(&["commit", "--quiet", "-m", "Initial commit"])) {
+        eprintln!("⚠ Failed to create the initial commit, is a git user.name and user.email set? Commit the project files yourself");
+    }
+}
@@ -0,0 +1,101 @@
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+use serde_json::{json, Value};
+use crate::config;
+use crate::output;
+use crate::rpc::RpcClient;
+
+/// Block time passed to omni-node in manual and instant mode. The node only seals on its own once a
+/// day, every other block is requested through `engine_createBlock`.
+const MANUAL_BLOCK_TIME_MS: u64 = 86_400_000;
+
+/// How often the instant sealer looks for pending extrinsics.
+const INSTANT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How blocks are authored by the dev node started with `dot serve`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SealMode {
+    /// A block as soon as an extrinsic is in the pool.
+    Instant,
+    /// Blocks only on `dot seal`.
+    Manual,
+    /// A block every given number of milliseconds.
+    Interval(u64),
+}
+
+impl SealMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "instant" => Ok(SealMode::Instant),
+            "manual" => Ok(SealMode::Manual),
+            _ => match value.strip_prefix("interval=").map(str::parse::<u64>) {
+                Some(Ok(ms)) if ms > 0 => Ok(SealMode::Interval(ms)),
+                _ => Err(format!("Invalid seal mode {}, expected instant, manual or interval=<ms>", value)),
+            },
+        }
+    }
+
+    /// Block authoring options of omni-node for this mode.
+    pub fn node_args(&self) -> Vec<String> {
+        let block_time = match self {
+            SealMode::Interval(ms) => *ms,
+            SealMode::Instant | SealMode::Manual => MANUAL_BLOCK_TIME_MS,
+        };
+        vec!["--dev-block-time".to_string(), block_time.to_string()]
+    }
+}
+
+/// RPC endpoint of the local node, following a `--rpc-port N` or `--rpc-port=N` among the node
+/// arguments or else `node.rpc-port` of the config. The last one wins, as for the node.
+pub fn local_rpc_url(node_args: &[&str]) -> String {
+    let mut port = None;
+    for (index, arg) in node_args.iter().enumerate() {
+        if *arg == "--rpc-port" {
+            port = node_args.get(index + 1).map(|port| port.to_string());
+        } else if let Some(value) = arg.strip_prefix("--rpc-port=") {
+            port = Some(value.to_string());
+        }
+    }
+    let port = port.unwrap_or_else(|| config::current().integer("node.rpc-port").to_string());
+    format!("ws://127.0.0.1:{}", port)
+}
+
+/// Produces `count` blocks on a node running in manual or instant seal mode.
+pub fn create_blocks(rpc_url: &str, count: u32, finalize: bool) -> Result<(), Box<dyn Error>> {
+    let mut client = RpcClient::connect(rpc_url)?;
+    for _ in 0..count {
+        let block = create_block(&mut client, true, finalize)?;
+        println!("✓ Sealed block {}", block["hash"].as_str().unwrap_or_default());
+    }
+    Ok(())
+}
+
+fn create_block(client: &mut RpcClient, create_empty: bool, finalize: bool) -> Result<Value, Box<dyn Error>> {
+    client
+        .request("engine_createBlock", json!([create_empty, finalize, null]))
+        .map_err(|e| format!("Failed to seal a block, is the node running with `dot serve --seal manual`? {}", e).into())
+}
+
+/// Seals a block whenever the transaction pool of the local node is not empty. Runs in the
+/// background for as long as the process lives and waits for the node to come up.
+pub fn spawn_instant_sealer(rpc_url: String) {
+    thread::spawn(move || loop {
+        let mut client = match RpcClient::connect(&rpc_url) {
+            Ok(client) => client,
+            Err(_) => {
+                thread::sleep(Duration::from_secs(1));
+                continue;
+            }
+        };
+        output::progress("Instant seal: producing a block for every new extrinsic");
+        while let Ok(pending) = client.request("author_pendingExtrinsics", json!([])) {
+            if pending.as_array().is_some_and(|pending| !pending.is_empty()) {
+                if let Err(e) = create_block(&mut client, false, true) {
+                    eprintln!("{}", e);
+                }
+            }
+            thread::sleep(INSTANT_POLL_INTERVAL);
+        }
+    });
+}
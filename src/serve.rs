@@ -1,5 +1,13 @@
-use std::process::{Command, ExitStatus};
-use std::path::Path;
+use std::process::{Child, Command, ExitStatus};
+use std::path::{Path, PathBuf};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use crate::config;
+use crate::install;
+use crate::output;
 
 
 pub fn make_executable(script_path: &Path) -> Result<ExitStatus, String> {
@@ -11,38 +19,217 @@ pub fn make_executable(script_path: &Path) -> Result<ExitStatus, String> {
 }
 
 pub fn run_script(script_path: &Path, args: &[&str]) -> Result<ExitStatus, String> {
-    Command::new("bash")
-        .arg(script_path)
-        .args(args)
-        .status()
-        .map_err(|e| format!("Failed to run script: {}", e))
+    let mut command = Command::new("bash");
+    command.arg(script_path).args(args);
+    output::command(&command);
+    command.status().map_err(|e| format!("Failed to run script: {}", e))
+}
+
+/// The `node.flags` and ports of the config followed by `args`. Ports in `args` take precedence.
+pub fn node_args(args: &[&str]) -> Vec<String> {
+    let config = config::current();
+    let mut node_args = config.strings("node.flags");
+    for (flag, key) in [("--rpc-port", "node.rpc-port"), ("--port", "node.p2p-port")] {
+        let given = args.iter().any(|arg| *arg == flag || arg.starts_with(&format!("{}=", flag)));
+        if !given {
+            node_args.extend([flag.to_string(), config.integer(key).to_string()]);
+        }
+    }
+    node_args.extend(args.iter().map(|arg| arg.to_string()));
+    node_args
+}
+
+/// Points the omni-node script at the configured binary and chain spec, unless a chain spec
+/// was already chosen, e.g. by `run_with_chain_spec`.
+fn set_node_env() {
+    env::set_var("OMNI_NODE", install::binary_path("polkadot-omni-node"));
+    if env::var_os("CHAIN_SPEC").is_none() {
+        env::set_var("CHAIN_SPEC", config::current().path("chain.spec"));
+    }
 }
 
-pub fn run(args: &[&str]) {
-    println!("Running omni-node...");
+pub fn run(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    output::progress("Running omni-node...");
 
-    let script_path = Path::new("./script/omni-node.sh");
+    let script_path = &config::current().path("paths.script");
+    set_node_env();
+    let args = node_args(args);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
 
     match make_executable(script_path) {
         Ok(status) if status.success() => {
-            println!("Script is executable.");
-        }
-        _ => {
-            eprintln!("Failed to make script executable");
-            return;
+            output::progress("Script is executable.");
         }
+        _ => return Err("Failed to make script executable".into()),
     }
 
-    println!("Running script: {:?}", script_path);
+    output::progress(format_args!("Running script: {:?}", script_path));
 
-    match run_script(script_path, args) {
+    match run_script(script_path, &args) {
         Ok(status) if status.success() => {
             println!("Omni-node is now running.");
+            Ok(())
         }
-        _ => {
-            eprintln!("Failed to run script at {:?}", script_path);
+        _ => Err(format!("Failed to run script at {:?}", script_path).into()),
+    }
+}
+
+/// Serves omni-node with another chain spec than the `chain.spec` of the config.
+pub fn run_with_chain_spec(chain_spec: &Path, args: &[&str]) -> Result<(), Box<dyn Error>> {
+    env::set_var("CHAIN_SPEC", chain_spec);
+    run(args)
+}
+
+pub fn spawn_script(script_path: &Path, args: &[&str]) -> Result<Child, String> {
+    let mut command = Command::new("bash");
+    command.arg(script_path).args(args);
+    output::command(&command);
+    command.spawn().map_err(|e| format!("Failed to run script: {}", e))
+}
+
+/// Serves omni-node with the runtime built from `crate_path`. With `watch` set,
+/// the runtime is rebuilt and the node restarted whenever the crate sources change.
+pub fn run_runtime_crate(crate_path: &Path, args: &[&str], watch: bool) -> Result<(), Box<dyn Error>> {
+    output::progress(format_args!("Running omni-node with runtime from {:?}...", crate_path));
+
+    prepare_runtime(crate_path)?;
+
+    let script_path = &config::current().path("paths.script");
+    if !make_executable(script_path)?.success() {
+        return Err(format!("Failed to make {:?} executable", script_path).into());
+    }
+    set_node_env();
+    let args = node_args(args);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let mut node = spawn_script(script_path, &args)?;
+    println!("Omni-node is now running.");
+
+    if !watch {
+        let status = node.wait()?;
+        if !status.success() {
+            return Err(format!("Omni-node exited with {}", status).into());
         }
+        return Ok(());
     }
+
+    println!("Watching {:?} for changes...", crate_path);
+    let mut last_change = latest_source_change(crate_path);
+    loop {
+        thread::sleep(Duration::from_secs(2));
+
+        if let Ok(Some(status)) = node.try_wait() {
+            return Err(format!("Omni-node exited with {}", status).into());
+        }
+
+        let change = latest_source_change(crate_path);
+        if change <= last_change {
+            continue;
+        }
+        last_change = change;
+
+        println!("\n↻ Runtime sources changed. Rebuilding...\n");
+        if let Err(e) = prepare_runtime(crate_path) {
+            eprintln!("{} -> Keeping the running node.", e);
+            continue;
+        }
+
+        let _ = node.kill();
+        let _ = node.wait();
+        node = spawn_script(script_path, &args)?;
+        println!("Omni-node restarted with the new runtime.");
+    }
+}
+
+/// Builds the runtime crate and regenerates the chain spec from its wasm.
+pub fn prepare_runtime(crate_path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    build_runtime(crate_path)?;
+    let wasm_path = find_runtime_wasm(crate_path)?;
+    output::progress(format_args!("Using runtime wasm: {:?}", wasm_path));
+    install::gen_chain_spec_from_wasm(&wasm_path)?;
+    Ok(wasm_path)
+}
+
+pub fn build_runtime(crate_path: &Path) -> Result<(), Box<dyn Error>> {
+    output::progress(format_args!("Building runtime at {:?}", crate_path));
+
+    let mut command = Command::new("cargo");
+    command.args(["build", "--release"]).current_dir(crate_path);
+    if output::quiet() {
+        command.arg("--quiet");
+    }
+    output::command(&command);
+    let status = command.status().map_err(|e| format!("Failed to run cargo: {}", e))?;
+
+    if !status.success() {
+        return Err("Failed to build the runtime".into());
+    }
+    Ok(())
+}
+
+/// Locates the `*.compact.compressed.wasm` of the runtime package at `crate_path` in the
+/// `target/` directory of the crate or of one of its parent workspaces.
+pub fn find_runtime_wasm(crate_path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let crate_path = fs::canonicalize(crate_path)?;
+    let package = package_name(&crate_path)?;
+    let file_name = format!("{}.compact.compressed.wasm", package.replace('-', "_"));
+    for dir in crate_path.ancestors() {
+        let path = dir.join("target").join("release").join("wbuild").join(&package).join(&file_name);
+        if path.is_file() {
+            return Ok(path);
+        }
+    }
+    Err(format!("No {} found under target/ for {:?}", file_name, crate_path).into())
+}
+
+/// The `package.name` of the `Cargo.toml` in `crate_path`.
+fn package_name(crate_path: &Path) -> Result<String, Box<dyn Error>> {
+    let manifest_path = crate_path.join("Cargo.toml");
+    let manifest: toml::Table = fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("Failed to read {:?}: {}", manifest_path, e))?
+        .parse()
+        .map_err(|e| format!("Invalid {:?}: {}", manifest_path, e))?;
+    let name = manifest.get("package").and_then(|package| package.get("name")).and_then(toml::Value::as_str);
+    Ok(name.ok_or(format!("{:?} has no package name", manifest_path))?.to_string())
+}
+
+/// The most recently built `*.compact.compressed.wasm` below a `wbuild` directory.
+pub fn newest_wasm(wbuild: &Path) -> Option<PathBuf> {
+    let mut found: Vec<PathBuf> = Vec::new();
+    collect_files(wbuild, ".compact.compressed.wasm", &mut found);
+    found
+        .into_iter()
+        .max_by_key(|path| modified(path).unwrap_or(SystemTime::UNIX_EPOCH))
+}
+
+/// Returns the latest modification time of the crate sources, ignoring `target/`.
+pub fn latest_source_change(crate_path: &Path) -> Option<SystemTime> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    collect_files(crate_path, ".rs", &mut files);
+    files.push(crate_path.join("Cargo.toml"));
+    files.iter().filter_map(|path| modified(path)).max()
+}
+
+fn collect_files(dir: &Path, suffix: &str, found: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name == "target" || name == ".git") {
+                continue;
+            }
+            collect_files(&path, suffix, found);
+        } else if path.to_string_lossy().ends_with(suffix) {
+            found.push(path);
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
 }
 
 // pub fn run(args: &[&str]) {
@@ -1,43 +1,527 @@
+use std::error::Error;
+use std::fs;
 use std::process::Command;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use serde_json::json;
+use crate::config;
+use crate::install;
+use crate::output;
+use crate::scaffold;
+use crate::serve;
 
-pub fn run_template(args: &[&str], template: &str) {
-    println!("Running {}...{:?}", template, args);
+/// A project template that can be installed or scaffolded with `--template <name>`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    pub name: String,
+    pub url: String,
+    /// Tag, branch or commit checked out after cloning [default: the default branch].
+    pub git_ref: Option<String>,
+    /// Without `git_ref`, check out the latest release tag instead of the default branch.
+    pub pin_release: bool,
+    pub build: String,
+    pub run: String,
+    pub description: String,
+}
 
-    let destination = format!("./templates/{}-template", template);
-    let destination_path = Path::new(&destination);
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("minimal", "Minimal FRAME runtime and node, the best starting point to learn"),
+    ("parachain", "Parachain runtime and node ready to connect to a relay chain"),
+    ("solochain", "Standalone chain with its own consensus"),
+];
 
-    if destination_path.exists() {
-        println!("\n✅︎ {}-template directory already exists at {}. -> Entering.\n", template, destination);
-    } else {
-        println!("\n↓ Let's grab the {} template from github.\n", template);
-        let status = Command::new("git")
-            .args(&["clone", "--quiet", &format!("https://github.com/paritytech/polkadot-sdk-{}-template.git", template), &destination])
-            .status()
-            .expect("Failed to clone template");
+fn builtin_templates() -> Vec<Template> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .map(|(name, description)| Template {
+            name: name.to_string(),
+            url: config::current().string("template.source").replace("{name}", name),
+            git_ref: None,
+            // Built-ins follow the polkadot-sdk releases, so a checkout is pinned to the release it was made from.
+            pin_release: true,
+            build: "cargo build --release".to_string(),
+            run: "cargo run --release -- --dev".to_string(),
+            description: description.to_string(),
+        })
+        .collect()
+}
+
+/// Built-in templates followed by the `[templates.<name>]` tables of the user config. A user
+/// template with the name of a built-in one replaces it.
+pub fn registry() -> Result<Vec<Template>, Box<dyn Error>> {
+    let mut templates = builtin_templates();
+    for template in user_templates()? {
+        match templates.iter_mut().find(|existing| existing.name == template.name) {
+            Some(existing) => *existing = template,
+            None => templates.push(template),
+        }
+    }
+    Ok(templates)
+}
 
-        if !status.success() {
-            eprintln!("Failed to clone template");
-            return;
+/// Names of the templates of `registry`, without loading the config, to check `--template`.
+pub fn names() -> Result<Vec<String>, Box<dyn Error>> {
+    let mut names: Vec<String> = BUILTIN_TEMPLATES.iter().map(|(name, _)| name.to_string()).collect();
+    for template in user_templates()? {
+        if !names.contains(&template.name) {
+            names.push(template.name);
         }
     }
+    Ok(names)
+}
+
+fn user_templates() -> Result<Vec<Template>, Box<dyn Error>> {
+    match config::user_config_path().filter(|path| path.exists()) {
+        Some(path) => {
+            let config = fs::read_to_string(&path)?;
+            parse_templates(&config).map_err(|e| format!("Invalid config {}: {}", path.display(), e).into())
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Reads the templates of a config file:
+///
+/// ```toml
+/// [templates.my-chain]
+/// url = "https://github.com/my-org/my-chain-template.git"
+/// ref = "v1.0.0"
+/// build = "cargo build --release"
+/// run = "./target/release/my-node --dev"
+/// description = "Our parachain template"
+/// ```
+pub fn parse_templates(config: &str) -> Result<Vec<Template>, Box<dyn Error>> {
+    let config: toml::Table = config.parse()?;
+    let tables = match config.get("templates") {
+        Some(toml::Value::Table(tables)) => tables,
+        Some(_) => return Err("`templates` must be a table".into()),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut templates = Vec::new();
+    for (name, table) in tables {
+        validate_name(name)?;
+        let field = |key: &str| table.get(key).and_then(toml::Value::as_str).map(String::from);
+        templates.push(Template {
+            name: name.clone(),
+            url: field("url").ok_or(format!("Template {} has no url", name))?,
+            git_ref: field("ref"),
+            pin_release: false,
+            build: field("build").unwrap_or_else(|| "cargo build --release".to_string()),
+            run: field("run").unwrap_or_else(|| "cargo run --release -- --dev".to_string()),
+            description: field("description").unwrap_or_default(),
+        });
+    }
+    Ok(templates)
+}
+
+/// Template names end up in the checkout path, so they follow the project naming rules.
+fn validate_name(name: &str) -> Result<(), Box<dyn Error>> {
+    if !scaffold::is_valid_name(name) {
+        return Err(format!("Invalid template name {}, use lowercase letters, digits, - and _", name).into());
+    }
+    Ok(())
+}
+
+pub fn find(name: &str) -> Result<Template, Box<dyn Error>> {
+    let templates = registry()?;
+    match templates.iter().find(|template| template.name == name) {
+        Some(template) => Ok(template.clone()),
+        None => {
+            let names: Vec<&str> = templates.iter().map(|template| template.name.as_str()).collect();
+            Err(format!("Unknown template {}, available templates: {}", name, names.join(", ")).into())
+        }
+    }
+}
+
+pub fn list() -> Result<(), Box<dyn Error>> {
+    for template in registry()? {
+        println!("{:<16} {}", template.name, template.description);
+        println!("{:<16} {}{}", "", template.url, template.git_ref.map(|r| format!(" @ {}", r)).unwrap_or_default());
+    }
+    if let Some(path) = config::user_config_path() {
+        println!("\nAdd your own templates under [templates.<name>] in {}", path.display());
+    }
+    Ok(())
+}
+
+/// Lockfile recording which template revision a project or template checkout comes from.
+pub const LOCKFILE: &str = "dot.lock";
+
+/// The `[template]` table of the lockfile.
+#[derive(Debug, PartialEq)]
+pub struct Lock {
+    pub name: String,
+    pub url: String,
+    /// Ref asked for when cloning, if any.
+    pub git_ref: Option<String>,
+    /// Default branch of the template repository.
+    pub branch: String,
+    pub commit: String,
+    /// Project name, for projects created with `dot new` whose history no longer contains the template.
+    pub project: Option<String>,
+}
+
+impl Lock {
+    pub fn read(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = dir.join(LOCKFILE);
+        let content = fs::read_to_string(&path)
+            .map_err(|_| format!("No {} in {}, was it created with `dot new` or `dot install`?", LOCKFILE, dir.display()))?;
+        Self::parse(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e).into())
+    }
 
-    println!("Entered directory: {}", destination);
+    pub fn parse(content: &str) -> Result<Self, Box<dyn Error>> {
+        let lock: toml::Table = content.parse()?;
+        let table = lock.get("template").and_then(toml::Value::as_table).ok_or("missing [template] table")?;
+        let field = |key: &str| table.get(key).and_then(toml::Value::as_str).map(String::from);
+        Ok(Lock {
+            name: field("name").ok_or("missing name")?,
+            url: field("url").ok_or("missing url")?,
+            git_ref: field("ref"),
+            branch: field("branch").ok_or("missing branch")?,
+            commit: field("commit").ok_or("missing commit")?,
+            project: field("project"),
+        })
+    }
 
-    let repo_path = Path::new(&destination);
-    println!("args: {:?}", args);
+    pub fn write(&self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(dir.join(LOCKFILE), self.to_toml()?)?;
+        Ok(())
+    }
 
-    let status = Command::new("cargo")
-        .args(&["run", "--release", "--", "--dev"])
+    pub fn to_toml(&self) -> Result<String, Box<dyn Error>> {
+        let mut table = toml::Table::new();
+        table.insert("name".to_string(), self.name.clone().into());
+        table.insert("url".to_string(), self.url.clone().into());
+        if let Some(git_ref) = &self.git_ref {
+            table.insert("ref".to_string(), git_ref.clone().into());
+        }
+        table.insert("branch".to_string(), self.branch.clone().into());
+        table.insert("commit".to_string(), self.commit.clone().into());
+        if let Some(project) = &self.project {
+            table.insert("project".to_string(), project.clone().into());
+        }
+        let mut lock = toml::Table::new();
+        lock.insert("template".to_string(), toml::Value::Table(table));
+        Ok(format!("# Generated by dot, records the template revision of this project.\n{}", toml::to_string(&lock)?))
+    }
+}
+
+/// Runs git in `dir` and returns its trimmed output.
+fn git(dir: &Path, args: &[&str]) -> Result<String, Box<dyn Error>> {
+    run_git(Command::new("git").current_dir(dir), args)
+}
+
+fn run_git(command: &mut Command, args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = command
         .args(args)
-        .current_dir(repo_path)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Clones a template into `destination`, checks out its ref, if any, and returns the lock of the checkout.
+pub fn clone(template: &Template, destination: &Path) -> Result<Lock, Box<dyn Error>> {
+    let status = Command::new("git")
+        .args(["clone", "--quiet", &template.url])
+        .arg(destination)
         .status()
-        .expect("Failed to run project");
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !status.success() {
+        return Err(format!("Failed to clone {}", template.url).into());
+    }
+
+    let branch = git(destination, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    let git_ref = match &template.git_ref {
+        Some(git_ref) => Some(git_ref.clone()),
+        None if template.pin_release => latest_tag(destination)?,
+        None => None,
+    };
+    if let Some(git_ref) = &git_ref {
+        git(destination, &["checkout", "--quiet", git_ref])
+            .map_err(|e| format!("Failed to checkout {} of the template: {}", git_ref, e))?;
+    }
+    Ok(Lock {
+        name: template.name.clone(),
+        url: template.url.clone(),
+        git_ref,
+        branch,
+        commit: git(destination, &["rev-parse", "HEAD"])?,
+        project: None,
+    })
+}
+
+/// The most recently created tag of a repository, if it has any.
+fn latest_tag(dir: &Path) -> Result<Option<String>, Box<dyn Error>> {
+    let tag = git(dir, &["for-each-ref", "--sort=-creatordate", "--count=1", "--format=%(refname:short)", "refs/tags"])?;
+    Ok(Some(tag).filter(|tag| !tag.is_empty()))
+}
+
+/// Fetches `target` from the template repository into the repository at `dir` and returns its commit.
+fn fetch(dir: &Path, lock: &Lock, target: &str) -> Result<String, Box<dyn Error>> {
+    if !dir.join(".git").exists() {
+        git(dir, &["init", "--quiet"])?;
+    }
+    git(dir, &["fetch", "--quiet", &lock.url, target])?;
+    git(dir, &["rev-parse", "FETCH_HEAD"])
+}
+
+/// Latest commit of the default branch of the template and how many commits the checkout is behind it.
+pub fn behind(dir: &Path, lock: &Lock) -> Result<(String, u32), Box<dyn Error>> {
+    let upstream = fetch(dir, lock, &lock.branch)?;
+    let count = git(dir, &["rev-list", "--count", &format!("{}..{}", lock.commit, upstream)])?;
+    Ok((upstream, count.parse()?))
+}
+
+pub fn status(dir: &Path, json: bool) -> Result<(), Box<dyn Error>> {
+    let lock = Lock::read(dir)?;
+    if json {
+        let (upstream, behind) = behind(dir, &lock)?;
+        let status = json!({
+            "name": lock.name,
+            "url": lock.url,
+            "ref": lock.git_ref,
+            "branch": lock.branch,
+            "commit": lock.commit,
+            "upstream": upstream,
+            "behind": behind,
+        });
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+    println!("Template: {} ({})", lock.name, lock.url);
+    match &lock.git_ref {
+        Some(git_ref) => println!("Pinned:   {} at {}", git_ref, short(&lock.commit)),
+        None => println!("Tracking: {} at {}", lock.branch, short(&lock.commit)),
+    }
 
+    let (upstream, behind) = behind(dir, &lock)?;
+    if behind == 0 {
+        println!("\n✓ Up to date with {} ({})", lock.branch, short(&upstream));
+    } else if lock.git_ref.is_some() {
+        println!("\n{} commits behind {} ({}), run `dot template update --ref <tag|commit>` to move", behind, lock.branch, short(&upstream));
+    } else {
+        println!("\n{} commits behind {} ({}), run `dot template update` to catch up", behind, lock.branch, short(&upstream));
+    }
+    Ok(())
+}
+
+/// Moves a checkout to `git_ref` (default: the locked ref, or the latest commit of the default
+/// branch), by rebasing the local commits onto it or, with `reclone`, by cloning it again.
+/// Projects created with `dot new` get the template changes applied instead.
+pub fn update(dir: &Path, git_ref: Option<&str>, reclone: bool) -> Result<(), Box<dyn Error>> {
+    let mut lock = Lock::read(dir)?;
+    let git_ref = git_ref.map(String::from).or(lock.git_ref.clone());
+    let target = fetch(dir, &lock, git_ref.as_deref().unwrap_or(&lock.branch))?;
+    if target == lock.commit {
+        println!("✓ Already at {}", short(&target));
+        return Ok(());
+    }
+    if !git(dir, &["status", "--porcelain", "--untracked-files=no"])?.is_empty() {
+        return Err(format!("{} has uncommitted changes, commit or stash them first", dir.display()).into());
+    }
+
+    if reclone {
+        if let Some(project) = &lock.project {
+            return Err(format!("{} was created with `dot new`, re-cloning would discard it. Create a new project instead", project).into());
+        }
+        // Everything that is not in the template repository would be lost with the old checkout
+        let untracked: Vec<String> = git(dir, &["status", "--porcelain"])?
+            .lines()
+            .filter_map(|line| line.strip_prefix("?? "))
+            .filter(|path| *path != LOCKFILE)
+            .map(String::from)
+            .collect();
+        if !untracked.is_empty() {
+            return Err(format!("{} has untracked files, re-cloning would delete them: {}", dir.display(), untracked.join(", ")).into());
+        }
+        let local_commits = git(dir, &["rev-list", "--count", &format!("{}..HEAD", lock.commit)])?;
+        if local_commits != "0" {
+            return Err(format!("{} has {} local commits on top of the template, update without --reclone to keep them", dir.display(), local_commits).into());
+        }
+
+        println!("↓ Re-cloning {} at {}", lock.url, git_ref.as_deref().unwrap_or(&lock.branch));
+        let template = Template {
+            name: lock.name.clone(),
+            url: lock.url.clone(),
+            git_ref: git_ref.clone(),
+            pin_release: false,
+            build: String::new(),
+            run: String::new(),
+            description: String::new(),
+        };
+        lock = reclone_into(&template, dir)?;
+    } else if let Some(project) = lock.project.clone() {
+        // The history of a `dot new` project starts after the template, so the template changes are
+        // applied as a patch, renamed after the project like the project files were
+        println!("Applying the template changes from {} to {}", short(&lock.commit), short(&target));
+        if git(dir, &["cat-file", "-e", &format!("{}^{{commit}}", lock.commit)]).is_err() {
+            git(dir, &["fetch", "--quiet", &lock.url, &lock.commit])?;
+        }
+        let base = project_tree(dir, &lock.commit, &lock.name, &project)?;
+        let updated = project_tree(dir, &target, &lock.name, &project)?;
+        let patch = dir.join(".git").join("dot-template.patch");
+        git(dir, &["diff", "--binary", &format!("--output={}", patch.display()), &base, &updated])?;
+        let applied = if base == updated {
+            Ok(String::new())
+        } else {
+            git(dir, &["apply", "--3way", &patch.display().to_string()])
+        };
+        fs::remove_file(&patch)?;
+        if let Err(e) = applied {
+            if git(dir, &["diff", "--name-only", "--diff-filter=U"])?.is_empty() {
+                return Err(format!("Failed to apply the template changes: {}", e).into());
+            }
+            lock.git_ref = git_ref;
+            lock.commit = target;
+            lock.write(dir)?;
+            return Err(format!("The template changes conflict with the project, resolve the conflicts and commit them\n{}", e).into());
+        }
+        lock.git_ref = git_ref;
+        lock.commit = target;
+        lock.write(dir)?;
+        println!("✓ Updated {} to {}, review and commit the staged changes", lock.name, short(&lock.commit));
+        return Ok(());
+    } else {
+        if git(dir, &["merge-base", "--is-ancestor", &lock.commit, "HEAD"]).is_err() {
+            return Err(format!(
+                "The history of {} does not contain the template commit {}, merge the template changes manually",
+                dir.display(),
+                short(&lock.commit)
+            )
+            .into());
+        }
+        println!("Rebasing the local commits onto {}", short(&target));
+        git(dir, &["rebase", "--quiet", "--onto", &target, &lock.commit])
+            .map_err(|e| format!("{}\nResolve the conflicts and run `git rebase --continue`, or `git rebase --abort`", e))?;
+        lock.git_ref = git_ref;
+        lock.commit = target;
+    }
+
+    lock.write(dir)?;
+    println!("✓ Updated {} to {}", lock.name, short(&lock.commit));
+    Ok(())
+}
+
+/// Writes the tree of a template `commit` with its identifiers renamed after `project`, as `dot new`
+/// does, and returns the tree id. A separate index keeps the project index and files untouched.
+fn project_tree(dir: &Path, commit: &str, template: &str, project: &str) -> Result<String, Box<dyn Error>> {
+    let export = dir.join(".git").join("dot-template");
+    let index = dir.join(".git").join("dot-template.index");
+    if export.exists() {
+        fs::remove_dir_all(&export)?;
+    }
+    let git = |args: &[&str]| run_git(Command::new("git").current_dir(dir).env("GIT_INDEX_FILE", &index), args);
+
+    git(&["read-tree", commit])?;
+    git(&["checkout-index", "--all", &format!("--prefix={}/", export.display())])?;
+    scaffold::rename_files(&export, template, project)?;
+    git(&["--work-tree", &export.display().to_string(), "add", "--all", "--force"])?;
+    let tree = git(&["write-tree"]);
+    fs::remove_dir_all(&export)?;
+    fs::remove_file(&index)?;
+    tree
+}
+
+/// Clones a template next to `dir` and only replaces `dir` with it once the clone succeeded.
+fn reclone_into(template: &Template, dir: &Path) -> Result<Lock, Box<dyn Error>> {
+    let name = dir.file_name().ok_or("Invalid template directory")?.to_string_lossy().to_string();
+    let staging = dir.with_file_name(format!(".{}.reclone", name));
+    let previous = dir.with_file_name(format!(".{}.previous", name));
+    for leftover in [&staging, &previous] {
+        if leftover.exists() {
+            fs::remove_dir_all(leftover)?;
+        }
+    }
+
+    let lock = match clone(template, &staging) {
+        Ok(lock) => lock,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+    fs::rename(dir, &previous)?;
+    if let Err(e) = fs::rename(&staging, dir) {
+        fs::rename(&previous, dir)?;
+        return Err(format!("Failed to replace {}: {}", dir.display(), e).into());
+    }
+    fs::remove_dir_all(&previous)?;
+    Ok(lock)
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(10)]
+}
+
+/// Runs a shell command such as `cargo run --release -- --dev` with extra arguments appended.
+fn run_command(command: &str, args: &[&str], dir: &Path) -> Result<(), Box<dyn Error>> {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(format!("{} \"$@\"", command)).arg("sh").args(args).current_dir(dir);
+    output::command(&shell);
+    let status = shell.status().map_err(|e| format!("Failed to run `{}`: {}", command, e))?;
     if !status.success() {
-        eprintln!("Failed to run project");
-        return;
+        return Err(format!("`{}` failed", command).into());
+    }
+    Ok(())
+}
+
+/// Clones a template into `<paths.templates>/<name>-template` unless it is already there and returns the checkout.
+fn checkout(template: &Template) -> Result<PathBuf, Box<dyn Error>> {
+    validate_name(&template.name)?;
+    let destination = config::current().path("paths.templates").join(format!("{}-template", template.name));
+
+    if destination.exists() {
+        output::progress(format_args!("\n✅︎ {}-template directory already exists at {}. -> Entering.\n", template.name, destination.display()));
+        if let Ok(lock) = Lock::read(&destination) {
+            if template.git_ref.is_some() && lock.git_ref != template.git_ref {
+                println!(
+                    "⚠ The checkout is at {}, not {}. Run `dot template update --path {} --ref {}` to switch.\n",
+                    lock.git_ref.as_deref().unwrap_or(&lock.branch),
+                    template.git_ref.as_deref().unwrap_or_default(),
+                    destination.display(),
+                    template.git_ref.as_deref().unwrap_or_default()
+                );
+            }
+        }
+    } else {
+        output::progress(format_args!("\n↓ Let's grab the {} template from {}.\n", template.name, template.url));
+        let lock = clone(template, &destination).map_err(|e| format!("Failed to clone template: {}", e))?;
+        lock.write(&destination).map_err(|e| format!("Failed to write {}: {}", LOCKFILE, e))?;
+    }
+
+    output::progress(format_args!("Entered directory: {}", destination.display()));
+    Ok(destination)
+}
+
+pub fn run_template(args: &[&str], template: &Template) -> Result<(), Box<dyn Error>> {
+    output::progress(format_args!("Running {}...{:?}", template.name, args));
+
+    let repo_path = checkout(template)?;
+
+    run_command(&template.build, &[], &repo_path).map_err(|e| format!("Failed to build project: {}", e))?;
+    run_command(&template.run, args, &repo_path).map_err(|e| format!("Failed to run project: {}", e))?;
+
+    println!("{} is now running.", template.name);
+    Ok(())
+}
+
+/// Builds only the runtime crate of a template and serves it with omni-node instead of
+/// compiling the template's own node.
+pub fn run_template_omni(args: &[&str], template: &Template) -> Result<(), Box<dyn Error>> {
+    if template.name == "solochain" {
+        return Err("The solochain template runs its own consensus and cannot be served by omni-node".into());
+    }
+    output::progress(format_args!("Running the {} runtime with omni-node...{:?}", template.name, args));
+
+    let runtime_path = checkout(template)?.join("runtime");
+    if !runtime_path.join("Cargo.toml").exists() {
+        return Err(format!("No runtime crate found at {}", runtime_path.display()).into());
     }
 
-    println!("{} is now running.", template);
-}
\ No newline at end of file
+    install::install_chain_spec_builder()?;
+    install::install_omni_node()?;
+    serve::run_runtime_crate(&runtime_path, args, false)
+}
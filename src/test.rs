@@ -1,12 +1,40 @@
 #[cfg(test)]
 mod e2e_tests {
-    use super::*;
     use std::fs;
+    use std::os::unix::fs::PermissionsExt;
     use std::path::Path;
     use std::process::Command;
-    use crate::serve::make_executable;
-    use crate::install::{install, install_polkadot, install_chain_spec_builder, install_omni_node, run_download_script, gen_chain_spec, move_chain_spec};
+    use crate::serve::{make_executable, find_runtime_wasm};
+    use crate::install::{install, move_chain_spec};
     use crate::process::Stdio;
+    use crate::metadata;
+    use frame_metadata::v14::{ExtrinsicMetadata, PalletMetadata, PalletStorageMetadata, RuntimeMetadataV14, StorageEntryMetadata, StorageEntryModifier, StorageEntryType};
+    use frame_metadata::RuntimeMetadataPrefixed;
+    use parity_scale_codec::Encode;
+    use scale_info::{meta_type, Path as TypePath, Type, TypeInfo};
+    use scale_info::build::Fields;
+    use parity_scale_codec::Compact;
+    use serde_json::json;
+    use crate::keys::{self, Pair, Scheme};
+    use crate::keystore::Keystore;
+    use crate::query;
+    use crate::watch;
+    use crate::events::EventRecord;
+    use crate::para;
+    use crate::wasm;
+    use crate::hashing;
+    use crate::try_runtime;
+    use crate::bench;
+    use crate::fork;
+    use crate::seal::{self, SealMode};
+    use crate::template;
+    use crate::scaffold;
+    use crate::build;
+    use crate::pallet::{self, PalletNames};
+    use crate::config::{self, Config};
+    use crate::cli::{Cli, Commands, CargoProfile, OutputFormat, Runtime};
+    use clap::{CommandFactory, Parser};
+    use frame_metadata::v14::StorageHasher;
 
 
     // INSTALL TESTS
@@ -99,7 +127,7 @@ mod e2e_tests {
 
         // Simulate the chain spec generation command
         let chain_spec_status = Command::new("./binaries/chain-spec-builder")
-            .args(&[
+            .args([
                 "create",
                 "-t", "development",
                 "--relay-chain", "westend2",
@@ -130,7 +158,7 @@ mod e2e_tests {
     #[test]
     fn test_install() {
         // This is a high-level integration test, calling the install function
-        install("template_name");
+        let _ = install("template_name");
 
         // You can add assertions here for the expected log outputs or effects
     }
@@ -142,13 +170,1069 @@ mod e2e_tests {
         let script_path = Path::new("script/omni-node.sh");
 
         // Ensure the script exists and is not already executable
-        let result = make_executable(&script_path);
+        let result = make_executable(script_path);
 
         assert!(result.is_ok(), "Failed to make script executable");
 
         // Verify the script is executable
-        let metadata = std::fs::metadata(&script_path).unwrap();
-        assert!(metadata.permissions().readonly() == false, "File should be executable");
+        let metadata = std::fs::metadata(script_path).unwrap();
+        assert!(!metadata.permissions().readonly(), "File should be executable");
     
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_find_runtime_wasm() {
+        let workspace = std::env::temp_dir().join("dot-test-runtime-workspace");
+        let _ = fs::remove_dir_all(&workspace);
+        for runtime in ["my-runtime", "other-runtime"] {
+            let crate_path = workspace.join(runtime);
+            fs::create_dir_all(&crate_path).unwrap();
+            fs::write(crate_path.join("Cargo.toml"), format!("[package]\nname = \"{}\"\n", runtime)).unwrap();
+            let wbuild = workspace.join("target/release/wbuild").join(runtime);
+            fs::create_dir_all(&wbuild).unwrap();
+            let file_name = runtime.replace('-', "_");
+            fs::write(wbuild.join(format!("{}.wasm", file_name)), b"").unwrap();
+            fs::write(wbuild.join(format!("{}.compact.compressed.wasm", file_name)), b"").unwrap();
+        }
+
+        // The wasm of the crate is picked, not the most recently built one of the workspace
+        let wasm = find_runtime_wasm(&workspace.join("my-runtime")).expect("Runtime wasm should be found");
+        assert!(wasm.ends_with("my-runtime/my_runtime.compact.compressed.wasm"));
+        let wasm = find_runtime_wasm(&workspace.join("other-runtime")).expect("Runtime wasm should be found");
+        assert!(wasm.ends_with("other-runtime/other_runtime.compact.compressed.wasm"));
+
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+
+    // METADATA TESTS
+    fn test_metadata_bytes() -> Vec<u8> {
+        let pallet = PalletMetadata {
+            name: "Balances",
+            storage: Some(PalletStorageMetadata {
+                prefix: "Balances",
+                entries: vec![
+                    StorageEntryMetadata {
+                        name: "TotalIssuance",
+                        modifier: StorageEntryModifier::Default,
+                        ty: StorageEntryType::Plain(meta_type::<Vec<u8>>()),
+                        default: vec![0],
+                        docs: vec![],
+                    },
+                    StorageEntryMetadata {
+                        name: "Maybe",
+                        modifier: StorageEntryModifier::Optional,
+                        ty: StorageEntryType::Plain(meta_type::<Option<(u32, bool, Compact<u128>)>>()),
+                        default: vec![0],
+                        docs: vec![],
+                    },
+                ],
+            }),
+            calls: None,
+            event: None,
+            constants: vec![],
+            error: None,
+            index: 10,
+        };
+        let extrinsic = ExtrinsicMetadata { ty: meta_type::<()>(), version: 4, signed_extensions: vec![] };
+        RuntimeMetadataPrefixed::from(RuntimeMetadataV14::new(vec![pallet], extrinsic, meta_type::<()>())).encode()
+    }
+
+    #[test]
+    fn test_metadata_from_wasm() {
+        // A fake runtime that only implements the legacy `Metadata_metadata` API
+        let opaque = test_metadata_bytes().encode();
+        let data: String = opaque.iter().map(|byte| format!("\\{:02x}", byte)).collect();
+        let packed = ((opaque.len() as u64) << 32) | 16;
+        let runtime = wat::parse_str(format!(
+            r#"(module
+                (import "env" "memory" (memory 1))
+                (import "env" "ext_allocator_malloc_version_1" (func $malloc (param i32) (result i32)))
+                (import "env" "ext_storage_get_version_1" (func $storage_get (param i64) (result i64)))
+                (global (export "__heap_base") i32 (i32.const 8192))
+                (data (i32.const 16) "{}")
+                (func (export "Metadata_metadata") (param i32 i32) (result i64)
+                    (drop (call $malloc (i32.const 32)))
+                    (i64.const {})))"#,
+            data, packed
+        ))
+        .unwrap();
+
+        let wasm_path = std::env::temp_dir().join("dot-test-runtime.wasm");
+        fs::write(&wasm_path, runtime).unwrap();
+        let metadata = metadata::from_wasm(&wasm_path).expect("Metadata should be extracted");
+        fs::remove_file(&wasm_path).unwrap();
+
+        assert_eq!(metadata.version, 14);
+        let pallet = metadata.pallet("balances").expect("Pallet lookup ignores case");
+        assert_eq!(pallet.index, 10);
+        let entry = &pallet.storage.as_ref().unwrap().entries[0];
+        match &entry.ty {
+            StorageEntryType::Plain(ty) => assert_eq!(metadata.type_name(ty.id), "Vec<u8>"),
+            _ => panic!("Expected a plain storage entry"),
+        }
+        assert!(metadata.pallet("Assets").is_err());
+    }
+
+    #[test]
+    fn test_dynamic_codec_roundtrip() {
+        let metadata = metadata::decode(&test_metadata_bytes()).unwrap();
+        let entries = &metadata.pallet("Balances").unwrap().storage.as_ref().unwrap().entries;
+        let ty = match &entries[1].ty {
+            StorageEntryType::Plain(ty) => ty.id,
+            _ => unreachable!(),
+        };
+
+        let mut encoded = Vec::new();
+        metadata.encode_value(ty, &json!([7, true, "1000000000000000000000"]), &mut encoded).unwrap();
+        assert_eq!(encoded, Some((7u32, true, Compact(1_000_000_000_000_000_000_000u128))).encode());
+        let decoded = metadata.decode_value(ty, &mut &encoded[..]).unwrap();
+        assert_eq!(decoded, json!([7, true, "1000000000000000000000"]));
+
+        let mut none = Vec::new();
+        metadata.encode_value(ty, &json!(null), &mut none).unwrap();
+        assert_eq!(none, vec![0]);
+        assert!(metadata.encode_value(ty, &json!([7, true]), &mut Vec::new()).is_err());
+    }
+
+    // KEY TESTS
+    #[test]
+    fn test_dev_accounts() {
+        let alice = Pair::from_uri("//Alice").unwrap().account_id();
+        assert_eq!(hex::encode(alice), "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d");
+        assert_eq!(keys::ss58_encode(&alice, 42).unwrap(), "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+
+        let stash = Pair::from_uri("//Alice//stash").unwrap().account_id();
+        assert_eq!(keys::ss58_encode(&stash, 42).unwrap(), "5GNJqTPyNqANBkUVMN1LPPrxXnFouWXoe2wNSmmEoLctxiZY");
+        assert!(keys::ss58_encode(&alice, 16383).is_ok());
+        assert!(keys::ss58_encode(&alice, 16384).is_err());
+
+        let bob = keys::parse_account("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty").unwrap();
+        assert_eq!(bob, Pair::from_uri("//Bob").unwrap().account_id());
+        assert!(keys::ss58_decode("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694tz").is_err());
+    }
+
+    #[test]
+    fn test_key_schemes() {
+        let ed25519 = Pair::from_uri_with_scheme("//Alice", Scheme::Ed25519).unwrap();
+        assert_eq!(hex::encode(ed25519.public()), "88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee");
+        assert_eq!(ed25519.sign(b"payload").len(), 64);
+
+        let ecdsa = Pair::from_uri_with_scheme("//Alice", Scheme::Ecdsa).unwrap();
+        assert_eq!(hex::encode(ecdsa.public()), "020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1");
+        assert_eq!(hex::encode(ecdsa.account_id()), "01e552298e47454041ea31273b4b630c64c104e4514aa3643490b8aaca9cf8ed");
+        assert_eq!(ecdsa.sign(b"payload").len(), 65);
+
+        assert!(Pair::from_uri_with_scheme("//Alice/soft", Scheme::Ed25519).is_err());
+        assert_eq!(keys::generate_phrase(24).unwrap().split(' ').count(), 24);
+    }
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let path = std::env::temp_dir().join("dot-test-keystore/keys.json");
+        let _ = fs::remove_file(&path);
+
+        let pair = Pair::from_uri_with_scheme("//Bob", Scheme::Ed25519).unwrap();
+        let mut keystore = Keystore::open(&path).unwrap();
+        keystore.add("bob", "//Bob", &pair, "secret").unwrap();
+        assert!(keystore.add("bob", "//Bob", &pair, "secret").is_err());
+
+        let keystore = Keystore::open(&path).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("//Bob"));
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(!path.with_extension("json.tmp").exists());
+        assert_eq!(keystore.unlock("bob", "secret").unwrap(), ("//Bob".to_string(), Scheme::Ed25519));
+        assert!(keystore.unlock("bob", "wrong").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_keystore_unreadable() {
+        let path = std::env::temp_dir().join("dot-test-keystore-unreadable/keys.json");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, [0xff, 0xfe, 0x00]).unwrap();
+
+        assert!(Keystore::open(&path).is_err());
+        assert_eq!(fs::read(&path).unwrap(), [0xff, 0xfe, 0x00]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    // QUERY TESTS
+    #[test]
+    fn test_storage_keys() {
+        let prefix = query::storage_prefix("System", "Events");
+        assert_eq!(hex::encode(prefix), "26aa394eea5630e07c48ae0c9558cef780d41e5e16056765bc8461851072c9d7");
+
+        let alice = Pair::from_uri("//Alice").unwrap().account_id();
+        let mut key = query::storage_prefix("System", "Account");
+        key.extend_from_slice(&query::hash_key(&StorageHasher::Blake2_128Concat, &alice));
+        assert_eq!(
+            hex::encode(key),
+            "26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9\
+             de1e86a9a8c739864cf3cc5ec2bea59fd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+        );
+        assert_eq!(query::hash_key(&StorageHasher::Twox64Concat, &[1, 0, 0, 0]).len(), 12);
+    }
+
+    // WATCH TESTS
+    #[test]
+    fn test_header_hash() {
+        // Polkadot genesis header
+        let header = json!({
+            "parentHash": format!("0x{}", "00".repeat(32)),
+            "number": "0x0",
+            "stateRoot": "0x29d0d972cd27cbc511e9589fcb7a4506d5eb6a9e8df205f00472e5ab354a4e17",
+            "extrinsicsRoot": "0x03170a2e7597b7b7e3d84c05391d139a62b157e78786d8c082f29dcf4c111314",
+            "digest": { "logs": [] },
+        });
+        assert_eq!(
+            hex::encode(watch::header_hash(&header).unwrap()),
+            "91b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c3"
+        );
+
+        let mut header = header;
+        header["number"] = json!("0x4d2");
+        header["digest"]["logs"] = json!(["0x0661757261200000000000000000"]);
+        let mut encoded = vec![0u8; 32];
+        encoded.extend(Compact(1234u32).encode());
+        encoded.extend(hex::decode("29d0d972cd27cbc511e9589fcb7a4506d5eb6a9e8df205f00472e5ab354a4e17").unwrap());
+        encoded.extend(hex::decode("03170a2e7597b7b7e3d84c05391d139a62b157e78786d8c082f29dcf4c111314").unwrap());
+        encoded.extend(Compact(1u32).encode());
+        encoded.extend(hex::decode("0661757261200000000000000000").unwrap());
+        assert_eq!(watch::header_hash(&header).unwrap(), hashing::blake2_256(&encoded));
+        assert_eq!(watch::header_number(&header).unwrap(), 1234);
+        assert!(watch::header_hash(&json!({ "number": "0x1" })).is_err());
+    }
+
+    #[test]
+    fn test_filter_events() {
+        let event = |pallet: &str, name: &str| EventRecord {
+            extrinsic_index: Some(0),
+            pallet: pallet.to_string(),
+            name: name.to_string(),
+            fields: json!({}),
+        };
+        let records = vec![event("System", "ExtrinsicSuccess"), event("Balances", "Transfer"), event("System", "NewAccount")];
+        let names = |pallet| watch::filter_events(&records, pallet).map(|event| event.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names(Some("System")), ["ExtrinsicSuccess", "NewAccount"]);
+        assert_eq!(names(Some("Balances")), ["Transfer"]);
+        assert_eq!(names(None).len(), 3);
+    }
+
+    // PARA TESTS
+    #[test]
+    fn test_para_head_number() {
+        let mut head = vec![0u8; 32];
+        head.extend(Compact(1234u32).encode());
+        head.extend([0u8; 64]);
+        assert_eq!(para::head_number(&head).unwrap(), 1234);
+        assert!(para::head_number(&[0u8; 16]).is_err());
+    }
+
+    // RUNTIME TESTS
+    #[test]
+    fn test_runtime_version_section() {
+        let version = ("dot-runtime", "dot-runtime", 1u32, 101u32, 2u32, Vec::<([u8; 8], u32)>::new(), 3u32).encode();
+        let mut section = vec!["runtime_version".len() as u8];
+        section.extend_from_slice(b"runtime_version");
+        section.extend_from_slice(&version);
+
+        let mut code = b"\0asm\x01\0\0\0".to_vec();
+        // Section id 0 (custom) and its size as a single byte LEB128 integer
+        code.extend([0, section.len() as u8]);
+        code.extend(&section);
+
+        assert_eq!(wasm::custom_section(&code, "runtime_version").unwrap(), Some(&version[..]));
+        assert_eq!(wasm::custom_section(&code, "runtime_apis").unwrap(), None);
+        let runtime_version = wasm::runtime_version(&code).unwrap();
+        assert_eq!(runtime_version.spec_name, "dot-runtime");
+        assert_eq!(runtime_version.spec_version, 101);
+        assert_eq!(runtime_version.transaction_version, 3);
+        // Runtime API ids are blake2_64 hashes of the trait name
+        assert_eq!(hex::encode(hashing::blake2_64(b"Core")), "df6acb689907609b");
+        assert!(wasm::custom_section(b"not wasm", "runtime_version").is_err());
+        assert!(runtime_version.apis.is_empty());
+
+        // Runtime APIs are embedded in their own section
+        let mut apis = hashing::blake2_64(b"Core").to_vec();
+        apis.extend(5u32.to_le_bytes());
+        apis.extend(hashing::blake2_64(b"Metadata"));
+        apis.extend(2u32.to_le_bytes());
+        let mut section = vec!["runtime_apis".len() as u8];
+        section.extend_from_slice(b"runtime_apis");
+        section.extend_from_slice(&apis);
+        code.extend([0, section.len() as u8]);
+        code.extend(&section);
+
+        let runtime_version = wasm::runtime_version(&code).unwrap();
+        assert_eq!(runtime_version.spec_version, 101);
+        assert_eq!(runtime_version.apis, [(hashing::blake2_64(b"Core"), 5), (hashing::blake2_64(b"Metadata"), 2)]);
+        assert!(wasm::decode_runtime_apis(&apis[..11]).is_err());
+    }
+
+    // TRY-RUNTIME TESTS
+    #[test]
+    fn test_try_runtime_summary() {
+        let lines: Vec<String> = [
+            "2025-01-10 12:00:00 INFO try-runtime::cli: 🔬 Running TryRuntime_on_runtime_upgrade with checks: All",
+            "2025-01-10 12:00:01 WARN runtime::executive: ⚠️ Balances declares internal migrations (which *might* execute). On-chain `StorageVersion(1)` vs in-code storage version `StorageVersion(2)`",
+            "2025-01-10 12:00:02 INFO try-runtime::cli: PoV size (zstd-compressed compact proof): 12.3 KB. For parachains, it's your responsibility to verify that a PoV of this size fits within any relaychain constraints.",
+            "2025-01-10 12:00:02 INFO try-runtime::cli: Consumed ref_time: 0.0123s (0.62% of max block)",
+            "2025-01-10 12:00:02 INFO try-runtime::cli: ✅ TryRuntime_on_runtime_upgrade executed without errors.",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+        let summary = try_runtime::summarize(&lines);
+        assert!(summary.errors.is_empty());
+        assert_eq!(summary.measurements.len(), 2);
+        assert!(summary.measurements[0].starts_with("PoV size"));
+        assert_eq!(summary.warnings.len(), 1);
+
+        let failed = try_runtime::summarize(&["Error: Input(\"Migrations are not idempotent\")".to_string()]);
+        assert_eq!(failed.errors.len(), 1);
+    }
+
+    // BENCH TESTS
+    #[test]
+    fn test_parse_weights() {
+        let source = r#"
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: `Template::Something` (r:0 w:1)
+	fn do_something() -> Weight {
+		// Minimum execution time: 9_000_000 picoseconds.
+		Weight::from_parts(9_000_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// The range of component `s` is `[0, 100]`.
+	fn cause_error(s: u32, ) -> Weight {
+		Weight::from_parts(6_000_000, 1489)
+			// Standard Error: 1_000
+			.saturating_add(Weight::from_parts(2_000, 0).saturating_mul(s.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(s.into())))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+}
+
+impl WeightInfo for () {
+	fn do_something() -> Weight {
+		Weight::from_parts(1, 1)
+	}
+}
+"#;
+        let weights = bench::parse_weights(source);
+        assert_eq!(weights.len(), 2);
+        assert_eq!(
+            weights[1],
+            bench::ExtrinsicWeight { name: "cause_error".to_string(), ref_time: 6_000_000, proof_size: 1489, reads: 1, writes: 1 }
+        );
+        assert_eq!((weights[0].ref_time, weights[0].reads, weights[0].writes), (9_000_000, 0, 1));
+    }
+
+    #[test]
+    fn test_find_pallet_crate() {
+        let project = std::env::temp_dir().join("dot-test-bench-project");
+        let pallet_dir = project.join("pallets/template");
+        fs::create_dir_all(&pallet_dir).unwrap();
+        fs::write(pallet_dir.join("Cargo.toml"), "[package]\nname = \"pallet-template\"\n").unwrap();
+
+        assert_eq!(bench::find_pallet_crate(&project, "pallet_template"), Some(pallet_dir));
+        assert_eq!(bench::find_pallet_crate(&project, "pallet_balances"), None);
+        fs::remove_dir_all(&project).unwrap();
+    }
+
+    // FORK TESTS
+    #[test]
+    fn test_fork_chain_spec() {
+        let metadata = metadata::decode(&test_metadata_bytes()).unwrap();
+        let para_id_key = crate::rpc::hex_encode(&query::storage_prefix("ParachainInfo", "ParachainId"));
+        let relay_number_key = crate::rpc::hex_encode(&query::storage_prefix("ParachainSystem", "LastRelayChainBlockNumber"));
+        let snapshot = json!({
+            "version": 1,
+            "chain": "Asset Hub",
+            "properties": { "tokenSymbol": "DOT" },
+            "top": {
+                "0x3a636f6465": "0x00",
+                para_id_key.clone(): "0xe8030000",
+                relay_number_key.clone(): "0x0a000000",
+            },
+        });
+
+        let chain_spec = fork::build_chain_spec(&snapshot, &metadata).unwrap();
+        assert_eq!(chain_spec["name"], "Asset Hub (fork)");
+        assert_eq!(chain_spec["para_id"], 1000);
+        assert_eq!(chain_spec["properties"]["tokenSymbol"], "DOT");
+        let top = &chain_spec["genesis"]["raw"]["top"];
+        assert_eq!(top["0x3a636f6465"], "0x00");
+        assert_eq!(top[&para_id_key], "0xe8030000");
+        assert!(top.get(&relay_number_key).is_none(), "Relay bound storage should be dropped");
+    }
+
+    // Stand-ins for the runtime types the dev overrides are encoded against
+    struct AccountId32;
+    struct SessionKeys;
+    struct AccountInfo;
+    struct AccountData;
+
+    impl TypeInfo for AccountId32 {
+        type Identity = Self;
+        fn type_info() -> Type {
+            Type::builder().path(TypePath::new("AccountId32", "sp_core::crypto")).composite(Fields::unnamed().field(|f| f.ty::<[u8; 32]>()))
+        }
+    }
+
+    impl TypeInfo for SessionKeys {
+        type Identity = Self;
+        fn type_info() -> Type {
+            Type::builder().path(TypePath::new("SessionKeys", "runtime")).composite(
+                Fields::named()
+                    .field(|f| f.ty::<[u8; 32]>().name("aura"))
+                    .field(|f| f.ty::<[u8; 32]>().name("grandpa")),
+            )
+        }
+    }
+
+    impl TypeInfo for AccountInfo {
+        type Identity = Self;
+        fn type_info() -> Type {
+            Type::builder().path(TypePath::new("AccountInfo", "frame_system")).composite(
+                Fields::named()
+                    .field(|f| f.ty::<u32>().name("nonce"))
+                    .field(|f| f.ty::<u32>().name("consumers"))
+                    .field(|f| f.ty::<u32>().name("providers"))
+                    .field(|f| f.ty::<u32>().name("sufficients"))
+                    .field(|f| f.ty::<AccountData>().name("data")),
+            )
+        }
+    }
+
+    impl TypeInfo for AccountData {
+        type Identity = Self;
+        fn type_info() -> Type {
+            Type::builder().path(TypePath::new("AccountData", "pallet_balances")).composite(
+                Fields::named()
+                    .field(|f| f.ty::<u128>().name("free"))
+                    .field(|f| f.ty::<u128>().name("reserved"))
+                    .field(|f| f.ty::<u128>().name("frozen"))
+                    .field(|f| f.ty::<u128>().name("flags")),
+            )
+        }
+    }
+
+    /// Metadata of a solochain-like runtime with the pallets `dot fork` overrides.
+    fn fork_metadata_bytes() -> Vec<u8> {
+        let entry = |name, ty| StorageEntryMetadata { name, modifier: StorageEntryModifier::Default, ty, default: vec![0], docs: vec![] };
+        let map = |name, hasher, key, value, default| StorageEntryMetadata {
+            name,
+            modifier: StorageEntryModifier::Default,
+            ty: StorageEntryType::Map { hashers: vec![hasher], key, value },
+            default,
+            docs: vec![],
+        };
+        let pallet = |name, index, entries| PalletMetadata {
+            name,
+            storage: Some(PalletStorageMetadata { prefix: name, entries }),
+            calls: None,
+            event: None,
+            constants: vec![],
+            error: None,
+            index,
+        };
+        let pallets = vec![
+            pallet("System", 0, vec![map("Account", StorageHasher::Blake2_128Concat, meta_type::<AccountId32>(), meta_type::<AccountInfo>(), vec![0; 80])]),
+            pallet("Aura", 1, vec![entry("Authorities", StorageEntryType::Plain(meta_type::<Vec<[u8; 32]>>()))]),
+            pallet("Session", 2, vec![
+                entry("Validators", StorageEntryType::Plain(meta_type::<Vec<AccountId32>>())),
+                entry("QueuedKeys", StorageEntryType::Plain(meta_type::<Vec<(AccountId32, SessionKeys)>>())),
+                map("NextKeys", StorageHasher::Twox64Concat, meta_type::<AccountId32>(), meta_type::<SessionKeys>(), vec![0]),
+            ]),
+            pallet("Sudo", 3, vec![entry("Key", StorageEntryType::Plain(meta_type::<AccountId32>()))]),
+        ];
+        let extrinsic = ExtrinsicMetadata { ty: meta_type::<()>(), version: 4, signed_extensions: vec![] };
+        RuntimeMetadataPrefixed::from(RuntimeMetadataV14::new(pallets, extrinsic, meta_type::<()>())).encode()
+    }
+
+    #[test]
+    fn test_fork_dev_overrides() {
+        let metadata = metadata::decode(&fork_metadata_bytes()).unwrap();
+        let alice = Pair::from_uri("//Alice").unwrap();
+        let alice_account = alice.account_id();
+        let aura = alice.public();
+        let grandpa = Pair::from_uri_with_scheme("//Alice", Scheme::Ed25519).unwrap().public();
+        let key = |pallet, item, hashed: &[u8]| {
+            let mut key = query::storage_prefix(pallet, item);
+            key.extend_from_slice(hashed);
+            crate::rpc::hex_encode(&key)
+        };
+        let account_key = key("System", "Account", &[&hashing::blake2_128(&alice_account)[..], &alice_account].concat());
+
+        // Alice already has an account with a nonce but no providers
+        let mut alice_info = (5u32, 0u32, 0u32, 0u32).encode();
+        alice_info.extend(vec![0u8; 64]);
+        let snapshot = json!({
+            "chain": "Solochain",
+            "top": { "0x3a636f6465": "0x00", account_key.clone(): crate::rpc::hex_encode(&alice_info) },
+        });
+
+        let chain_spec = fork::build_chain_spec(&snapshot, &metadata).unwrap();
+        let top = &chain_spec["genesis"]["raw"]["top"];
+        assert_eq!(top[key("Sudo", "Key", &[])], crate::rpc::hex_encode(&alice_account));
+        assert_eq!(top[key("Aura", "Authorities", &[])], crate::rpc::hex_encode(&[&[4u8][..], &aura].concat()));
+        assert_eq!(top[key("Session", "Validators", &[])], crate::rpc::hex_encode(&[&[4u8][..], &alice_account].concat()));
+        let session_keys = [&aura[..], &grandpa].concat();
+        assert_eq!(
+            top[key("Session", "QueuedKeys", &[])],
+            crate::rpc::hex_encode(&[&[4u8][..], &alice_account, &session_keys].concat())
+        );
+        let next_keys_key = key("Session", "NextKeys", &[&hashing::twox_64(&alice_account)[..], &alice_account].concat());
+        assert_eq!(top[next_keys_key], crate::rpc::hex_encode(&session_keys));
+
+        let mut funded = (5u32, 0u32, 1u32, 0u32, 1_000_000_000_000_000_000_000u128).encode();
+        funded.extend(vec![0u8; 48]);
+        assert_eq!(top[account_key], crate::rpc::hex_encode(&funded));
+    }
+
+    #[test]
+    fn test_fork_child_storage() {
+        let metadata = metadata::decode(&test_metadata_bytes()).unwrap();
+        let child_key = crate::rpc::hex_encode(b":child_storage:default:contract");
+        let snapshot = json!({
+            "top": { "0x3a636f6465": "0x00", child_key.clone(): "0x1234" },
+            "childrenDefault": { crate::rpc::hex_encode(b"contract"): { "0x01": "0x02" } },
+        });
+        let chain_spec = fork::build_chain_spec(&snapshot, &metadata).unwrap();
+        let raw = &chain_spec["genesis"]["raw"];
+        assert!(raw["top"].get(&child_key).is_none(), "Child trie roots are computed at genesis");
+        assert_eq!(raw["childrenDefault"][crate::rpc::hex_encode(b"contract")]["0x01"], "0x02");
+
+        // Snapshots without the child trie contents cannot be served
+        let snapshot = json!({ "top": { "0x3a636f6465": "0x00", child_key: "0x1234" } });
+        assert!(fork::build_chain_spec(&snapshot, &metadata).is_err());
+    }
+
+    // SEAL TESTS
+    #[test]
+    fn test_seal_modes() {
+        assert_eq!(SealMode::parse("instant"), Ok(SealMode::Instant));
+        assert_eq!(SealMode::parse("interval=500"), Ok(SealMode::Interval(500)));
+        assert!(SealMode::parse("interval=0").is_err());
+        assert!(SealMode::parse("aura").is_err());
+
+        assert_eq!(SealMode::Interval(500).node_args(), vec!["--dev-block-time", "500"]);
+        assert_eq!(SealMode::Manual.node_args(), SealMode::Instant.node_args());
+        assert_eq!(seal::local_rpc_url(&["--rpc-port", "9988"]), "ws://127.0.0.1:9988");
+        assert_eq!(seal::local_rpc_url(&["--dev", "--rpc-port=9977"]), "ws://127.0.0.1:9977");
+        assert_eq!(seal::local_rpc_url(&[]), "ws://127.0.0.1:9944");
+    }
+
+    // TEMPLATE TESTS
+    #[test]
+    fn test_parse_templates() {
+        let config = r#"
+[templates.our-chain]
+url = "https://git.example.com/our/chain-template.git"
+ref = "v1.2.0"
+run = "./target/release/our-node --dev"
+description = "Our parachain template"
+"#;
+        let templates = template::parse_templates(config).unwrap();
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].name, "our-chain");
+        assert_eq!(templates[0].git_ref.as_deref(), Some("v1.2.0"));
+        assert_eq!(templates[0].build, "cargo build --release");
+        assert_eq!(templates[0].run, "./target/release/our-node --dev");
+
+        assert!(template::parse_templates("[templates.broken]\nref = \"main\"\n").is_err());
+        assert!(template::parse_templates("[templates.\"../../x\"]\nurl = \"https://example.com/x.git\"\n").is_err());
+        assert!(template::parse_templates("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rename_template_identifiers() {
+        let manifest = r#"[package]
+name = "parachain-template-runtime"
+repository = "https://github.com/paritytech/polkadot-sdk-parachain-template.git"
+
+[dependencies]
+pallet-parachain-template = { path = "../pallets/template" }
+"#;
+        let renamed = scaffold::rename_identifiers(manifest, "parachain", "my-chain");
+        assert!(renamed.contains("name = \"my-chain-runtime\""));
+        assert!(renamed.contains("pallet-my-chain = {"));
+        assert!(renamed.contains("polkadot-sdk-parachain-template.git"));
+        assert_eq!(
+            scaffold::rename_identifiers("use parachain_template_runtime::Block;", "parachain", "my-chain"),
+            "use my_chain_runtime::Block;"
+        );
+
+        assert!(scaffold::validate_name("my-chain").is_ok());
+        assert!(scaffold::validate_name("My Chain").is_err());
+        assert!(scaffold::validate_name("1chain").is_err());
+    }
+
+    #[test]
+    fn test_template_lock() {
+        let lock = template::Lock {
+            name: "parachain".to_string(),
+            url: "https://github.com/paritytech/polkadot-sdk-parachain-template.git".to_string(),
+            git_ref: Some("v0.0.4".to_string()),
+            branch: "master".to_string(),
+            commit: "4ad5c1e8a7e3a0e5c3f5d1c7b1e9a2f0d3c4b5a6".to_string(),
+            project: Some("my-chain".to_string()),
+        };
+        assert_eq!(template::Lock::parse(&lock.to_toml().unwrap()).unwrap(), lock);
+        assert!(template::Lock::parse("[template]\nname = \"minimal\"\n").is_err());
+    }
+
+    #[test]
+    fn test_template_behind_upstream() {
+        let root = std::env::temp_dir().join("dot-test-template-behind");
+        let _ = fs::remove_dir_all(&root);
+        let upstream = root.join("upstream");
+        fs::create_dir_all(&upstream).unwrap();
+        let git = |dir: &Path, args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=dot", "-c", "user.email=dot@example.com"])
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&upstream, &["init", "--quiet", "--initial-branch=main"]);
+        git(&upstream, &["commit", "--quiet", "--allow-empty", "-m", "first"]);
+        git(&upstream, &["tag", "v1"]);
+
+        let template = template::Template {
+            name: "demo".to_string(),
+            url: upstream.display().to_string(),
+            git_ref: Some("v1".to_string()),
+            pin_release: false,
+            build: String::new(),
+            run: String::new(),
+            description: String::new(),
+        };
+        let checkout = root.join("checkout");
+        let lock = template::clone(&template, &checkout).unwrap();
+        assert_eq!((lock.branch.as_str(), lock.git_ref.as_deref()), ("main", Some("v1")));
+
+        // Without a ref, release pinned templates are checked out at their latest tag
+        git(&upstream, &["commit", "--quiet", "--allow-empty", "-m", "unreleased"]);
+        let pinned = template::Template { git_ref: None, pin_release: true, ..template.clone() };
+        let pinned_lock = template::clone(&pinned, &root.join("pinned")).unwrap();
+        assert_eq!((pinned_lock.git_ref.as_deref(), pinned_lock.commit.as_str()), (Some("v1"), lock.commit.as_str()));
+        let unpinned = template::Template { git_ref: None, ..template.clone() };
+        assert_eq!(template::clone(&unpinned, &root.join("unpinned")).unwrap().git_ref, None);
+
+        git(&upstream, &["commit", "--quiet", "--allow-empty", "-m", "second"]);
+        git(&upstream, &["commit", "--quiet", "--allow-empty", "-m", "third"]);
+        let (_, behind) = template::behind(&checkout, &lock).unwrap();
+        assert_eq!(behind, 3);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_template_reclone() {
+        let root = std::env::temp_dir().join("dot-test-template-reclone");
+        let _ = fs::remove_dir_all(&root);
+        let upstream = root.join("upstream");
+        fs::create_dir_all(&upstream).unwrap();
+        let git = |dir: &Path, args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=dot", "-c", "user.email=dot@example.com"])
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&upstream, &["init", "--quiet", "--initial-branch=main"]);
+        git(&upstream, &["commit", "--quiet", "--allow-empty", "-m", "first"]);
+        git(&upstream, &["tag", "v1"]);
+        git(&upstream, &["commit", "--quiet", "--allow-empty", "-m", "second"]);
+        git(&upstream, &["tag", "v2"]);
+
+        let template = template::Template {
+            name: "demo".to_string(),
+            url: upstream.display().to_string(),
+            git_ref: Some("v1".to_string()),
+            pin_release: false,
+            build: String::new(),
+            run: String::new(),
+            description: String::new(),
+        };
+        let checkout = root.join("demo-template");
+        let lock = template::clone(&template, &checkout).unwrap();
+        lock.write(&checkout).unwrap();
+
+        // Untracked files and local commits would be lost, so the checkout is kept as is
+        fs::write(checkout.join("notes.txt"), "keep me").unwrap();
+        assert!(template::update(&checkout, Some("v2"), true).is_err());
+        assert!(checkout.join("notes.txt").exists());
+        fs::remove_file(checkout.join("notes.txt")).unwrap();
+        git(&checkout, &["commit", "--quiet", "--allow-empty", "-m", "local"]);
+        assert!(template::update(&checkout, Some("v2"), true).is_err());
+        git(&checkout, &["reset", "--quiet", "--hard", "v1"]);
+
+        template::update(&checkout, Some("v2"), true).unwrap();
+        let updated = template::Lock::read(&checkout).unwrap();
+        assert_eq!(updated.git_ref.as_deref(), Some("v2"));
+        assert_ne!(updated.commit, lock.commit);
+        let leftovers: Vec<_> = fs::read_dir(&root).unwrap().flatten().map(|entry| entry.file_name()).collect();
+        assert_eq!(leftovers.len(), 2, "Only upstream and the checkout should remain: {:?}", leftovers);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_template_update_project() {
+        let root = std::env::temp_dir().join("dot-test-template-update-project");
+        let _ = fs::remove_dir_all(&root);
+        let upstream = root.join("upstream");
+        fs::create_dir_all(&upstream).unwrap();
+        let git = |dir: &Path, args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=dot", "-c", "user.email=dot@example.com"])
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&upstream, &["init", "--quiet", "--initial-branch=main"]);
+        fs::write(upstream.join("Cargo.toml"), "[package]\nname = \"demo-template-node\"\n").unwrap();
+        fs::write(upstream.join("README.md"), "Demo\n").unwrap();
+        git(&upstream, &["add", "-A"]);
+        git(&upstream, &["commit", "--quiet", "-m", "first"]);
+        git(&upstream, &["tag", "v1"]);
+        fs::write(upstream.join("Cargo.toml"), "[package]\nname = \"demo-template-node\"\nversion = \"2.0.0\"\n").unwrap();
+        fs::write(upstream.join("lib.rs"), "use demo_template_runtime;\n").unwrap();
+        git(&upstream, &["add", "-A"]);
+        git(&upstream, &["commit", "--quiet", "-m", "second"]);
+        git(&upstream, &["tag", "v2"]);
+
+        let template = template::Template {
+            name: "demo".to_string(),
+            url: upstream.display().to_string(),
+            git_ref: Some("v1".to_string()),
+            pin_release: false,
+            build: String::new(),
+            run: String::new(),
+            description: String::new(),
+        };
+        scaffold::new_project("my-chain", &template, &root).unwrap();
+        let project = root.join("my-chain");
+        fs::write(project.join("README.md"), "My chain\n").unwrap();
+        git(&project, &["add", "-A"]);
+        git(&project, &["commit", "--quiet", "-m", "local"]);
+
+        // The project history does not contain the template, so re-cloning is refused and the changes are applied
+        assert!(template::update(&project, Some("v2"), true).is_err());
+        template::update(&project, Some("v2"), false).unwrap();
+        assert_eq!(
+            fs::read_to_string(project.join("Cargo.toml")).unwrap(),
+            "[package]\nname = \"my-chain-node\"\nversion = \"2.0.0\"\n"
+        );
+        assert_eq!(fs::read_to_string(project.join("lib.rs")).unwrap(), "use my_chain_runtime;\n");
+        assert_eq!(fs::read_to_string(project.join("README.md")).unwrap(), "My chain\n");
+        assert_eq!(template::Lock::read(&project).unwrap().git_ref.as_deref(), Some("v2"));
+        assert!(!project.join(".git/dot-template").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    // BUILD TESTS
+    #[test]
+    fn test_node_binary() {
+        let metadata = json!({
+            "target_directory": "/project/target",
+            "packages": [
+                { "name": "parachain-template-runtime", "targets": [{ "name": "parachain_template_runtime", "kind": ["lib"] }] },
+                { "name": "xtask", "targets": [{ "name": "xtask", "kind": ["bin"] }] },
+                { "name": "parachain-template-node", "targets": [{ "name": "parachain-template-node", "kind": ["bin"] }] },
+            ],
+        });
+        assert_eq!(
+            build::node_binary(&metadata, "dev").unwrap(),
+            Path::new("/project/target/debug/parachain-template-node")
+        );
+        assert_eq!(
+            build::node_binary(&metadata, "production").unwrap(),
+            Path::new("/project/target/production/parachain-template-node")
+        );
+        assert!(build::node_binary(&json!({ "target_directory": "/t", "packages": [] }), "release").is_err());
+    }
+
+    #[test]
+    fn test_build_progress() {
+        let mut progress = build::BuildProgress::new(4);
+        let artifact = |package: &str, target: &str| {
+            json!({ "reason": "compiler-artifact", "package_id": package, "target": { "name": target } })
+        };
+        assert_eq!(progress.update(&artifact("serde 1.0.0", "build-script-build")), None);
+        progress.update(&artifact("serde 1.0.0", "serde"));
+        progress.update(&artifact("node 0.1.0", "node"));
+        assert_eq!(progress.compiled(), 2);
+        assert!(progress.status_line("node").ends_with("] 2/4 node"));
+
+        let warning = json!({ "reason": "compiler-message", "message": { "level": "warning", "rendered": "warning: unused variable" } });
+        assert_eq!(progress.update(&warning).as_deref(), Some("warning: unused variable"));
+        let note = json!({ "reason": "compiler-message", "message": { "level": "note", "rendered": "note: ..." } });
+        assert_eq!(progress.update(&note), None);
+    }
+
+    #[test]
+    fn test_parse_unit_durations() {
+        let html = r#"<script>
+const UNIT_DATA = [
+  { "i": 0, "name": "serde", "version": "1.0.0", "mode": "todo", "target": "", "start": 0.1, "duration": 2.5 },
+  { "i": 1, "name": "node", "version": "0.1.0", "mode": "todo", "target": " bin \"node\"", "start": 3.0, "duration": 61.0 }
+];
+const CONCURRENCY_DATA = [];
+</script>"#;
+        let units = build::parse_unit_durations(html);
+        assert_eq!(units[0], ("node v0.1.0 bin \"node\"".to_string(), 61.0));
+        assert_eq!(units[1].0, "serde v1.0.0");
+        assert!(build::parse_unit_durations("<html></html>").is_empty());
+        assert_eq!(build::format_duration(61.0), "1m 1s");
+    }
+
+    // PALLET TESTS
+    #[test]
+    fn test_pallet_names() {
+        let names = PalletNames::new("pallet-voting-booth").unwrap();
+        assert_eq!(names, PalletNames::new("voting_booth").unwrap());
+        assert_eq!(
+            (names.crate_name.as_str(), names.module.as_str(), names.type_name.as_str()),
+            ("pallet-voting-booth", "pallet_voting_booth", "VotingBooth")
+        );
+        assert!(PalletNames::new("Voting Booth").is_err());
+    }
+
+    #[test]
+    fn test_register_pallet() {
+        let names = PalletNames::new("voting").unwrap();
+        let runtime = "mod runtime {
+\t#[runtime::pallet_index(0)]
+\tpub type System = frame_system::Pallet<Runtime>;
+\t#[runtime::pallet_index(7)]
+\tpub type Balances = pallet_balances::Pallet<Runtime>;
+}
+";
+        let registered = pallet::register_pallet(runtime, &names).unwrap().unwrap();
+        assert!(registered.contains("\t#[runtime::pallet_index(8)]\n\tpub type Voting = pallet_voting::Pallet<Runtime>;\n}"));
+        assert!(pallet::register_pallet(&registered, &names).is_err());
+
+        let construct_runtime = "construct_runtime!(
+    pub enum Runtime {
+        System: frame_system = 0,
+        Balances: pallet_balances::{Pallet, Call, Storage, Event<T>} = 10,
+    }
+);
+";
+        let registered = pallet::register_pallet(construct_runtime, &names).unwrap().unwrap();
+        assert!(registered.contains("= 10,\n        Voting: pallet_voting = 11,\n    }"));
+        assert_eq!(pallet::register_pallet("fn main() {}", &names).unwrap(), None);
+    }
+
+    #[test]
+    fn test_mirror_pallet_dependency() {
+        let manifest = r#"[dependencies]
+pallet-parachain-template = { path = "../pallets/template", default-features = false }
+
+[features]
+std = [
+	"codec/std",
+	"pallet-parachain-template/std",
+	"sp-io/std",
+]
+try-runtime = ["pallet-parachain-template/try-runtime"]
+"#;
+        let source = PalletNames {
+            crate_name: "pallet-parachain-template".to_string(),
+            module: "pallet_parachain_template".to_string(),
+            type_name: String::new(),
+        };
+        let names = PalletNames::new("voting").unwrap();
+        let mirrored = pallet::mirror_dependency(manifest, &source, &names, "../pallets/voting").unwrap();
+        assert!(mirrored.contains("pallet-voting = { path = \"../pallets/voting\", default-features = false }"));
+        assert!(mirrored.contains("\t\"pallet-parachain-template/std\",\n\t\"pallet-voting/std\",\n\t\"sp-io/std\","));
+        assert!(mirrored.contains("try-runtime = [\"pallet-parachain-template/try-runtime\", \"pallet-voting/try-runtime\"]"));
+    }
+
+    #[test]
+    fn test_add_frame_pallet() {
+        let project = std::env::temp_dir().join("dot-test-add-pallet");
+        fs::create_dir_all(project.join("runtime/src")).unwrap();
+        fs::write(project.join("Cargo.toml"), "[workspace]\nmembers = [\"runtime\"]\n").unwrap();
+        fs::write(
+            project.join("runtime/Cargo.toml"),
+            "[package]\nname = \"my-runtime\"\n\n[dependencies]\npolkadot-sdk = { version = \"2503.0.1\", features = [\"pallet-balances\", \"runtime\"] }\n",
+        )
+        .unwrap();
+        fs::write(
+            project.join("runtime/src/lib.rs"),
+            "mod runtime {\n\t#[runtime::pallet_index(0)]\n\tpub type System = frame_system;\n}\n\nimpl frame_system::Config for Runtime {\n\ttype Nonce = u32;\n}\n\nimpl pallet_balances::Config for Runtime {}\n\nfn helper() {}\n",
+        )
+        .unwrap();
+
+        let changes = pallet::add_pallet(&project, "utility", false).unwrap();
+        // Pallets without a known configuration would leave the runtime uncompilable
+        assert!(pallet::add_pallet(&project, "nomination-pools", false).is_err());
+        let incomplete = pallet::add_pallet(&project, "nomination-pools", true).unwrap();
+        fs::remove_dir_all(&project).unwrap();
+        assert!(incomplete[1].after.contains("// TODO: the other Config items of pallet-nomination-pools"));
+        assert_eq!(changes.len(), 2);
+        assert!(changes[0].after.contains("features = [\"pallet-balances\", \"pallet-utility\", \"runtime\"]"));
+        let runtime = &changes[1].after;
+        assert!(runtime.contains("\tpub type Utility = pallet_utility;\n}"));
+        assert!(runtime.contains("impl pallet_balances::Config for Runtime {}\n\nimpl pallet_utility::Config for Runtime {\n"));
+        assert!(runtime.ends_with("}\n\nfn helper() {}\n"));
+    }
+
+    #[test]
+    fn test_pick_pallet_version() {
+        let index = [
+            r#"{"name":"pallet-proxy","vers":"38.0.0","deps":[{"name":"frame-support","req":"^38.0.0"}],"yanked":false}"#,
+            r#"{"name":"pallet-proxy","vers":"38.1.0","deps":[{"name":"frame-support","req":"^38.0.1"}],"yanked":false}"#,
+            r#"{"name":"pallet-proxy","vers":"38.2.0","deps":[{"name":"frame-support","req":"^38.0.2"}],"yanked":true}"#,
+            r#"{"name":"pallet-proxy","vers":"40.1.0","deps":[{"name":"frame","package":"polkadot-sdk-frame","req":"^0.9.1"}],"yanked":false}"#,
+        ]
+        .join("\n");
+        assert_eq!(pallet::pick_version(&index, "frame-support", &["38".to_string()]), Some("38.1.0".to_string()));
+        assert_eq!(pallet::pick_version(&index, "frame-support", &["40".to_string()]), None);
+        assert_eq!(pallet::pick_version(&index, "polkadot-sdk-frame", &["0.9".to_string()]), Some("40.1.0".to_string()));
+    }
+
+    // CONFIG TESTS
+    #[test]
+    fn test_project_config() {
+        let content = "[node]\nrpc-port = 9955\n\n[paths]\nbinaries = \"./bin\"\n\n[networks]\nwestend = \"wss://westend-rpc.polkadot.io\"\n";
+        let mut config = Config::new(Path::new("/project").to_path_buf(), None);
+        config.add_layer(Path::new("/project/dot.toml"), content).unwrap();
+        assert_eq!(config.integer("node.rpc-port"), 9955);
+        assert_eq!(config.string("sdk.release"), "polkadot-stable2412");
+        assert_eq!(config.path("paths.binaries"), Path::new("/project/bin"));
+        assert_eq!(config.rpc_url("local").unwrap(), "ws://127.0.0.1:9955");
+        assert_eq!(config.rpc_url("westend").unwrap(), "wss://westend-rpc.polkadot.io");
+        assert_eq!(config.rpc_url("ws://127.0.0.1:1234").unwrap(), "ws://127.0.0.1:1234");
+        assert!(config.rpc_url("kusama").is_err());
+        assert!(config.get("node.port").is_err());
+        assert!(config.add_layer(Path::new("dot.toml"), "[node]\nrpc-port = \"9955\"\n").is_err());
+        assert!(config.add_layer(Path::new("dot.toml"), "[output]\nformat = \"yaml\"\n").is_err());
+
+        std::env::set_var(config::env_name("node.p2p-port"), "30444");
+        assert_eq!(config.integer("node.p2p-port"), 30444);
+        std::env::remove_var(config::env_name("node.p2p-port"));
+        assert_eq!(config.integer("node.p2p-port"), 30333);
+    }
+
+    #[test]
+    fn test_set_config_value() {
+        let content = "# Local settings\n[node]\nrpc-port = 9944 # default\n";
+        let updated = config::set_value(content, "node.rpc-port", "9955", None).unwrap();
+        assert_eq!(updated, "# Local settings\n[node]\nrpc-port = 9955 # default\n");
+        let updated = config::set_value(&updated, "node.flags", "[\"--dev\", \"--tmp\"]", None).unwrap();
+        assert!(updated.contains("flags = [\"--dev\", \"--tmp\"]"));
+        let updated = config::set_value(&updated, "networks.westend", "wss://westend-rpc.polkadot.io", None).unwrap();
+        assert!(updated.ends_with("[networks]\nwestend = \"wss://westend-rpc.polkadot.io\"\n"));
+        assert!(config::set_value(&updated, "node.rpc-port", "local", None).is_err());
+        assert!(config::set_value(&updated, "node.unknown", "1", None).is_err());
+        let updated = config::set_value("", "node.rpc-port", "9955", Some("ci")).unwrap();
+        assert_eq!(updated, "[profile.ci.node]\nrpc-port = 9955\n");
+    }
+
+    #[test]
+    fn test_config_profiles() {
+        let global = "[keys]\nsigner = \"//Bob\"\n\n[templates.my-chain]\nurl = \"https://example.com/my-chain.git\"\n\n[profile.ci.node]\nflags = [\"--tmp\"]\nrpc-port = 9966\n";
+        let project = "[node]\nrpc-port = 9955\n\n[profile.local.node]\nrpc-port = 9977\n";
+        let mut config = Config::new(Path::new(".").to_path_buf(), Some("ci".to_string()));
+        config.add_user_layer(Path::new("/home/dev/.config/dot/config.toml"), global).unwrap();
+        config.add_layer(Path::new("dot.toml"), project).unwrap();
+        assert_eq!(config.profiles, ["ci", "local"]);
+
+        // Templates are only registered from the user config, so they are not accepted elsewhere
+        let templates = "[templates.my-chain]\nurl = \"https://example.com/my-chain.git\"\n";
+        assert!(Config::new(Path::new(".").to_path_buf(), None).add_layer(Path::new("dot.toml"), templates).is_err());
+        let profile_templates = "[profile.ci.templates.my-chain]\nurl = \"https://example.com/my-chain.git\"\n";
+        assert!(Config::new(Path::new(".").to_path_buf(), None).add_user_layer(Path::new("config.toml"), profile_templates).is_err());
+
+        let origin = |key: &str| config.lookup(key).unwrap().1.to_string();
+        assert_eq!(config.string("keys.signer"), "//Bob");
+        assert_eq!(origin("keys.signer"), "/home/dev/.config/dot/config.toml");
+        assert_eq!(config.strings("node.flags"), ["--tmp"]);
+        assert_eq!(origin("node.flags"), "/home/dev/.config/dot/config.toml [profile.ci]");
+        // The project config is merged over the profiles of the user config
+        assert_eq!(config.integer("node.rpc-port"), 9955);
+        assert_eq!(origin("node.rpc-port"), "dot.toml");
+        assert_eq!(origin("sdk.release"), "default");
+        assert_eq!(origin("networks.local"), "default");
+    }
+
+    // CLI TESTS
+    #[test]
+    fn test_cli_commands() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["dot", "install", "--template", "parachain", "--omni", "--", "--rpc-port", "9955"]).unwrap();
+        match cli.command {
+            Commands::Install { template, omni, args, .. } => {
+                assert_eq!(template.as_deref(), Some("parachain"));
+                assert!(omni);
+                assert_eq!(args, ["--rpc-port", "9955"]);
+            }
+            command => panic!("Unexpected command {:?}", command),
+        }
+        assert!(Cli::try_parse_from(["dot", "install", "parachain"]).is_err());
+        assert!(Cli::try_parse_from(["dot", "install", "--template", "unknown"]).is_err());
+        assert!(Cli::try_parse_from(["dot", "install", "--omni"]).is_err());
+
+        let cli = Cli::try_parse_from(["dot", "build", "--profile", "production"]).unwrap();
+        assert!(matches!(cli.command, Commands::Build { profile: CargoProfile::Production, .. }));
+        let cli = Cli::try_parse_from(["dot", "chain-spec", "generate", "--runtime", "people-westend"]).unwrap();
+        assert!(format!("{:?}", cli.command).contains("People"));
+        assert_eq!(Runtime::People.name(), "people_westend_runtime");
+        assert!(Cli::try_parse_from(["dot", "chain-spec", "generate", "--runtime", "people-westend", "--wasm", "a.wasm"]).is_err());
+        assert!(Cli::try_parse_from(["dot", "serve", "--watch"]).is_err());
+    }
+
+    #[test]
+    fn test_global_flags() {
+        let cli = Cli::try_parse_from(["dot", "metadata", "--wasm", "runtime.wasm", "--output", "json", "-q"]).unwrap();
+        assert_eq!(cli.output, Some(OutputFormat::Json));
+        assert!(Cli::try_parse_from(["dot", "--verbose", "--quiet", "config", "show"]).is_err());
+
+        let options = cli.config_options();
+        assert!(!options.allow_new);
+        let mut config = Config::new(Path::new(".").to_path_buf(), None);
+        config.flags = options.flags;
+        config.add_layer(Path::new("dot.toml"), "[output]\nformat = \"text\"\nverbosity = \"verbose\"\n").unwrap();
+        assert_eq!(config.string("output.format"), "json");
+        assert_eq!(config.lookup("output.verbosity").unwrap().1.to_string(), "command line flag --quiet");
+
+        // Commands without JSON output reject --output json instead of ignoring it
+        let json_output = |args: &[&str]| Cli::try_parse_from(args).unwrap().command.has_json_output();
+        assert!(json_output(&["dot", "tx", "System", "remark", "0x00"]));
+        assert!(json_output(&["dot", "key", "list"]));
+        assert!(json_output(&["dot", "build", "--history"]));
+        assert!(json_output(&["dot", "template", "status"]));
+        assert!(!json_output(&["dot", "build"]));
+        assert!(!json_output(&["dot", "key", "insert", "--key-type", "aura", "--suri", "//Alice"]));
+        assert!(!json_output(&["dot", "seal"]));
+
+        let cli = Cli::try_parse_from(["dot", "--config-profile", "ci", "--config", "ci/dot.toml", "config", "set", "node.rpc-port", "9955"]).unwrap();
+        let options = cli.config_options();
+        assert!(options.allow_new);
+        assert_eq!(options.profile.as_deref(), Some("ci"));
+        assert_eq!(options.file.as_deref(), Some(Path::new("ci/dot.toml")));
+
+        // The config profile is global and does not collide with the cargo profile of build and run
+        let cli = Cli::try_parse_from(["dot", "config", "show", "--config-profile", "ci"]).unwrap();
+        assert_eq!(cli.config_options().profile.as_deref(), Some("ci"));
+        let cli = Cli::try_parse_from(["dot", "build", "--config-profile", "ci", "--profile", "production"]).unwrap();
+        assert_eq!(cli.config_options().profile.as_deref(), Some("ci"));
+        assert!(matches!(cli.command, Commands::Build { profile: CargoProfile::Production, .. }));
+    }
+}
@@ -0,0 +1,169 @@
+use std::error::Error;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use crate::config;
+use crate::install;
+
+/// Outcome of a try-runtime run, extracted from its log output.
+#[derive(Default)]
+pub struct Summary {
+    pub passed: bool,
+    /// Weight and PoV size measurements.
+    pub measurements: Vec<String>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Scrapes the state of the chain at `rpc_url` into a snapshot file.
+pub fn create_snapshot(rpc_url: &str, output: &Path) -> Result<(), Box<dyn Error>> {
+    ensure_installed()?;
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let rpc_url = config::current().rpc_url(rpc_url)?;
+    println!("Creating a snapshot of {} in {}", rpc_url, output.display());
+    let args = vec!["create-snapshot".to_string(), "--uri".to_string(), rpc_url.to_string(), output.display().to_string()];
+    let (success, lines) = run(&args)?;
+    let mut summary = summarize(&lines);
+    summary.passed = success && output.exists();
+    print_summary("create-snapshot", &summary);
+    if !summary.passed {
+        return Err("Failed to create the snapshot".into());
+    }
+    Ok(())
+}
+
+/// Runs the `on_runtime_upgrade` hooks of `wasm` and their try-runtime checks against a snapshot.
+pub fn on_runtime_upgrade(snapshot: &Path, wasm: &Path, checks: &str, blocktime: &str) -> Result<(), Box<dyn Error>> {
+    ensure_installed()?;
+    if !snapshot.exists() {
+        return Err(format!("Snapshot not found: {}, create one with `dot try-runtime create-snapshot`", snapshot.display()).into());
+    }
+    if !wasm.exists() {
+        return Err(format!("Runtime not found: {}", wasm.display()).into());
+    }
+
+    println!("Running on-runtime-upgrade of {} against {}", wasm.display(), snapshot.display());
+    let args: Vec<String> = [
+        "--runtime",
+        &wasm.display().to_string(),
+        "on-runtime-upgrade",
+        "--checks",
+        checks,
+        "--blocktime",
+        blocktime,
+        "snap",
+        "--path",
+        &snapshot.display().to_string(),
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    let (success, lines) = run(&args)?;
+    let mut summary = summarize(&lines);
+    summary.passed = success && summary.errors.is_empty();
+    print_summary("on-runtime-upgrade", &summary);
+    if !summary.passed {
+        return Err("Runtime upgrade checks failed".into());
+    }
+    Ok(())
+}
+
+fn ensure_installed() -> Result<(), Box<dyn Error>> {
+    if !install::binary_path(install::TRY_RUNTIME).exists() {
+        install::install_try_runtime()?;
+    }
+    Ok(())
+}
+
+/// Runs try-runtime, echoing its output while collecting it. try-runtime logs to stderr.
+fn run(args: &[String]) -> Result<(bool, Vec<String>), Box<dyn Error>> {
+    let mut child = Command::new(install::binary_path(install::TRY_RUNTIME))
+        .args(args)
+        .env("RUST_LOG", "info")
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run try-runtime: {}", e))?;
+
+    let mut lines = Vec::new();
+    for line in BufReader::new(child.stderr.take().unwrap()).lines() {
+        let line = line?;
+        eprintln!("{}", line);
+        lines.push(strip_ansi(&line));
+    }
+    Ok((child.wait()?.success(), lines))
+}
+
+/// Picks the measurements, warnings and errors out of try-runtime's log lines.
+pub fn summarize(lines: &[String]) -> Summary {
+    let mut summary = Summary::default();
+    for line in lines {
+        let message = log_message(line);
+        let lower = message.to_lowercase();
+        if line.contains(" ERROR ") || message.starts_with("Error") || lower.contains("panicked at") || message.contains('❌') {
+            summary.errors.push(message.to_string());
+        } else if lower.contains("pov") || lower.contains("ref_time") || lower.contains("proof_size") || lower.contains("weight") {
+            summary.measurements.push(message.to_string());
+        } else if line.contains(" WARN ") {
+            summary.warnings.push(message.to_string());
+        }
+    }
+    summary
+}
+
+/// Drops the `<timestamp> <level> <target>:` prefix of a log line.
+fn log_message(line: &str) -> &str {
+    for level in [" ERROR ", " WARN ", " INFO ", " DEBUG ", " TRACE "] {
+        if let Some(index) = line.find(level) {
+            let rest = &line[index + level.len()..];
+            return rest.split_once(": ").map(|(_, message)| message).unwrap_or(rest).trim();
+        }
+    }
+    line.trim()
+}
+
+fn strip_ansi(line: &str) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip `ESC [ ... <letter>`
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        output.push(c);
+    }
+    output
+}
+
+fn print_summary(command: &str, summary: &Summary) {
+    println!(" ");
+    println!("===========================================================================");
+    println!(" ");
+    if summary.passed {
+        println!("$ try-runtime {} passed ✓", command);
+    } else {
+        println!("$ try-runtime {} failed ✗", command);
+    }
+    for (title, lines) in [("Weight and PoV", &summary.measurements), ("Warnings", &summary.warnings), ("Errors", &summary.errors)] {
+        if lines.is_empty() {
+            continue;
+        }
+        println!(" ");
+        println!("{}:", title);
+        for line in lines {
+            println!("  {}", line);
+        }
+    }
+    println!(" ");
+    println!("===========================================================================");
+    println!(" ");
+}
@@ -0,0 +1,260 @@
+use std::error::Error;
+use parity_scale_codec::{Compact, Encode};
+use scale_info::TypeDef;
+use serde_json::{json, Value};
+use crate::events::{self, EventRecord};
+use crate::hashing;
+use crate::keys::{self, Pair};
+use crate::metadata::{self, Metadata, SignedExtension};
+use crate::output;
+use crate::rpc::{self, RpcClient};
+use crate::watch;
+
+/// Result of an extrinsic that made it into a block.
+pub struct TxOutcome {
+    pub block_hash: String,
+    pub events: Vec<EventRecord>,
+}
+
+/// Encodes, signs and submits `<pallet>.<call>(args)` and waits for it to be included.
+pub fn run(rpc_url: &str, pallet: &str, call: &str, args: &[&str], signer: &str, finalized: bool, json: bool) -> Result<(), Box<dyn Error>> {
+    let mut client = RpcClient::connect(rpc_url)?;
+    let metadata = metadata::from_rpc(&mut client)?;
+    let pair = Pair::from_uri(signer)?;
+
+    let values: Vec<Value> = args.iter().map(|arg| parse_arg(arg)).collect();
+    let call_data = encode_call(&metadata, pallet, call, &values)?;
+    let outcome = submit(&mut client, &metadata, &pair, &call_data, finalized)?;
+    if json {
+        let events: Vec<Value> = outcome.events.iter().map(events::event_json).collect();
+        println!("{}", serde_json::to_string_pretty(&json!({ "block_hash": outcome.block_hash, "events": events }))?);
+    }
+    Ok(())
+}
+
+/// CLI arguments are read as JSON when possible and as plain strings otherwise.
+pub fn parse_arg(arg: &str) -> Value {
+    serde_json::from_str(arg).unwrap_or_else(|_| Value::String(arg.to_string()))
+}
+
+/// Encodes a call as `pallet index ++ call index ++ arguments`.
+pub fn encode_call(metadata: &Metadata, pallet_name: &str, call_name: &str, args: &[Value]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let pallet = metadata.pallet(pallet_name)?;
+    let call = metadata
+        .variant(pallet.calls, call_name)
+        .ok_or_else(|| format!("Call {}.{} not found in runtime metadata", pallet.name, call_name))?;
+
+    let signature: Vec<String> = call
+        .fields
+        .iter()
+        .map(|field| format!("{}: {}", field.name.clone().unwrap_or_default(), metadata.type_name(field.ty.id)))
+        .collect();
+    if args.len() != call.fields.len() {
+        return Err(format!(
+            "{}.{} expects {} arguments: {}({})",
+            pallet.name,
+            call.name,
+            call.fields.len(),
+            call.name,
+            signature.join(", ")
+        )
+        .into());
+    }
+
+    let mut data = vec![pallet.index, call.index];
+    for (field, arg) in call.fields.iter().zip(args) {
+        metadata.encode_value(field.ty.id, arg, &mut data).map_err(|e| {
+            format!("Invalid argument {}: {}", field.name.clone().unwrap_or_default(), e)
+        })?;
+    }
+    Ok(data)
+}
+
+/// Wraps an encoded call in `Sudo.sudo`.
+pub fn sudo_call(metadata: &Metadata, call_data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let pallet = metadata.pallet("Sudo")?;
+    let sudo = metadata
+        .variant(pallet.calls, "sudo")
+        .ok_or("Call Sudo.sudo not found in runtime metadata")?;
+    // `Box<RuntimeCall>` is encoded inline, so the inner call can be appended as is.
+    let mut data = vec![pallet.index, sudo.index];
+    data.extend_from_slice(call_data);
+    Ok(data)
+}
+
+/// Wraps an encoded call in `Sudo.sudo_unchecked_weight` with a zero weight, for calls such as
+/// `System.set_code` whose declared weight fills the whole block.
+pub fn sudo_unchecked_weight_call(metadata: &Metadata, call_data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let pallet = metadata.pallet("Sudo")?;
+    let sudo = metadata
+        .variant(pallet.calls, "sudo_unchecked_weight")
+        .ok_or("Call Sudo.sudo_unchecked_weight not found in runtime metadata")?;
+    let weight = sudo
+        .fields
+        .iter()
+        .find(|field| field.name.as_deref() == Some("weight"))
+        .ok_or("Unexpected Sudo.sudo_unchecked_weight signature")?;
+    // `Weight { ref_time, proof_size }`, or a plain `u64` on old runtimes
+    let zero = match metadata.types.resolve(weight.ty.id).map(|ty| &ty.type_def) {
+        Some(TypeDef::Composite(def)) => def.fields.iter().filter_map(|field| Some((field.name.clone()?, json!(0)))).collect(),
+        _ => json!(0),
+    };
+
+    let mut data = vec![pallet.index, sudo.index];
+    data.extend_from_slice(call_data);
+    metadata.encode_value(weight.ty.id, &zero, &mut data)?;
+    Ok(data)
+}
+
+/// Signs a call and submits it, printing its status updates and events.
+pub fn submit(client: &mut RpcClient, metadata: &Metadata, pair: &Pair, call_data: &[u8], finalized: bool) -> Result<TxOutcome, Box<dyn Error>> {
+    let extrinsic = sign_extrinsic(client, metadata, pair, call_data)?;
+    submit_and_watch(client, metadata, &extrinsic, finalized)
+}
+
+/// Builds a signed extrinsic (format version 4) with an immortal era and no tip.
+pub fn sign_extrinsic(client: &mut RpcClient, metadata: &Metadata, pair: &Pair, call_data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let account = pair.account_id();
+    let address = keys::ss58_encode(&account, metadata.ss58_prefix)?;
+
+    let nonce = client
+        .request("system_accountNextIndex", json!([address]))?
+        .as_u64()
+        .ok_or("Unexpected system_accountNextIndex response")?;
+    let version = client.request("state_getRuntimeVersion", json!([]))?;
+    let genesis_hash = client.request("chain_getBlockHash", json!([0]))?;
+
+    let mut extra = Vec::new();
+    let mut additional = Vec::new();
+    for extension in &metadata.extrinsic.signed_extensions {
+        let (extra_value, additional_value) = extension_values(extension, nonce, &version, &genesis_hash);
+        metadata
+            .encode_value(extension.ty, &extra_value, &mut extra)
+            .and_then(|_| metadata.encode_value(extension.additional_signed, &additional_value, &mut additional))
+            .map_err(|e| format!("Unsupported signed extension {}: {}", extension.identifier, e))?;
+    }
+
+    let mut payload = call_data.to_vec();
+    payload.extend_from_slice(&extra);
+    payload.extend_from_slice(&additional);
+    let signature = if payload.len() > 256 {
+        pair.sign(&hashing::blake2_256(&payload))
+    } else {
+        pair.sign(&payload)
+    };
+
+    let mut body = vec![0b1000_0000 | metadata.extrinsic.version];
+    metadata.encode_value(metadata.extrinsic.address_ty, &json!(rpc::hex_encode(&account)), &mut body)?;
+    metadata.encode_value(
+        metadata.extrinsic.signature_ty,
+        &json!({ pair.signature_variant(): rpc::hex_encode(&signature) }),
+        &mut body,
+    )?;
+    body.extend_from_slice(&extra);
+    body.extend_from_slice(call_data);
+
+    let mut extrinsic = Compact(body.len() as u32).encode();
+    extrinsic.extend_from_slice(&body);
+    Ok(extrinsic)
+}
+
+/// Values for the explicit (`extra`) and implicit (`additional_signed`) data of a signed extension.
+fn extension_values(extension: &SignedExtension, nonce: u64, version: &Value, genesis_hash: &Value) -> (Value, Value) {
+    match extension.identifier.as_str() {
+        "CheckSpecVersion" => (Value::Null, version["specVersion"].clone()),
+        "CheckTxVersion" => (Value::Null, version["transactionVersion"].clone()),
+        "CheckGenesis" => (Value::Null, genesis_hash.clone()),
+        "CheckMortality" | "CheckEra" => (json!("Immortal"), genesis_hash.clone()),
+        "CheckNonce" => (json!(nonce), Value::Null),
+        "ChargeTransactionPayment" => (json!(0), Value::Null),
+        "ChargeAssetTxPayment" => (json!({ "tip": 0, "asset_id": null }), Value::Null),
+        "CheckMetadataHash" => (json!({ "mode": "Disabled" }), Value::Null),
+        _ => (Value::Null, Value::Null),
+    }
+}
+
+/// Submits an extrinsic and waits until it is in a block (or finalized), then prints its events.
+/// Returns an error when the extrinsic is dropped or its dispatch failed.
+pub fn submit_and_watch(client: &mut RpcClient, metadata: &Metadata, extrinsic: &[u8], finalized: bool) -> Result<TxOutcome, Box<dyn Error>> {
+    let extrinsic_hex = rpc::hex_encode(extrinsic);
+    let subscription = client.subscribe("author_submitAndWatchExtrinsic", json!([extrinsic_hex]))?;
+    output::progress(format_args!("Submitted extrinsic {}", rpc::hex_encode(&hashing::blake2_256(extrinsic))));
+
+    let mut outcome = None;
+    loop {
+        let status = client.next_notification(&subscription)?;
+        match &status {
+            Value::String(state) if state == "ready" || state == "future" => output::progress(format_args!("  status: {}", state)),
+            Value::Object(map) if map.contains_key("broadcast") => output::progress("  status: broadcast"),
+            Value::Object(map) if map.contains_key("inBlock") => {
+                let block_hash = map["inBlock"].as_str().unwrap_or_default().to_string();
+                output::progress(format_args!("  status: in block {} (#{})", block_hash, block_number(client, &block_hash)?));
+                let result = extrinsic_events(client, metadata, &block_hash, &extrinsic_hex)?;
+                outcome = Some(result);
+                if !finalized {
+                    break;
+                }
+            }
+            Value::Object(map) if map.contains_key("finalized") => {
+                let block_hash = map["finalized"].as_str().unwrap_or_default().to_string();
+                output::progress(format_args!("  status: finalized in {}", block_hash));
+                if outcome.is_none() {
+                    outcome = Some(extrinsic_events(client, metadata, &block_hash, &extrinsic_hex)?);
+                }
+                break;
+            }
+            Value::Object(map) if map.contains_key("retracted") => output::progress(format_args!("  status: retracted from {}", map["retracted"])),
+            other => return Err(format!("Extrinsic was not included: {}", other).into()),
+        }
+    }
+
+    let outcome = outcome.ok_or("Extrinsic was not included")?;
+    report(metadata, &outcome)?;
+    Ok(outcome)
+}
+
+fn extrinsic_events(client: &mut RpcClient, metadata: &Metadata, block_hash: &str, extrinsic_hex: &str) -> Result<TxOutcome, Box<dyn Error>> {
+    let block = client.request("chain_getBlock", json!([block_hash]))?;
+    let index = block
+        .pointer("/block/extrinsics")
+        .and_then(Value::as_array)
+        .and_then(|extrinsics| extrinsics.iter().position(|item| item.as_str() == Some(extrinsic_hex)))
+        .ok_or("Extrinsic not found in block")? as u64;
+
+    let events = events::fetch_events(client, metadata, block_hash)?
+        .into_iter()
+        .filter(|event| event.extrinsic_index == Some(index))
+        .collect();
+    Ok(TxOutcome { block_hash: block_hash.to_string(), events })
+}
+
+/// Prints the events of an extrinsic, unless they are printed as JSON, and turns a failed dispatch into an error.
+fn report(metadata: &Metadata, outcome: &TxOutcome) -> Result<(), Box<dyn Error>> {
+    let text = !output::json();
+    if text {
+        println!("\nEvents:");
+        for event in &outcome.events {
+            println!("  {}", events::format_event(event));
+        }
+    }
+
+    for event in &outcome.events {
+        if event.is("System", "ExtrinsicFailed") {
+            let error = events::format_dispatch_error(metadata, &event.fields["dispatch_error"]);
+            return Err(format!("Dispatch error: {}", error).into());
+        }
+        if event.is("Sudo", "Sudid") {
+            if let Some(error) = event.fields.pointer("/sudo_result/Err") {
+                return Err(format!("Sudo dispatch error: {}", events::format_dispatch_error(metadata, error)).into());
+            }
+        }
+    }
+    if text {
+        println!("\n✓ Extrinsic succeeded in block {}", outcome.block_hash);
+    }
+    Ok(())
+}
+
+pub fn block_number(client: &mut RpcClient, block_hash: &str) -> Result<u64, Box<dyn Error>> {
+    watch::header_number(&client.request("chain_getHeader", json!([block_hash]))?)
+}
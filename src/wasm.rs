@@ -0,0 +1,248 @@
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use parity_scale_codec::{Decode, Encode};
+use wasmi::{Caller, Engine, ExternType, Linker, Memory, Module, Store, Val};
+
+/// Prefix of zstd compressed runtime blobs (`*.compact.compressed.wasm`).
+pub const ZSTD_PREFIX: [u8; 8] = [82, 188, 83, 118, 70, 219, 142, 5];
+
+/// Upper bound for a decompressed runtime, mirroring the limit used by the node.
+const CODE_BOMB_LIMIT: u64 = 50 * 1024 * 1024;
+
+const WASM_PAGE_SIZE: u32 = 65536;
+
+/// Reads a runtime wasm file, decompressing it if it is zstd compressed.
+pub fn read_runtime(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    decompress(&bytes)
+}
+
+pub fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(&ZSTD_PREFIX)
+}
+
+pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if !is_compressed(bytes) {
+        return Ok(bytes.to_vec());
+    }
+
+    let mut compressed = &bytes[ZSTD_PREFIX.len()..];
+    let decoder = ruzstd::StreamingDecoder::new(&mut compressed)
+        .map_err(|e| format!("Failed to decompress runtime: {}", e))?;
+    let mut code = Vec::new();
+    decoder
+        .take(CODE_BOMB_LIMIT + 1)
+        .read_to_end(&mut code)
+        .map_err(|e| format!("Failed to decompress runtime: {}", e))?;
+    if code.len() as u64 > CODE_BOMB_LIMIT {
+        return Err("Decompressed runtime exceeds the size limit".into());
+    }
+    Ok(code)
+}
+
+/// Extracts the SCALE encoded `RuntimeMetadataPrefixed` from a runtime,
+/// preferring metadata V15 and falling back to the legacy `Metadata_metadata` call.
+pub fn metadata_bytes(code: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if let Ok(output) = call_runtime_api(code, "Metadata_metadata_at_version", &15u32.encode()) {
+        if let Ok(Some(metadata)) = Option::<Vec<u8>>::decode(&mut &output[..]) {
+            return Ok(metadata);
+        }
+    }
+
+    let output = call_runtime_api(code, "Metadata_metadata", &[])?;
+    let metadata = Vec::<u8>::decode(&mut &output[..])
+        .map_err(|e| format!("Failed to decode runtime metadata: {}", e))?;
+    Ok(metadata)
+}
+
+/// A runtime API as `blake2_64(name)` and version.
+pub type RuntimeApi = ([u8; 8], u32);
+
+/// Leading fields of `sp_version::RuntimeVersion`.
+#[derive(Decode)]
+pub struct RuntimeVersion {
+    pub spec_name: String,
+    pub impl_name: String,
+    pub authoring_version: u32,
+    pub spec_version: u32,
+    pub impl_version: u32,
+    pub apis: Vec<RuntimeApi>,
+    pub transaction_version: u32,
+}
+
+/// Reads the runtime version from the `runtime_version` and `runtime_apis` custom sections,
+/// falling back to calling `Core_version` for runtimes built without them.
+pub fn runtime_version(code: &[u8]) -> Result<RuntimeVersion, Box<dyn Error>> {
+    let section = match custom_section(code, "runtime_version")? {
+        Some(section) => section,
+        None => return decode_runtime_version(&call_runtime_api(code, "Core_version", &[])?),
+    };
+    let mut version = decode_runtime_version(section)?;
+    // The APIs are not known when the version section is built, they are embedded separately.
+    if let Some(apis) = custom_section(code, "runtime_apis")? {
+        version.apis = decode_runtime_apis(apis)?;
+    }
+    Ok(version)
+}
+
+fn decode_runtime_version(encoded: &[u8]) -> Result<RuntimeVersion, Box<dyn Error>> {
+    RuntimeVersion::decode(&mut &encoded[..]).map_err(|e| format!("Failed to decode runtime version: {}", e).into())
+}
+
+/// Decodes the `runtime_apis` section: 12 byte entries of an API id and its little endian `u32` version,
+/// without a length prefix.
+pub fn decode_runtime_apis(section: &[u8]) -> Result<Vec<RuntimeApi>, Box<dyn Error>> {
+    if !section.len().is_multiple_of(12) {
+        return Err(format!("Invalid runtime_apis section of {} bytes", section.len()).into());
+    }
+    Ok(section
+        .chunks_exact(12)
+        .map(|entry| {
+            let (id, version) = entry.split_at(8);
+            (id.try_into().unwrap(), u32::from_le_bytes(version.try_into().unwrap()))
+        })
+        .collect())
+}
+
+/// Returns the contents of the first custom section with the given name.
+pub fn custom_section<'a>(code: &'a [u8], name: &str) -> Result<Option<&'a [u8]>, Box<dyn Error>> {
+    if !code.starts_with(b"\0asm") || code.len() < 8 {
+        return Err("Not a wasm module".into());
+    }
+
+    let mut rest = &code[8..];
+    while !rest.is_empty() {
+        let id = rest[0];
+        rest = &rest[1..];
+        let size = read_leb128(&mut rest)? as usize;
+        if size > rest.len() {
+            return Err("Truncated wasm section".into());
+        }
+        let (mut section, next) = rest.split_at(size);
+        rest = next;
+
+        if id == 0 {
+            let name_len = read_leb128(&mut section)? as usize;
+            if name_len <= section.len() && &section[..name_len] == name.as_bytes() {
+                return Ok(Some(&section[name_len..]));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn read_leb128(input: &mut &[u8]) -> Result<u32, Box<dyn Error>> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let (&byte, rest) = input.split_first().ok_or("Truncated wasm module")?;
+        *input = rest;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Invalid LEB128 integer in wasm module".into())
+}
+
+struct HostState {
+    memory: Option<Memory>,
+    heap_next: u32,
+}
+
+/// Calls a runtime API function that only needs the allocator from the host.
+/// Every other host function is stubbed and fails if the runtime calls it.
+pub fn call_runtime_api(code: &[u8], method: &str, input: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let engine = Engine::default();
+    let module = Module::new(&engine, code).map_err(|e| format!("Invalid runtime wasm: {}", e))?;
+    let mut store = Store::new(&engine, HostState { memory: None, heap_next: 0 });
+    let mut linker = Linker::<HostState>::new(&engine);
+
+    let mut imported_memory = None;
+    for import in module.imports() {
+        match import.ty() {
+            ExternType::Func(func_type) => {
+                let name = import.name().to_string();
+                linker.func_new(import.module(), import.name(), func_type.clone(), move |caller, params, results| {
+                    host_function(caller, &name, params, results)
+                })?;
+            }
+            ExternType::Memory(memory_type) => {
+                let memory = Memory::new(&mut store, *memory_type).map_err(|e| e.to_string())?;
+                linker.define(import.module(), import.name(), memory)?;
+                imported_memory = Some(memory);
+            }
+            _ => return Err(format!("Unsupported runtime import: {}", import.name()).into()),
+        }
+    }
+
+    let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+    let memory = match imported_memory.or_else(|| instance.get_memory(&store, "memory")) {
+        Some(memory) => memory,
+        None => return Err("Runtime does not define a memory".into()),
+    };
+    let heap_base = match instance.get_global(&store, "__heap_base").map(|global| global.get(&store)) {
+        Some(Val::I32(base)) => base as u32,
+        _ => return Err("Runtime does not export __heap_base".into()),
+    };
+    store.data_mut().memory = Some(memory);
+    store.data_mut().heap_next = heap_base;
+
+    let input_ptr = allocate(&mut store, memory, input.len() as u32)?;
+    memory.write(&mut store, input_ptr as usize, input).map_err(|e| e.to_string())?;
+
+    let func = instance
+        .get_typed_func::<(i32, i32), i64>(&store, method)
+        .map_err(|_| format!("Runtime does not export {}", method))?;
+    let packed = func
+        .call(&mut store, (input_ptr as i32, input.len() as i32))
+        .map_err(|e| format!("{} failed: {}", method, e))? as u64;
+
+    let output_ptr = (packed & 0xffff_ffff) as usize;
+    let output_len = (packed >> 32) as usize;
+    let mut output = vec![0u8; output_len];
+    memory.read(&store, output_ptr, &mut output).map_err(|e| e.to_string())?;
+    Ok(output)
+}
+
+fn host_function(mut caller: Caller<'_, HostState>, name: &str, params: &[Val], results: &mut [Val]) -> Result<(), wasmi::Error> {
+    match name {
+        "ext_allocator_malloc_version_1" => {
+            let size = match params.first() {
+                Some(Val::I32(size)) => *size as u32,
+                _ => return Err(wasmi::Error::new("Invalid allocation size")),
+            };
+            let memory = caller.data().memory.ok_or_else(|| wasmi::Error::new("Memory is not initialised"))?;
+            let ptr = allocate(&mut caller, memory, size).map_err(|e| wasmi::Error::new(e.to_string()))?;
+            results[0] = Val::I32(ptr as i32);
+            Ok(())
+        }
+        "ext_allocator_free_version_1" | "ext_logging_log_version_1" | "ext_misc_print_utf8_version_1"
+        | "ext_misc_print_hex_version_1" | "ext_misc_print_num_version_1" => Ok(()),
+        "ext_logging_max_level_version_1" => {
+            results[0] = Val::I32(0);
+            Ok(())
+        }
+        _ => Err(wasmi::Error::new(format!("Host function {} is not available", name))),
+    }
+}
+
+/// Bump allocator on top of the runtime heap; nothing is ever freed.
+fn allocate(mut ctx: impl wasmi::AsContextMut<Data = HostState>, memory: Memory, size: u32) -> Result<u32, Box<dyn Error>> {
+    let ptr = ctx.as_context().data().heap_next;
+    let end = size
+        .checked_add(7)
+        .and_then(|size| ptr.checked_add(size & !7))
+        .ok_or("Runtime heap exhausted")?;
+
+    let current = u32::from(memory.current_pages(&ctx)) as u64 * WASM_PAGE_SIZE as u64;
+    if end as u64 > current {
+        let missing = (end as u64 - current).div_ceil(WASM_PAGE_SIZE as u64) as u32;
+        let pages = wasmi::core::Pages::new(missing).ok_or("Runtime heap exhausted")?;
+        memory.grow(&mut ctx, pages).map_err(|e| e.to_string())?;
+    }
+
+    ctx.as_context_mut().data_mut().heap_next = end;
+    Ok(ptr)
+}
@@ -0,0 +1,117 @@
+use std::error::Error;
+use parity_scale_codec::{Compact, Encode};
+use serde_json::{json, Value};
+use crate::events::{self, EventRecord};
+use crate::hashing;
+use crate::metadata;
+use crate::rpc::{self, RpcClient};
+use crate::tx;
+
+/// What `dot watch` streams from the node.
+pub enum WatchMode {
+    Blocks,
+    Finalized,
+    Events,
+}
+
+/// Streams new blocks, finalized blocks or events until the connection is closed.
+pub fn run(rpc_url: &str, mode: WatchMode, pallet: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut client = RpcClient::connect(rpc_url)?;
+    match mode {
+        WatchMode::Blocks => watch_heads(&mut client, "chain_subscribeNewHeads"),
+        WatchMode::Finalized => watch_heads(&mut client, "chain_subscribeFinalizedHeads"),
+        WatchMode::Events => watch_events(&mut client, pallet),
+    }
+}
+
+fn watch_heads(client: &mut RpcClient, method: &str) -> Result<(), Box<dyn Error>> {
+    let subscription = client.subscribe(method, json!([]))?;
+    println!("Watching blocks...");
+
+    loop {
+        let header = client.next_notification(&subscription)?;
+        let number = header_number(&header)?;
+        let hash = header_hash(&header)?;
+        println!(
+            "#{} {} (parent {})",
+            number,
+            rpc::hex_encode(&hash),
+            header["parentHash"].as_str().unwrap_or_default()
+        );
+    }
+}
+
+fn watch_events(client: &mut RpcClient, pallet: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut metadata = metadata::from_rpc(client)?;
+    let pallet = match pallet {
+        Some(name) => Some(metadata.pallet(name)?.name.clone()),
+        None => None,
+    };
+
+    let key = rpc::hex_encode(&events::system_events_key());
+    let subscription = client.subscribe("state_subscribeStorage", json!([[key]]))?;
+    println!("Watching events{}...", pallet.as_ref().map(|name| format!(" of {}", name)).unwrap_or_default());
+
+    loop {
+        let change = client.next_notification(&subscription)?;
+        let block_hash = change["block"].as_str().unwrap_or_default().to_string();
+        let data = match change.pointer("/changes/0/1").and_then(Value::as_str) {
+            Some(data) => rpc::hex_decode(data)?,
+            None => continue,
+        };
+
+        // Events of a block that enacted a runtime upgrade are encoded with the new metadata.
+        let records = match events::decode_events(&metadata, &data) {
+            Ok(records) => records,
+            Err(_) => {
+                metadata = metadata::from_rpc(client)?;
+                match events::decode_events(&metadata, &data) {
+                    Ok(records) => records,
+                    Err(e) => {
+                        eprintln!("Failed to decode events of block {}: {}", block_hash, e);
+                        continue;
+                    }
+                }
+            }
+        };
+
+        let number = tx::block_number(client, &block_hash)?;
+        for event in filter_events(&records, pallet.as_deref()) {
+            let phase = match event.extrinsic_index {
+                Some(index) => format!("extrinsic {}", index),
+                None => "block".to_string(),
+            };
+            println!("#{} [{}] {}", number, phase, events::format_event(event));
+        }
+    }
+}
+
+/// Parses the hex encoded `number` field of a block header.
+pub fn header_number(header: &Value) -> Result<u64, Box<dyn Error>> {
+    let number = header["number"].as_str().ok_or("Unexpected block header")?;
+    Ok(u64::from_str_radix(number.trim_start_matches("0x"), 16)?)
+}
+
+/// Hashes a block header notification the way the node does: blake2_256 of the SCALE encoded header.
+pub fn header_hash(header: &Value) -> Result<[u8; 32], Box<dyn Error>> {
+    let field = |name: &str| -> Result<Vec<u8>, Box<dyn Error>> {
+        let value = header[name].as_str().ok_or("Unexpected block header")?;
+        rpc::hex_decode(value)
+    };
+    let logs = header.pointer("/digest/logs").and_then(Value::as_array).ok_or("Unexpected block header")?;
+
+    let mut encoded = field("parentHash")?;
+    Compact(header_number(header)?).encode_to(&mut encoded);
+    encoded.extend(field("stateRoot")?);
+    encoded.extend(field("extrinsicsRoot")?);
+    Compact(logs.len() as u32).encode_to(&mut encoded);
+    for log in logs {
+        encoded.extend(rpc::hex_decode(log.as_str().ok_or("Unexpected digest log")?)?);
+    }
+    Ok(hashing::blake2_256(&encoded))
+}
+
+/// The events emitted by `pallet`, or all of them.
+pub fn filter_events<'a>(records: &'a [EventRecord], pallet: Option<&'a str>) -> impl Iterator<Item = &'a EventRecord> {
+    records.iter().filter(move |event| pallet.is_none_or(|name| event.pallet == name))
+}